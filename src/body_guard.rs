@@ -0,0 +1,58 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::{Body, Frame, SizeHint};
+
+/// Body that keeps `guard` alive until the body ends, fails or is dropped. The
+/// pool uses it to keep a connection leased while its body is read, redirects
+/// to keep alive the client created for one hop: dropping that client would
+/// close the connection its body is still being read from.
+struct GuardedBody<TGuard: Send + Sync + 'static> {
+    inner: BoxBody<Bytes, String>,
+    guard: Option<TGuard>,
+}
+
+impl<TGuard: Send + Sync + Unpin + 'static> Body for GuardedBody<TGuard> {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        let result = Pin::new(&mut this.inner).poll_frame(cx);
+
+        if matches!(result, Poll::Ready(None) | Poll::Ready(Some(Err(_)))) {
+            this.guard = None;
+        }
+
+        result
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+pub(crate) fn hold_until_body_ends<TGuard: Send + Sync + Unpin + 'static>(
+    response: crate::HyperResponse,
+    guard: TGuard,
+) -> crate::HyperResponse {
+    let (parts, body) = response.into_parts();
+
+    let body = GuardedBody {
+        inner: body,
+        guard: Some(guard),
+    };
+
+    crate::HyperResponse::from_parts(parts, body.boxed())
+}
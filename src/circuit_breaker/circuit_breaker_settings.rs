@@ -0,0 +1,18 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy)]
+pub struct CircuitBreakerSettings {
+    /// Consecutive failed requests to one endpoint that open its circuit
+    pub failure_threshold: usize,
+    /// How long an open circuit fails requests before letting one probe through
+    pub open_timeout: Duration,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_timeout: Duration::from_secs(30),
+        }
+    }
+}
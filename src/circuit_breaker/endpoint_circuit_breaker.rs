@@ -0,0 +1,373 @@
+use std::{collections::HashMap, future::Future, time::Instant};
+
+use crate::MyHttpClientError;
+
+use super::CircuitBreakerSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitBreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+enum EndpointState {
+    Closed { failures: usize },
+    Open { until: Instant },
+    HalfOpen { probing: bool },
+}
+
+impl EndpointState {
+    fn as_state(&self) -> CircuitBreakerState {
+        match self {
+            EndpointState::Closed { .. } => CircuitBreakerState::Closed,
+            EndpointState::Open { .. } => CircuitBreakerState::Open,
+            EndpointState::HalfOpen { .. } => CircuitBreakerState::HalfOpen,
+        }
+    }
+}
+
+/// Fails requests to an endpoint fast while it is down instead of letting each of
+/// them wait for the connect timeout.
+///
+/// Endpoints are tracked by host and port, so one breaker can be shared by all
+/// the clients of a service. After `failure_threshold` consecutive requests
+/// failed to connect, lost their connection or timed out, the circuit of the endpoint opens and
+/// requests fail with [`MyHttpClientError::CircuitBreakerOpen`]. Once
+/// `open_timeout` passes, the circuit is half-open: one request goes through as
+/// a probe, and its outcome closes or reopens the circuit. Any response,
+/// whatever its status, counts as a success, and so do errors that are not the
+/// endpoint's fault, such as a failing request body stream.
+pub struct CircuitBreaker {
+    settings: CircuitBreakerSettings,
+    endpoints: parking_lot::Mutex<HashMap<String, EndpointState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(settings: CircuitBreakerSettings) -> Self {
+        Self {
+            settings,
+            endpoints: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get_state(&self, host_port: &str) -> CircuitBreakerState {
+        match self.endpoints.lock().get(host_port) {
+            Some(EndpointState::Open { until }) if *until <= Instant::now() => {
+                CircuitBreakerState::HalfOpen
+            }
+            Some(state) => state.as_state(),
+            None => CircuitBreakerState::Closed,
+        }
+    }
+
+    /// Runs `request` to `host_port` if the circuit lets it through and records
+    /// its outcome. `state_changed` is called on every transition of the circuit,
+    /// with no lock held, so it may use the breaker.
+    pub(crate) async fn execute<TResult>(
+        &self,
+        host_port: &str,
+        request: impl Future<Output = Result<TResult, MyHttpClientError>>,
+        state_changed: impl Fn(CircuitBreakerState),
+    ) -> Result<TResult, MyHttpClientError> {
+        let (probe, transition) = self.acquire(host_port)?;
+
+        if let Some(state) = transition {
+            state_changed(state);
+        }
+
+        let mut probe_guard = ProbeGuard {
+            circuit_breaker: self,
+            host_port,
+            active: probe,
+        };
+
+        let result = request.await;
+        probe_guard.active = false;
+
+        let success = match &result {
+            Ok(_) => true,
+            Err(err) => !is_failure(err),
+        };
+
+        if let Some(state) = self.report(host_port, success) {
+            state_changed(state);
+        }
+
+        result
+    }
+
+    /// Returns whether the request is the probe of a half-open circuit, and the
+    /// transition of the circuit letting it through, if any.
+    fn acquire(
+        &self,
+        host_port: &str,
+    ) -> Result<(bool, Option<CircuitBreakerState>), MyHttpClientError> {
+        let mut endpoints = self.endpoints.lock();
+
+        let state = match endpoints.get_mut(host_port) {
+            Some(state) => state,
+            None => return Ok((false, None)),
+        };
+
+        match state {
+            EndpointState::Closed { .. } => Ok((false, None)),
+            EndpointState::Open { until } => {
+                if *until > Instant::now() {
+                    return Err(MyHttpClientError::CircuitBreakerOpen(host_port.to_string()));
+                }
+
+                *state = EndpointState::HalfOpen { probing: true };
+                Ok((true, Some(CircuitBreakerState::HalfOpen)))
+            }
+            EndpointState::HalfOpen { probing } => {
+                if *probing {
+                    return Err(MyHttpClientError::CircuitBreakerOpen(host_port.to_string()));
+                }
+
+                *probing = true;
+                Ok((true, None))
+            }
+        }
+    }
+
+    fn report(&self, host_port: &str, success: bool) -> Option<CircuitBreakerState> {
+        let mut endpoints = self.endpoints.lock();
+
+        if success {
+            let state = endpoints.remove(host_port)?;
+            return match state {
+                EndpointState::Closed { .. } => None,
+                _ => Some(CircuitBreakerState::Closed),
+            };
+        }
+
+        let state = endpoints
+            .entry(host_port.to_string())
+            .or_insert(EndpointState::Closed { failures: 0 });
+
+        match state {
+            EndpointState::Closed { failures } => {
+                *failures += 1;
+                if *failures < self.settings.failure_threshold {
+                    return None;
+                }
+            }
+            // A request sent before the circuit opened
+            EndpointState::Open { .. } => return None,
+            EndpointState::HalfOpen { .. } => {}
+        }
+
+        *state = EndpointState::Open {
+            until: Instant::now() + self.settings.open_timeout,
+        };
+
+        Some(CircuitBreakerState::Open)
+    }
+}
+
+/// Lets the next request probe a half-open circuit if the probe was dropped
+/// before it finished.
+struct ProbeGuard<'s> {
+    circuit_breaker: &'s CircuitBreaker,
+    host_port: &'s str,
+    active: bool,
+}
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+
+        let mut endpoints = self.circuit_breaker.endpoints.lock();
+        if let Some(EndpointState::HalfOpen { probing }) = endpoints.get_mut(self.host_port) {
+            *probing = false;
+        }
+    }
+}
+
+/// Only errors saying the endpoint is unreachable or unresponsive count.
+/// `CanNotExecuteRequest` also covers failures on the caller's side, so it does
+/// not.
+fn is_failure(err: &MyHttpClientError) -> bool {
+    matches!(
+        err,
+        MyHttpClientError::CanNotConnectToRemoteHost(_)
+            | MyHttpClientError::Disconnected
+            | MyHttpClientError::RequestNotSent
+            | MyHttpClientError::RequestTimeout(_)
+            | MyHttpClientError::ReconnectsExhausted(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use parking_lot::Mutex;
+
+    use super::{CircuitBreaker, CircuitBreakerState};
+    use crate::{circuit_breaker::CircuitBreakerSettings, MyHttpClientError};
+
+    const HOST_PORT: &str = "127.0.0.1:8080";
+
+    async fn execute(
+        circuit_breaker: &CircuitBreaker,
+        result: Result<(), MyHttpClientError>,
+        transitions: &Mutex<Vec<CircuitBreakerState>>,
+    ) -> Result<(), MyHttpClientError> {
+        circuit_breaker
+            .execute(HOST_PORT, async { result }, |state| {
+                transitions.lock().push(state)
+            })
+            .await
+    }
+
+    fn refused() -> Result<(), MyHttpClientError> {
+        Err(MyHttpClientError::CanNotConnectToRemoteHost(
+            "refused".to_string(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn opens_after_threshold_and_closes_after_successful_probe() {
+        let circuit_breaker = CircuitBreaker::new(CircuitBreakerSettings {
+            failure_threshold: 2,
+            open_timeout: Duration::from_millis(50),
+        });
+        let transitions = Mutex::new(Vec::new());
+
+        assert!(execute(&circuit_breaker, refused(), &transitions)
+            .await
+            .is_err());
+        assert_eq!(
+            circuit_breaker.get_state(HOST_PORT),
+            CircuitBreakerState::Closed
+        );
+
+        assert!(execute(&circuit_breaker, refused(), &transitions)
+            .await
+            .is_err());
+        assert_eq!(
+            circuit_breaker.get_state(HOST_PORT),
+            CircuitBreakerState::Open
+        );
+
+        let err = execute(&circuit_breaker, Ok(()), &transitions)
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, MyHttpClientError::CircuitBreakerOpen(_)));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        execute(&circuit_breaker, Ok(()), &transitions)
+            .await
+            .unwrap();
+        assert_eq!(
+            circuit_breaker.get_state(HOST_PORT),
+            CircuitBreakerState::Closed
+        );
+
+        assert_eq!(
+            transitions.lock().as_slice(),
+            &[
+                CircuitBreakerState::Open,
+                CircuitBreakerState::HalfOpen,
+                CircuitBreakerState::Closed,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn state_changed_can_use_the_breaker() {
+        let circuit_breaker = CircuitBreaker::new(CircuitBreakerSettings {
+            failure_threshold: 1,
+            open_timeout: Duration::from_millis(20),
+        });
+        let observed = Mutex::new(Vec::new());
+        let state_changed = |_| observed.lock().push(circuit_breaker.get_state(HOST_PORT));
+
+        let _ = circuit_breaker
+            .execute(HOST_PORT, async { refused() }, state_changed)
+            .await;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        circuit_breaker
+            .execute(HOST_PORT, async { Ok(()) }, state_changed)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            observed.lock().as_slice(),
+            &[
+                CircuitBreakerState::Open,
+                CircuitBreakerState::HalfOpen,
+                CircuitBreakerState::Closed,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn caller_side_errors_do_not_open_the_circuit() {
+        let circuit_breaker = CircuitBreaker::new(CircuitBreakerSettings {
+            failure_threshold: 1,
+            open_timeout: Duration::from_secs(60),
+        });
+        let transitions = Mutex::new(Vec::new());
+
+        let body_failed = Err(MyHttpClientError::CanNotExecuteRequest(
+            "Body stream failed".to_string(),
+        ));
+        assert!(execute(&circuit_breaker, body_failed, &transitions)
+            .await
+            .is_err());
+
+        assert_eq!(
+            circuit_breaker.get_state(HOST_PORT),
+            CircuitBreakerState::Closed
+        );
+        assert!(transitions.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_probe_reopens_and_dropped_probe_is_released() {
+        let circuit_breaker = CircuitBreaker::new(CircuitBreakerSettings {
+            failure_threshold: 1,
+            open_timeout: Duration::from_millis(20),
+        });
+        let transitions = Mutex::new(Vec::new());
+
+        assert!(execute(&circuit_breaker, refused(), &transitions)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        assert!(execute(&circuit_breaker, refused(), &transitions)
+            .await
+            .is_err());
+        assert_eq!(
+            circuit_breaker.get_state(HOST_PORT),
+            CircuitBreakerState::Open
+        );
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        // A probe that never finishes blocks the others until it is dropped
+        let probe = circuit_breaker.execute(
+            HOST_PORT,
+            std::future::pending::<Result<(), MyHttpClientError>>(),
+            |_| {},
+        );
+        let timed_out = tokio::time::timeout(Duration::from_millis(10), probe).await;
+        assert!(timed_out.is_err());
+
+        execute(&circuit_breaker, Ok(()), &transitions)
+            .await
+            .unwrap();
+        assert_eq!(
+            circuit_breaker.get_state(HOST_PORT),
+            CircuitBreakerState::Closed
+        );
+    }
+}
@@ -0,0 +1,4 @@
+mod circuit_breaker_settings;
+pub use circuit_breaker_settings::*;
+mod endpoint_circuit_breaker;
+pub use endpoint_circuit_breaker::*;
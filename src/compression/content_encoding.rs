@@ -0,0 +1,78 @@
+/// Content codings a response body can be decoded from. Each one is supported
+/// only when the matching cargo feature (`gzip`, `deflate`, `brotli`, `zstd`)
+/// is enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl ContentEncoding {
+    pub const ALL: [ContentEncoding; 4] = [
+        ContentEncoding::Gzip,
+        ContentEncoding::Deflate,
+        ContentEncoding::Brotli,
+        ContentEncoding::Zstd,
+    ];
+
+    pub fn from_header_value(value: &str) -> Option<Self> {
+        let value = value.trim();
+
+        if value.eq_ignore_ascii_case("gzip") || value.eq_ignore_ascii_case("x-gzip") {
+            return Some(Self::Gzip);
+        }
+
+        if value.eq_ignore_ascii_case("deflate") {
+            return Some(Self::Deflate);
+        }
+
+        if value.eq_ignore_ascii_case("br") {
+            return Some(Self::Brotli);
+        }
+
+        if value.eq_ignore_ascii_case("zstd") {
+            return Some(Self::Zstd);
+        }
+
+        None
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        match self {
+            Self::Gzip => cfg!(feature = "gzip"),
+            Self::Deflate => cfg!(feature = "deflate"),
+            Self::Brotli => cfg!(feature = "brotli"),
+            Self::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// `Accept-Encoding` value listing every supported coding, or `None` when
+    /// the crate is built without any codec feature.
+    pub fn get_accept_encoding() -> Option<String> {
+        let mut result = String::new();
+
+        for encoding in Self::ALL.iter().filter(|itm| itm.is_supported()) {
+            if !result.is_empty() {
+                result.push_str(", ");
+            }
+            result.push_str(encoding.as_str());
+        }
+
+        if result.is_empty() {
+            return None;
+        }
+
+        Some(result)
+    }
+}
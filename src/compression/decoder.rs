@@ -0,0 +1,167 @@
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+use std::io::Write;
+
+use super::ContentEncoding;
+
+/// Incremental decoder: compressed input is written in as it arrives and the
+/// decoded output produced so far is taken out after every write.
+pub trait Decoder: Send + Sync {
+    fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>>;
+    fn finish(self: Box<Self>) -> std::io::Result<Vec<u8>>;
+}
+
+/// `max_decoded_size` caps the total output of the decoder over the whole body.
+#[allow(unused_variables)]
+pub fn create_decoder(
+    encoding: ContentEncoding,
+    max_decoded_size: usize,
+) -> Option<Box<dyn Decoder>> {
+    match encoding {
+        #[cfg(feature = "gzip")]
+        ContentEncoding::Gzip => Some(Box::new(flate2::write::GzDecoder::new(DecodedOutput::new(
+            max_decoded_size,
+        )))),
+        #[cfg(feature = "deflate")]
+        ContentEncoding::Deflate => Some(Box::new(flate2::write::ZlibDecoder::new(
+            DecodedOutput::new(max_decoded_size),
+        ))),
+        #[cfg(feature = "brotli")]
+        ContentEncoding::Brotli => Some(Box::new(brotli::DecompressorWriter::new(
+            DecodedOutput::new(max_decoded_size),
+            4096,
+        ))),
+        #[cfg(feature = "zstd")]
+        ContentEncoding::Zstd => Some(Box::new(
+            zstd::stream::write::Decoder::new(DecodedOutput::new(max_decoded_size)).ok()?,
+        )),
+        #[allow(unreachable_patterns)]
+        _ => None,
+    }
+}
+
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+pub struct DecodedOutput {
+    data: Vec<u8>,
+    written: usize,
+    max_size: usize,
+}
+
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+impl DecodedOutput {
+    fn new(max_size: usize) -> Self {
+        Self {
+            data: Vec::new(),
+            written: 0,
+            max_size,
+        }
+    }
+}
+
+/// Fails the write that takes the decoded size over the limit, so a small
+/// compressed body can not expand into an unbounded amount of memory even
+/// within one `decode` call.
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+impl Write for DecodedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.len() > self.max_size - self.written {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("decoded body is larger than {} bytes", self.max_size),
+            ));
+        }
+
+        self.written += buf.len();
+        self.data.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(any(
+    feature = "gzip",
+    feature = "deflate",
+    feature = "brotli",
+    feature = "zstd"
+))]
+fn write_and_take<W: Write>(
+    writer: &mut W,
+    output: impl Fn(&mut W) -> &mut DecodedOutput,
+    data: &[u8],
+) -> std::io::Result<Vec<u8>> {
+    writer.write_all(data)?;
+    writer.flush()?;
+    Ok(std::mem::take(&mut output(writer).data))
+}
+
+#[cfg(feature = "gzip")]
+impl Decoder for flate2::write::GzDecoder<DecodedOutput> {
+    fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        write_and_take(self, |itm| itm.get_mut(), data)
+    }
+
+    fn finish(self: Box<Self>) -> std::io::Result<Vec<u8>> {
+        (*self).finish().map(|output| output.data)
+    }
+}
+
+#[cfg(feature = "deflate")]
+impl Decoder for flate2::write::ZlibDecoder<DecodedOutput> {
+    fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        write_and_take(self, |itm| itm.get_mut(), data)
+    }
+
+    fn finish(self: Box<Self>) -> std::io::Result<Vec<u8>> {
+        (*self).finish().map(|output| output.data)
+    }
+}
+
+#[cfg(feature = "brotli")]
+impl Decoder for brotli::DecompressorWriter<DecodedOutput> {
+    fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        write_and_take(self, |itm| itm.get_mut(), data)
+    }
+
+    fn finish(self: Box<Self>) -> std::io::Result<Vec<u8>> {
+        (*self).into_inner().map(|output| output.data).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Brotli stream is not complete",
+            )
+        })
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Decoder for zstd::stream::write::Decoder<'static, DecodedOutput> {
+    fn decode(&mut self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        write_and_take(self, |itm| itm.get_mut(), data)
+    }
+
+    fn finish(mut self: Box<Self>) -> std::io::Result<Vec<u8>> {
+        self.flush()?;
+        Ok(self.into_inner().data)
+    }
+}
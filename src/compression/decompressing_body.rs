@@ -0,0 +1,421 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http::{header, HeaderMap};
+use http_body_util::{combinators::BoxBody, BodyExt};
+use hyper::body::{Body, Frame};
+
+use super::{decoder::Decoder, ContentEncoding};
+
+/// Largest body a [`DecompressingBody`] produces unless the client sets its own
+/// limit. Compression ratios of gzip and brotli reach the thousands, so the size
+/// of the compressed body says nothing about the memory its decoding takes.
+pub const MAX_DECODED_BODY_SIZE: usize = 100 * 1024 * 1024;
+
+/// Decodes a response body as it is polled, so chunked and other streamed
+/// bodies are decoded piece by piece instead of being collected first.
+/// Trailers of the inner body are passed through after the decoded data.
+/// Decoded output over `max_decoded_size` ends the body with an error frame.
+pub struct DecompressingBody {
+    inner: BoxBody<Bytes, String>,
+    /// In decoding order: the coding applied last by the server goes first.
+    encodings: Vec<ContentEncoding>,
+    max_decoded_size: usize,
+    /// Created on the first data frame, so an empty body (HEAD, 204, 304) with a
+    /// `Content-Encoding` header is not treated as a truncated stream.
+    decoders: Option<Vec<Box<dyn Decoder>>>,
+    trailers: Option<HeaderMap>,
+    finished: bool,
+}
+
+impl DecompressingBody {
+    pub fn new(
+        inner: BoxBody<Bytes, String>,
+        encodings: Vec<ContentEncoding>,
+        max_decoded_size: usize,
+    ) -> Self {
+        Self {
+            inner,
+            encodings,
+            max_decoded_size,
+            decoders: None,
+            trailers: None,
+            finished: false,
+        }
+    }
+
+    fn decode(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if self.decoders.is_none() {
+            let mut decoders = Vec::with_capacity(self.encodings.len());
+            for encoding in self.encodings.iter() {
+                match super::decoder::create_decoder(*encoding, self.max_decoded_size) {
+                    Some(decoder) => decoders.push(decoder),
+                    None => {
+                        return Err(format!(
+                            "Content-Encoding '{}' is not supported",
+                            encoding.as_str()
+                        ))
+                    }
+                }
+            }
+            self.decoders = Some(decoders);
+        }
+
+        let mut result = data.to_vec();
+
+        for (decoder, encoding) in self
+            .decoders
+            .as_mut()
+            .unwrap()
+            .iter_mut()
+            .zip(self.encodings.iter())
+        {
+            result = decoder
+                .decode(&result)
+                .map_err(|err| decode_error(*encoding, err))?;
+        }
+
+        Ok(result)
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>, String> {
+        let decoders = match self.decoders.take() {
+            Some(decoders) => decoders,
+            None => return Ok(Vec::new()),
+        };
+
+        // Whatever an outer decoder flushes on finish still has to go through
+        // the decoders after it.
+        let mut result = Vec::new();
+        for (mut decoder, encoding) in decoders.into_iter().zip(self.encodings.iter()) {
+            let mut decoded = decoder
+                .decode(&result)
+                .map_err(|err| decode_error(*encoding, err))?;
+            let tail = decoder
+                .finish()
+                .map_err(|err| decode_error(*encoding, err))?;
+            decoded.extend_from_slice(&tail);
+            result = decoded;
+        }
+
+        Ok(result)
+    }
+}
+
+fn decode_error(encoding: ContentEncoding, err: std::io::Error) -> String {
+    format!(
+        "Can not decode '{}' response body: {}",
+        encoding.as_str(),
+        err
+    )
+}
+
+impl Body for DecompressingBody {
+    type Data = Bytes;
+    type Error = String;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.finished {
+                return Poll::Ready(this.trailers.take().map(|itm| Ok(Frame::trailers(itm))));
+            }
+
+            let frame = match Pin::new(&mut this.inner).poll_frame(cx) {
+                Poll::Ready(frame) => frame,
+                Poll::Pending => return Poll::Pending,
+            };
+
+            match frame {
+                Some(Ok(frame)) => match frame.into_data() {
+                    Ok(data) => {
+                        let decoded = this.decode(&data)?;
+                        if !decoded.is_empty() {
+                            return Poll::Ready(Some(Ok(Frame::data(decoded.into()))));
+                        }
+                        continue;
+                    }
+                    Err(frame) => {
+                        if let Ok(trailers) = frame.into_trailers() {
+                            this.trailers = Some(trailers);
+                        }
+                    }
+                },
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => {}
+            }
+
+            // Trailers or the end of the inner body: the compressed stream is over
+            this.finished = true;
+            let tail = this.finish()?;
+            if !tail.is_empty() {
+                return Poll::Ready(Some(Ok(Frame::data(tail.into()))));
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.finished && self.trailers.is_none()
+    }
+}
+
+/// Wraps the body of a response whose `Content-Encoding` lists only supported
+/// codings into a [`DecompressingBody`], and drops the `Content-Encoding` and
+/// `Content-Length` headers that no longer describe the body. Responses with no
+/// coding or with a coding that is not supported are returned as is.
+pub fn decompress_response(
+    response: crate::HyperResponse,
+    max_decoded_size: usize,
+) -> crate::HyperResponse {
+    let encodings = match get_encodings_to_decode(response.headers()) {
+        Some(encodings) => encodings,
+        None => return response,
+    };
+
+    let (mut parts, body) = response.into_parts();
+    parts.headers.remove(header::CONTENT_ENCODING);
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    let body = DecompressingBody::new(body, encodings, max_decoded_size).boxed();
+    crate::HyperResponse::from_parts(parts, body)
+}
+
+fn get_encodings_to_decode(headers: &HeaderMap) -> Option<Vec<ContentEncoding>> {
+    let mut result = Vec::new();
+
+    for value in headers.get_all(header::CONTENT_ENCODING) {
+        let value = value.to_str().ok()?;
+
+        for item in value.split(',') {
+            let item = item.trim();
+            if item.is_empty() || item.eq_ignore_ascii_case("identity") {
+                continue;
+            }
+
+            let encoding = ContentEncoding::from_header_value(item)?;
+            if !encoding.is_supported() {
+                return None;
+            }
+
+            result.push(encoding);
+        }
+    }
+
+    if result.is_empty() {
+        return None;
+    }
+
+    // Codings are listed in the order they were applied
+    result.reverse();
+    Some(result)
+}
+
+/// Adds `Accept-Encoding` with every supported coding unless the request
+/// already has one.
+pub fn add_accept_encoding(headers: &mut HeaderMap) {
+    if headers.contains_key(header::ACCEPT_ENCODING) {
+        return;
+    }
+
+    if let Some(accept_encoding) = ContentEncoding::get_accept_encoding() {
+        headers.insert(
+            header::ACCEPT_ENCODING,
+            http::HeaderValue::from_str(&accept_encoding).unwrap(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body_util::BodyExt;
+
+    use super::decompress_response;
+    #[cfg(any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    use super::ContentEncoding;
+
+    #[cfg(any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    fn encode(encoding: ContentEncoding, data: &[u8]) -> Vec<u8> {
+        crate::compression::compress(encoding, data).unwrap()
+    }
+
+    #[cfg(any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    fn payload() -> Vec<u8> {
+        "The quick brown fox jumps over the lazy dog. "
+            .repeat(200)
+            .into_bytes()
+    }
+
+    #[cfg(any(
+        feature = "gzip",
+        feature = "deflate",
+        feature = "brotli",
+        feature = "zstd"
+    ))]
+    #[tokio::test]
+    async fn chunked_body_is_decoded_as_it_streams() {
+        use futures::SinkExt;
+
+        for encoding in ContentEncoding::ALL.iter().filter(|itm| itm.is_supported()) {
+            let encoded = encode(*encoding, &payload());
+
+            let builder = http::Response::builder()
+                .status(200)
+                .header("Content-Encoding", encoding.as_str())
+                .header("Content-Length", encoded.len());
+            let (mut sender, response) = crate::http1::create_chunked_body_response(builder);
+
+            let response = decompress_response(response, super::MAX_DECODED_BODY_SIZE);
+            assert!(response.headers().get("content-encoding").is_none());
+            assert!(response.headers().get("content-length").is_none());
+
+            let writer = tokio::spawn(async move {
+                for chunk in encoded.chunks(100) {
+                    let frame = hyper::body::Frame::data(Bytes::copy_from_slice(chunk));
+                    sender.send(Ok(frame)).await.unwrap();
+                }
+
+                let mut trailers = http::HeaderMap::new();
+                trailers.insert("x-checksum", "42".parse().unwrap());
+                sender
+                    .send(Ok(hyper::body::Frame::trailers(trailers)))
+                    .await
+                    .unwrap();
+            });
+
+            let collected = response.into_body().collect().await.unwrap();
+            writer.await.unwrap();
+
+            assert_eq!(
+                collected.trailers().unwrap().get("x-checksum").unwrap(),
+                "42"
+            );
+            assert_eq!(collected.to_bytes().as_ref(), payload().as_slice());
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_encoding_is_left_as_is() {
+        let response = http::Response::builder()
+            .header("Content-Encoding", "compress")
+            .body(Bytes::from_static(b"raw"));
+        let response = crate::utils::into_full_body_response(response.unwrap().map(Into::into));
+
+        let response = decompress_response(response, super::MAX_DECODED_BODY_SIZE);
+
+        assert_eq!(
+            response.headers().get("content-encoding").unwrap(),
+            "compress"
+        );
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"raw");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn empty_body_with_encoding_is_empty() {
+        let builder = http::Response::builder()
+            .status(304)
+            .header("Content-Encoding", "gzip");
+
+        let response = decompress_response(
+            crate::utils::into_empty_body(builder),
+            super::MAX_DECODED_BODY_SIZE,
+        );
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert!(body.is_empty());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn decoded_body_over_the_limit_is_an_error() {
+        let zeros = vec![0u8; 1024 * 1024];
+        let encoded = encode(ContentEncoding::Gzip, &zeros);
+        assert!(encoded.len() < 64 * 1024);
+
+        let response = http::Response::builder()
+            .header("Content-Encoding", "gzip")
+            .body(Bytes::from(encoded));
+        let response = crate::utils::into_full_body_response(response.unwrap().map(Into::into));
+
+        let response = decompress_response(response, 64 * 1024);
+
+        let err = response.into_body().collect().await.unwrap_err();
+        assert!(err.contains("larger than 65536 bytes"), "{}", err);
+
+        let response = http::Response::builder()
+            .header("Content-Encoding", "gzip")
+            .body(Bytes::from(encode(ContentEncoding::Gzip, &zeros)));
+        let response = crate::utils::into_full_body_response(response.unwrap().map(Into::into));
+
+        let response = decompress_response(response, zeros.len());
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.len(), zeros.len());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn http1_client_advertises_and_decodes() {
+        use tokio::io::AsyncWriteExt;
+
+        use crate::http1::{test_utils::*, MyHttpClient, MyHttpRequestBuilder};
+
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+
+            let body = encode(ContentEncoding::Gzip, &payload());
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(head.as_bytes()).await.unwrap();
+            socket.write_all(&body).await.unwrap();
+            received
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_decompress_responses(true);
+
+        let req = MyHttpRequestBuilder::new(http::Method::GET, "/").build();
+        let response = client
+            .do_request(&req, std::time::Duration::from_secs(5))
+            .await
+            .unwrap()
+            .into_response();
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), payload().as_slice());
+
+        let received = String::from_utf8(server.await.unwrap()).unwrap();
+        let accept_encoding = ContentEncoding::get_accept_encoding().unwrap();
+        assert!(received.contains(&format!("Accept-Encoding: {}\r\n", accept_encoding)));
+    }
+}
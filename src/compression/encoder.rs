@@ -0,0 +1,158 @@
+use bytes::Bytes;
+use http::header;
+use http_body_util::{BodyExt, Full};
+
+use super::ContentEncoding;
+
+/// Compresses a whole request body. Fails with [`std::io::ErrorKind::Unsupported`]
+/// when the cargo feature of `encoding` is not enabled.
+#[allow(unused_variables)]
+pub fn compress(encoding: ContentEncoding, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    #[allow(unused_imports)]
+    use std::io::Write;
+
+    match encoding {
+        #[cfg(feature = "gzip")]
+        ContentEncoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "deflate")]
+        ContentEncoding::Deflate => {
+            let mut encoder =
+                flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        #[cfg(feature = "brotli")]
+        ContentEncoding::Brotli => {
+            let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+            encoder.write_all(data)?;
+            encoder.flush()?;
+            Ok(encoder.into_inner())
+        }
+        #[cfg(feature = "zstd")]
+        ContentEncoding::Zstd => zstd::stream::encode_all(data, 0),
+        #[allow(unreachable_patterns)]
+        _ => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            format!("Content-Encoding '{}' is not enabled", encoding.as_str()),
+        )),
+    }
+}
+
+/// Compresses the body of a hyper request, sets `Content-Encoding` and
+/// recomputes `Content-Length`. A request that already has `Content-Encoding`,
+/// or has an empty body, is returned as is so a body is never encoded twice.
+pub async fn compress_request(
+    req: hyper::Request<Full<Bytes>>,
+    encoding: ContentEncoding,
+) -> std::io::Result<hyper::Request<Full<Bytes>>> {
+    if req.headers().contains_key(header::CONTENT_ENCODING) {
+        return Ok(req);
+    }
+
+    let (mut parts, body) = req.into_parts();
+    let body = body.collect().await.unwrap().to_bytes();
+
+    if body.is_empty() {
+        return Ok(hyper::Request::from_parts(parts, Full::new(body)));
+    }
+
+    let compressed = compress(encoding, &body)?;
+
+    parts.headers.insert(
+        header::CONTENT_ENCODING,
+        http::HeaderValue::from_static(encoding.as_str()),
+    );
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, compressed.len().into());
+
+    Ok(hyper::Request::from_parts(
+        parts,
+        Full::new(Bytes::from(compressed)),
+    ))
+}
+
+#[cfg(all(test, feature = "gzip"))]
+mod tests {
+    use bytes::Bytes;
+    use http::Method;
+    use http_body_util::{BodyExt, Full};
+
+    use super::{compress_request, ContentEncoding};
+    use crate::http1::MyHttpRequestBuilder;
+
+    fn gunzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Read;
+
+        let mut result = Vec::new();
+        flate2::read::GzDecoder::new(data)
+            .read_to_end(&mut result)
+            .unwrap();
+        result
+    }
+
+    #[test]
+    fn builder_compresses_body_and_recomputes_content_length() {
+        let body = "{\"value\":42}".repeat(100).into_bytes();
+
+        let mut builder = MyHttpRequestBuilder::new(Method::POST, "/upload");
+        builder.append_header("Content-Length", body.len().to_string().as_str());
+        builder.set_body_compression(ContentEncoding::Gzip);
+        let req = builder.build_with_body(body.clone());
+
+        let headers = std::str::from_utf8(&req.headers).unwrap();
+        assert_eq!(
+            headers,
+            format!(
+                "POST /upload HTTP/1.1\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n",
+                req.body.len()
+            )
+        );
+        assert_eq!(gunzip(&req.body), body);
+    }
+
+    #[test]
+    fn builder_does_not_encode_twice() {
+        let body = b"already encoded".to_vec();
+
+        let mut builder = MyHttpRequestBuilder::new(Method::POST, "/upload");
+        builder.append_header("Content-Encoding", "br");
+        builder.set_body_compression(ContentEncoding::Gzip);
+        let req = builder.build_with_body(body.clone());
+
+        assert_eq!(req.body.as_ref(), body.as_slice());
+        assert_eq!(
+            std::str::from_utf8(&req.headers).unwrap(),
+            "POST /upload HTTP/1.1\r\nContent-Encoding: br\r\nContent-Length: 15\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn hyper_request_is_compressed() {
+        let body = "payload ".repeat(100);
+
+        let req = hyper::Request::post("/upload")
+            .header("content-length", body.len())
+            .body(Full::new(Bytes::from(body.clone())))
+            .unwrap();
+
+        let req = compress_request(req, ContentEncoding::Gzip).await.unwrap();
+
+        assert_eq!(req.headers().get("content-encoding").unwrap(), "gzip");
+        let compressed = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(gunzip(&compressed), body.as_bytes());
+
+        let req = hyper::Request::post("/upload")
+            .header("content-encoding", "zstd")
+            .body(Full::new(Bytes::from_static(b"raw")))
+            .unwrap();
+        let req = compress_request(req, ContentEncoding::Gzip).await.unwrap();
+        let body = req.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body.as_ref(), b"raw");
+    }
+}
@@ -0,0 +1,7 @@
+mod content_encoding;
+pub use content_encoding::*;
+mod decoder;
+mod encoder;
+pub use encoder::*;
+mod decompressing_body;
+pub use decompressing_body::*;
@@ -0,0 +1,27 @@
+use tokio::net::TcpStream;
+
+/// A connection that can tell the application protocol negotiated with ALPN,
+/// which [`crate::http_auto::MyHttpAutoClient`] uses to pick HTTP/1.1 or HTTP/2.
+pub trait AlpnStream {
+    fn get_alpn_protocol(&self) -> Option<&[u8]>;
+}
+
+impl AlpnStream for TcpStream {
+    fn get_alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+#[cfg(unix)]
+impl AlpnStream for tokio::net::UnixStream {
+    fn get_alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+#[cfg(feature = "rustls")]
+impl<TStream> AlpnStream for tokio_rustls::client::TlsStream<TStream> {
+    fn get_alpn_protocol(&self) -> Option<&[u8]> {
+        self.get_ref().1.alpn_protocol()
+    }
+}
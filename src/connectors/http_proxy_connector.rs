@@ -0,0 +1,318 @@
+use std::marker::PhantomData;
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+use super::NoProxy;
+
+const MAX_RESPONSE_HEAD_SIZE: usize = 64 * 1024;
+
+/// Reaches the remote endpoint of `target_connector` through an HTTP proxy
+/// with a `CONNECT` tunnel, e.g.
+/// `HttpProxyConnector::new(TcpConnector::new("api.example.com:443"), TcpConnector::new("proxy:3128"))`.
+///
+/// Hosts matched by the `NO_PROXY`-style rules are dialed directly with
+/// `target_connector`.
+pub struct HttpProxyConnector<TStream, TConnector: MyHttpClientConnector<TStream>>
+where
+    TStream: AsyncRead + AsyncWrite,
+{
+    target_connector: TConnector,
+    proxy_connector: TConnector,
+    proxy_authorization: Option<String>,
+    no_proxy: NoProxy,
+    _stream: PhantomData<fn() -> TStream>,
+}
+
+impl<TStream, TConnector> HttpProxyConnector<TStream, TConnector>
+where
+    TStream: AsyncRead + AsyncWrite,
+    TConnector: MyHttpClientConnector<TStream>,
+{
+    pub fn new(target_connector: TConnector, proxy_connector: TConnector) -> Self {
+        Self {
+            target_connector,
+            proxy_connector,
+            proxy_authorization: None,
+            no_proxy: NoProxy::default(),
+            _stream: PhantomData,
+        }
+    }
+
+    /// Sends `Proxy-Authorization: Basic ...` with the `CONNECT` request.
+    pub fn set_proxy_basic_auth(&mut self, user_name: &str, password: &str) {
+        let credentials = format!("{}:{}", user_name, password);
+        self.proxy_authorization = Some(format!(
+            "Basic {}",
+            crate::utils::encode_base64(credentials.as_bytes())
+        ));
+    }
+
+    /// Sends `value` as the `Proxy-Authorization` header of the `CONNECT` request.
+    pub fn set_proxy_authorization(&mut self, value: impl Into<String>) {
+        self.proxy_authorization = Some(value.into());
+    }
+
+    pub fn set_no_proxy(&mut self, no_proxy: NoProxy) {
+        self.no_proxy = no_proxy;
+    }
+
+    pub fn get_target_connector(&self) -> &TConnector {
+        &self.target_connector
+    }
+
+    fn bypasses_proxy(&self) -> bool {
+        let host_port = self.target_connector.get_remote_endpoint().get_host_port();
+        self.no_proxy.matches(strip_port(host_port.as_str()))
+    }
+}
+
+#[async_trait::async_trait]
+impl<TStream, TConnector> MyHttpClientConnector<TStream> for HttpProxyConnector<TStream, TConnector>
+where
+    TStream: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    async fn connect(&self) -> Result<TStream, MyHttpClientError> {
+        if self.bypasses_proxy() {
+            return self.target_connector.connect().await;
+        }
+
+        let host_port = self
+            .target_connector
+            .get_remote_endpoint()
+            .get_host_port()
+            .to_string();
+
+        let mut stream = self.proxy_connector.connect().await?;
+
+        establish_tunnel(
+            &mut stream,
+            host_port.as_str(),
+            self.proxy_authorization.as_deref(),
+        )
+        .await
+        .map_err(|err| {
+            MyHttpClientError::CanNotConnectToRemoteHost(format!(
+                "CONNECT to '{}' through proxy '{}' failed: {}",
+                host_port,
+                self.proxy_connector.get_remote_endpoint().get_host_port(),
+                err
+            ))
+        })?;
+
+        Ok(stream)
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        self.target_connector.get_remote_endpoint()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.target_connector.is_debug()
+    }
+
+    fn reunite(read: ReadHalf<TStream>, write: WriteHalf<TStream>) -> TStream {
+        TConnector::reunite(read, write)
+    }
+}
+
+async fn establish_tunnel<TStream: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut TStream,
+    host_port: &str,
+    proxy_authorization: Option<&str>,
+) -> Result<(), String> {
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", host_port, host_port);
+
+    if let Some(proxy_authorization) = proxy_authorization {
+        request.push_str("Proxy-Authorization: ");
+        request.push_str(proxy_authorization);
+        request.push_str("\r\n");
+    }
+
+    request.push_str("\r\n");
+
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|err| err.to_string())?;
+
+    // Read byte by byte: whatever follows the response head belongs to the tunnel.
+    let mut response_head = Vec::new();
+    let mut byte = [0u8; 1];
+
+    while !response_head.ends_with(b"\r\n\r\n") {
+        if response_head.len() > MAX_RESPONSE_HEAD_SIZE {
+            return Err("Response head is too large".to_string());
+        }
+
+        let read = stream
+            .read(&mut byte)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        if read == 0 {
+            return Err("Proxy closed the connection".to_string());
+        }
+
+        response_head.push(byte[0]);
+    }
+
+    let status_line = response_head
+        .split(|b| *b == b'\n')
+        .next()
+        .unwrap_or_default();
+    let status_line = String::from_utf8_lossy(status_line);
+    let status_line = status_line.trim_end();
+
+    let status_code = status_line
+        .strip_prefix("HTTP/1.")
+        .and_then(|itm| itm.get(2..5))
+        .and_then(|itm| itm.parse::<u16>().ok());
+
+    match status_code {
+        Some(200..=299) => Ok(()),
+        _ => Err(format!("Proxy responded '{}'", status_line)),
+    }
+}
+
+fn strip_port(host_port: &str) -> &str {
+    if let Some(host) = host_port.strip_prefix('[') {
+        return match host.split_once(']') {
+            Some((host, _)) => host,
+            None => host,
+        };
+    }
+
+    match host_port.rsplit_once(':') {
+        Some((host, _)) if !host.contains(':') => host,
+        _ => host_port,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Method;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::HttpProxyConnector;
+    use crate::{
+        connectors::{NoProxy, TcpConnector},
+        http1::{test_utils::read_until_ends_with, MyHttpClient, MyHttpRequestBuilder},
+        MyHttpClientError,
+    };
+
+    async fn start_target() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            let _ = socket.read(&mut [0u8; 1]).await;
+        });
+
+        remote_endpoint
+    }
+
+    /// Answers one `CONNECT` with `response` and tunnels to the requested host on 2xx.
+    async fn start_proxy(response: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            let request_head = String::from_utf8(received).unwrap();
+
+            socket.write_all(response.as_bytes()).await.unwrap();
+
+            if response.starts_with("HTTP/1.1 2") {
+                let target = request_head.split(' ').nth(1).unwrap();
+                let mut upstream = tokio::net::TcpStream::connect(target).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut socket, &mut upstream).await;
+            }
+
+            request_head
+        });
+
+        (remote_endpoint, proxy)
+    }
+
+    #[tokio::test]
+    async fn request_is_tunneled_through_proxy() {
+        let target = start_target().await;
+        let (proxy, proxy_task) = start_proxy("HTTP/1.1 200 Connection established\r\n\r\n").await;
+
+        let mut connector =
+            HttpProxyConnector::new(TcpConnector::new(target.as_str()), TcpConnector::new(proxy));
+        connector.set_proxy_basic_auth("user", "secret");
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        drop(client);
+
+        let request_head = proxy_task.await.unwrap();
+        assert!(request_head.starts_with(&format!("CONNECT {} HTTP/1.1\r\n", target)));
+        assert!(request_head.contains("Proxy-Authorization: Basic dXNlcjpzZWNyZXQ=\r\n"));
+    }
+
+    #[tokio::test]
+    async fn refused_connect_is_an_error() {
+        let (proxy, _) =
+            start_proxy("HTTP/1.1 407 Proxy Authentication Required\r\nContent-Length: 0\r\n\r\n")
+                .await;
+
+        let connector =
+            HttpProxyConnector::new(TcpConnector::new("127.0.0.1:1"), TcpConnector::new(proxy));
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let result = client.do_request(&req, Duration::from_secs(5)).await;
+
+        match result {
+            Err(MyHttpClientError::CanNotConnectToRemoteHost(message)) => {
+                assert!(message.contains("407"), "{}", message)
+            }
+            Err(err) => panic!("Unexpected error: {:?}", err),
+            Ok(_) => panic!("Request through a refusing proxy succeeded"),
+        }
+    }
+
+    #[tokio::test]
+    async fn no_proxy_hosts_are_dialed_directly() {
+        let target = start_target().await;
+
+        let mut connector = HttpProxyConnector::new(
+            TcpConnector::new(target.as_str()),
+            TcpConnector::new("127.0.0.1:1"),
+        );
+        connector.set_no_proxy(NoProxy::parse("localhost,127.0.0.0/8"));
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+}
@@ -0,0 +1,22 @@
+mod alpn_stream;
+pub use alpn_stream::*;
+mod http_proxy_connector;
+pub use http_proxy_connector::*;
+mod no_proxy;
+pub use no_proxy::*;
+mod socks5_connector;
+pub use socks5_connector::*;
+mod tcp_connector;
+pub use tcp_connector::*;
+#[cfg(feature = "rustls")]
+mod tls_connector;
+#[cfg(feature = "rustls")]
+pub use tls_connector::*;
+#[cfg(feature = "rustls")]
+pub extern crate tokio_rustls;
+#[cfg(all(test, feature = "rustls"))]
+pub(crate) mod test_certificates;
+#[cfg(unix)]
+mod unix_socket_connector;
+#[cfg(unix)]
+pub use unix_socket_connector::*;
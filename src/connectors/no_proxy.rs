@@ -0,0 +1,158 @@
+use std::net::IpAddr;
+
+/// `NO_PROXY`-style list of hosts reached without the proxy, e.g.
+/// `localhost,.internal.corp,10.0.0.0/8`.
+///
+/// An entry matches its host and, for names, all subdomains (`example.com`
+/// and `.example.com` both match `api.example.com`). An IP network in CIDR
+/// notation matches the IP addresses in it, and `*` matches every host. Ports in
+/// entries are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct NoProxy {
+    entries: Vec<NoProxyEntry>,
+}
+
+#[derive(Debug, Clone)]
+enum NoProxyEntry {
+    Any,
+    Domain(String),
+    Network { address: IpAddr, prefix_len: u32 },
+}
+
+impl NoProxy {
+    pub fn parse(src: &str) -> Self {
+        let entries = src
+            .split(',')
+            .filter_map(|itm| parse_entry(itm.trim()))
+            .collect();
+
+        Self { entries }
+    }
+
+    /// Reads `NO_PROXY` or `no_proxy`.
+    pub fn from_env() -> Self {
+        match std::env::var("NO_PROXY").or_else(|_| std::env::var("no_proxy")) {
+            Ok(value) => Self::parse(value.as_str()),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// `host` is a name or an IP address, without a port.
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let ip: Option<IpAddr> = host.parse().ok();
+
+        self.entries.iter().any(|entry| match entry {
+            NoProxyEntry::Any => true,
+            NoProxyEntry::Domain(domain) => {
+                if host.eq_ignore_ascii_case(domain) {
+                    return true;
+                }
+
+                host.len() > domain.len()
+                    && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+                    && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+            }
+            NoProxyEntry::Network {
+                address,
+                prefix_len,
+            } => match ip {
+                Some(ip) => network_contains(*address, *prefix_len, ip),
+                None => false,
+            },
+        })
+    }
+}
+
+fn parse_entry(entry: &str) -> Option<NoProxyEntry> {
+    if entry.is_empty() {
+        return None;
+    }
+
+    if entry == "*" {
+        return Some(NoProxyEntry::Any);
+    }
+
+    if let Some((address, prefix_len)) = entry.split_once('/') {
+        let address: IpAddr = address.parse().ok()?;
+        let prefix_len: u32 = prefix_len.parse().ok()?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+
+        if prefix_len > max_prefix_len {
+            return None;
+        }
+
+        return Some(NoProxyEntry::Network {
+            address,
+            prefix_len,
+        });
+    }
+
+    if let Ok(address) = entry.trim_start_matches('[').trim_end_matches(']').parse() {
+        let prefix_len = if matches!(address, IpAddr::V4(_)) {
+            32
+        } else {
+            128
+        };
+
+        return Some(NoProxyEntry::Network {
+            address,
+            prefix_len,
+        });
+    }
+
+    let host = match entry.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => entry,
+    };
+
+    let host = host.trim_start_matches("*.").trim_start_matches('.');
+
+    if host.is_empty() {
+        return None;
+    }
+
+    Some(NoProxyEntry::Domain(host.to_string()))
+}
+
+fn network_contains(network: IpAddr, prefix_len: u32, ip: IpAddr) -> bool {
+    let (network, ip, bits) = match (network, ip) {
+        (IpAddr::V4(network), IpAddr::V4(ip)) => {
+            (u32::from(network) as u128, u32::from(ip) as u128, 32)
+        }
+        (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(ip), 128),
+        _ => return false,
+    };
+
+    if prefix_len == 0 {
+        return true;
+    }
+
+    let shift = bits - prefix_len;
+    network >> shift == ip >> shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NoProxy;
+
+    #[test]
+    fn matches_names_subdomains_and_networks() {
+        let no_proxy = NoProxy::parse("localhost, .internal.corp,example.com:8080,10.0.0.0/8,::1");
+
+        assert!(no_proxy.matches("localhost"));
+        assert!(no_proxy.matches("api.internal.corp"));
+        assert!(no_proxy.matches("internal.corp"));
+        assert!(no_proxy.matches("EXAMPLE.com"));
+        assert!(no_proxy.matches("www.example.com"));
+        assert!(no_proxy.matches("10.1.2.3"));
+        assert!(no_proxy.matches("[::1]"));
+
+        assert!(!no_proxy.matches("notexample.com"));
+        assert!(!no_proxy.matches("11.0.0.1"));
+        assert!(!no_proxy.matches("github.com"));
+
+        assert!(NoProxy::parse("*").matches("github.com"));
+        assert!(!NoProxy::parse("").matches("github.com"));
+    }
+}
@@ -0,0 +1,512 @@
+use std::{
+    marker::PhantomData,
+    net::{Ipv4Addr, Ipv6Addr},
+};
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+const SOCKS_VERSION: u8 = 5;
+const NO_AUTH: u8 = 0x00;
+const USER_NAME_PASSWORD_AUTH: u8 = 0x02;
+const NO_ACCEPTABLE_AUTH: u8 = 0xff;
+const CONNECT_COMMAND: u8 = 0x01;
+
+const ADDRESS_TYPE_IPV4: u8 = 0x01;
+const ADDRESS_TYPE_DOMAIN: u8 = 0x03;
+const ADDRESS_TYPE_IPV6: u8 = 0x04;
+
+/// Reaches `remote_endpoint` through a SOCKS5 proxy (RFC 1928) dialed with
+/// `proxy_connector`, e.g.
+/// `Socks5Connector::new("api.example.com:443", TcpConnector::new("bastion:1080"))`.
+///
+/// Domain names are resolved by the proxy. Wrap it with
+/// [`super::TlsConnector::new_over`] for HTTPS upstreams.
+pub struct Socks5Connector<TStream, TConnector: MyHttpClientConnector<TStream>>
+where
+    TStream: AsyncRead + AsyncWrite,
+{
+    remote_endpoint: String,
+    proxy_connector: TConnector,
+    credentials: Option<(String, String)>,
+    debug: bool,
+    _stream: PhantomData<fn() -> TStream>,
+}
+
+impl<TStream, TConnector> Socks5Connector<TStream, TConnector>
+where
+    TStream: AsyncRead + AsyncWrite,
+    TConnector: MyHttpClientConnector<TStream>,
+{
+    /// Panics if `remote_endpoint` is not a valid remote endpoint with a port.
+    pub fn new(remote_endpoint: impl Into<String>, proxy_connector: TConnector) -> Self {
+        let remote_endpoint = remote_endpoint.into();
+
+        if let Err(err) = RemoteEndpoint::try_parse(remote_endpoint.as_str()) {
+            panic!("Invalid remote endpoint '{}': {:?}", remote_endpoint, err);
+        }
+
+        if split_host_port(remote_endpoint.as_str()).is_none() {
+            panic!("Remote endpoint '{}' has no port", remote_endpoint);
+        }
+
+        Self {
+            remote_endpoint,
+            proxy_connector,
+            credentials: None,
+            debug: false,
+            _stream: PhantomData,
+        }
+    }
+
+    /// Panics if the credentials are invalid: see [`Self::try_set_credentials`].
+    pub fn set_credentials(&mut self, user_name: impl Into<String>, password: impl Into<String>) {
+        if let Err(err) = self.try_set_credentials(user_name, password) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Offers user name and password authentication (RFC 1929) to the proxy.
+    /// Fails unless both are 1 to 255 bytes long.
+    pub fn try_set_credentials(
+        &mut self,
+        user_name: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Result<(), String> {
+        let user_name = user_name.into();
+        let password = password.into();
+
+        for (name, value) in [("User name", &user_name), ("Password", &password)] {
+            if value.is_empty() || value.len() > 255 {
+                return Err(format!("{} must be 1 to 255 bytes long", name));
+            }
+        }
+
+        self.credentials = Some((user_name, password));
+        Ok(())
+    }
+
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+}
+
+#[async_trait::async_trait]
+impl<TStream, TConnector> MyHttpClientConnector<TStream> for Socks5Connector<TStream, TConnector>
+where
+    TStream: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    async fn connect(&self) -> Result<TStream, MyHttpClientError> {
+        let mut stream = self.proxy_connector.connect().await?;
+
+        establish_tunnel(
+            &mut stream,
+            self.remote_endpoint.as_str(),
+            self.credentials.as_ref(),
+        )
+        .await
+        .map_err(|err| {
+            MyHttpClientError::CanNotConnectToRemoteHost(format!(
+                "SOCKS5 CONNECT to '{}' through proxy '{}' failed: {}",
+                self.remote_endpoint,
+                self.proxy_connector.get_remote_endpoint().get_host_port(),
+                err
+            ))
+        })?;
+
+        Ok(stream)
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        RemoteEndpoint::try_parse(self.remote_endpoint.as_str()).unwrap()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    fn reunite(read: ReadHalf<TStream>, write: WriteHalf<TStream>) -> TStream {
+        TConnector::reunite(read, write)
+    }
+}
+
+async fn establish_tunnel<TStream: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut TStream,
+    remote_endpoint: &str,
+    credentials: Option<&(String, String)>,
+) -> Result<(), String> {
+    let auth_method = match credentials {
+        Some(_) => USER_NAME_PASSWORD_AUTH,
+        None => NO_AUTH,
+    };
+
+    write(stream, &[SOCKS_VERSION, 1, auth_method]).await?;
+
+    let reply = read_exact::<2, _>(stream).await?;
+
+    if reply[0] != SOCKS_VERSION {
+        return Err(format!("Proxy answered with SOCKS version {}", reply[0]));
+    }
+
+    match (reply[1], credentials) {
+        (NO_AUTH, _) => {}
+        (USER_NAME_PASSWORD_AUTH, Some((user_name, password))) => {
+            authenticate(stream, user_name, password).await?
+        }
+        (NO_ACCEPTABLE_AUTH, _) => {
+            return Err("Proxy accepts none of the offered auth methods".into())
+        }
+        (method, _) => {
+            return Err(format!(
+                "Proxy chose unoffered auth method 0x{:02x}",
+                method
+            ))
+        }
+    }
+
+    let (host, port) = split_host_port(remote_endpoint)
+        .ok_or_else(|| format!("Remote endpoint '{}' has no port", remote_endpoint))?;
+
+    let mut request = vec![SOCKS_VERSION, CONNECT_COMMAND, 0];
+    encode_address(&mut request, host)?;
+    request.extend_from_slice(&port.to_be_bytes());
+
+    write(stream, &request).await?;
+
+    let reply = read_exact::<4, _>(stream).await?;
+
+    if reply[1] != 0 {
+        return Err(format!("Proxy refused: {}", get_reply_message(reply[1])));
+    }
+
+    // Bound address and port of the proxy end, not needed by the tunnel
+    let address_len = match reply[3] {
+        ADDRESS_TYPE_IPV4 => 4,
+        ADDRESS_TYPE_IPV6 => 16,
+        ADDRESS_TYPE_DOMAIN => read_exact::<1, _>(stream).await?[0] as usize,
+        address_type => return Err(format!("Unknown address type 0x{:02x}", address_type)),
+    };
+
+    let mut bound_address = vec![0u8; address_len + 2];
+    stream
+        .read_exact(&mut bound_address)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(())
+}
+
+async fn authenticate<TStream: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut TStream,
+    user_name: &str,
+    password: &str,
+) -> Result<(), String> {
+    let mut request = vec![1, user_name.len() as u8];
+    request.extend_from_slice(user_name.as_bytes());
+    request.push(password.len() as u8);
+    request.extend_from_slice(password.as_bytes());
+
+    write(stream, &request).await?;
+
+    let reply = read_exact::<2, _>(stream).await?;
+
+    if reply[1] != 0 {
+        return Err("Proxy rejected the user name or password".into());
+    }
+
+    Ok(())
+}
+
+fn encode_address(dest: &mut Vec<u8>, host: &str) -> Result<(), String> {
+    if let Ok(address) = host.parse::<Ipv4Addr>() {
+        dest.push(ADDRESS_TYPE_IPV4);
+        dest.extend_from_slice(&address.octets());
+        return Ok(());
+    }
+
+    let ipv6 = host.trim_start_matches('[').trim_end_matches(']');
+
+    if let Ok(address) = ipv6.parse::<Ipv6Addr>() {
+        dest.push(ADDRESS_TYPE_IPV6);
+        dest.extend_from_slice(&address.octets());
+        return Ok(());
+    }
+
+    if host.is_empty() || host.len() > 255 {
+        return Err(format!("Host '{}' can not be sent to the proxy", host));
+    }
+
+    dest.push(ADDRESS_TYPE_DOMAIN);
+    dest.push(host.len() as u8);
+    dest.extend_from_slice(host.as_bytes());
+    Ok(())
+}
+
+fn split_host_port(host_port: &str) -> Option<(&str, u16)> {
+    let (host, port) = host_port.rsplit_once(':')?;
+    let port = port.parse().ok()?;
+
+    if host.contains(':') && !host.starts_with('[') {
+        return None;
+    }
+
+    Some((host, port))
+}
+
+fn get_reply_message(reply: u8) -> &'static str {
+    match reply {
+        0x01 => "general SOCKS server failure",
+        0x02 => "connection not allowed by ruleset",
+        0x03 => "network unreachable",
+        0x04 => "host unreachable",
+        0x05 => "connection refused",
+        0x06 => "TTL expired",
+        0x07 => "command not supported",
+        0x08 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+async fn write<TStream: AsyncWrite + Unpin>(
+    stream: &mut TStream,
+    data: &[u8],
+) -> Result<(), String> {
+    stream.write_all(data).await.map_err(|err| err.to_string())
+}
+
+async fn read_exact<const N: usize, TStream: AsyncRead + Unpin>(
+    stream: &mut TStream,
+) -> Result<[u8; N], String> {
+    let mut buf = [0u8; N];
+    stream
+        .read_exact(&mut buf)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Method;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::{encode_address, Socks5Connector};
+    use crate::{
+        connectors::TcpConnector,
+        http1::{test_utils::read_until_ends_with, MyHttpClient, MyHttpRequestBuilder},
+        MyHttpClientError,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    async fn start_target() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            let _ = socket.read(&mut [0u8; 1]).await;
+        });
+
+        port
+    }
+
+    /// Accepts one client with user `user` and password `secret` and tunnels it
+    /// to 127.0.0.1 at the requested port. Returns the requested address.
+    async fn start_socks5_stub() -> (String, tokio::task::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        let stub = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+
+            let mut greeting = [0u8; 2];
+            socket.read_exact(&mut greeting).await.unwrap();
+            let mut methods = vec![0u8; greeting[1] as usize];
+            socket.read_exact(&mut methods).await.unwrap();
+
+            if !methods.contains(&2) {
+                socket.write_all(&[5, 0xff]).await.unwrap();
+                return Vec::new();
+            }
+
+            socket.write_all(&[5, 2]).await.unwrap();
+
+            let mut credentials = Vec::new();
+            for _ in 0..2 {
+                let mut header = [0u8; 2];
+                let len = if credentials.is_empty() {
+                    socket.read_exact(&mut header).await.unwrap();
+                    header[1]
+                } else {
+                    socket.read_u8().await.unwrap()
+                };
+                let mut value = vec![0u8; len as usize];
+                socket.read_exact(&mut value).await.unwrap();
+                credentials.push(value);
+            }
+
+            if credentials != [b"user".to_vec(), b"secret".to_vec()] {
+                socket.write_all(&[1, 1]).await.unwrap();
+                return Vec::new();
+            }
+
+            socket.write_all(&[1, 0]).await.unwrap();
+
+            let mut request = [0u8; 4];
+            socket.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[..3], [5, 1, 0]);
+
+            let mut address = vec![request[3]];
+            let len = match request[3] {
+                1 => 4,
+                4 => 16,
+                _ => {
+                    let len = socket.read_u8().await.unwrap();
+                    address.push(len);
+                    len as usize
+                }
+            };
+            let mut value = vec![0u8; len];
+            socket.read_exact(&mut value).await.unwrap();
+            address.extend_from_slice(&value);
+
+            let port = socket.read_u16().await.unwrap();
+            let mut upstream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+
+            socket
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 0])
+                .await
+                .unwrap();
+
+            let _ = tokio::io::copy_bidirectional(&mut socket, &mut upstream).await;
+
+            address
+        });
+
+        (remote_endpoint, stub)
+    }
+
+    #[tokio::test]
+    async fn request_is_tunneled_with_domain_address() {
+        let port = start_target().await;
+        let (proxy, stub) = start_socks5_stub().await;
+
+        let mut connector =
+            Socks5Connector::new(format!("localhost:{}", port), TcpConnector::new(proxy));
+        connector.set_credentials("user", "secret");
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+        drop(client);
+
+        assert_eq!(stub.await.unwrap(), b"\x03\x09localhost");
+    }
+
+    #[test]
+    fn credentials_must_fit_in_one_byte_length() {
+        let mut connector = Socks5Connector::new("127.0.0.1:1", TcpConnector::new("127.0.0.1:2"));
+
+        assert!(connector.try_set_credentials("", "secret").is_err());
+        assert!(connector
+            .try_set_credentials("user", "x".repeat(256))
+            .is_err());
+        assert!(connector.credentials.is_none());
+
+        connector
+            .try_set_credentials("user", "x".repeat(255))
+            .unwrap();
+        assert!(connector.credentials.is_some());
+    }
+
+    #[tokio::test]
+    async fn wrong_credentials_are_an_error() {
+        let (proxy, _) = start_socks5_stub().await;
+
+        let mut connector = Socks5Connector::new("127.0.0.1:1", TcpConnector::new(proxy));
+        connector.set_credentials("user", "wrong");
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+
+        match client.do_request(&req, TIMEOUT).await {
+            Err(MyHttpClientError::CanNotConnectToRemoteHost(message)) => {
+                assert!(message.contains("user name or password"), "{}", message)
+            }
+            Err(err) => panic!("Unexpected error: {:?}", err),
+            Ok(_) => panic!("Request with wrong credentials succeeded"),
+        }
+    }
+
+    #[test]
+    fn addresses_are_encoded_by_type() {
+        let mut dest = Vec::new();
+        encode_address(&mut dest, "10.0.0.1").unwrap();
+        assert_eq!(dest, [1, 10, 0, 0, 1]);
+
+        let mut dest = Vec::new();
+        encode_address(&mut dest, "[::1]").unwrap();
+        assert_eq!(dest[0], 4);
+        assert_eq!(dest[1..], std::net::Ipv6Addr::LOCALHOST.octets());
+
+        let mut dest = Vec::new();
+        encode_address(&mut dest, "example.com").unwrap();
+        assert_eq!(dest, b"\x03\x0bexample.com");
+    }
+
+    #[cfg(feature = "rustls")]
+    #[tokio::test]
+    async fn tls_over_socks5() {
+        use crate::connectors::{test_certificates::TestCertificates, TlsConnector};
+
+        let certificates = TestCertificates::generate();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = tokio_rustls::TlsAcceptor::from(certificates.get_server_config(false));
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut tls = acceptor.accept(socket).await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !received.ends_with(b"\r\n\r\n") {
+                let read = tls.read(&mut buf).await.unwrap();
+                assert!(read > 0);
+                received.extend_from_slice(&buf[..read]);
+            }
+            tls.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = tls.read(&mut [0u8; 1]).await;
+        });
+
+        let (proxy, _) = start_socks5_stub().await;
+
+        let mut socks5_connector =
+            Socks5Connector::new(format!("localhost:{}", port), TcpConnector::new(proxy));
+        socks5_connector.set_credentials("user", "secret");
+
+        let connector = TlsConnector::new_over(socks5_connector, certificates.get_root_store());
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+}
@@ -0,0 +1,174 @@
+use std::{net::SocketAddr, time::Duration};
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::{
+    io::{ReadHalf, WriteHalf},
+    net::{TcpSocket, TcpStream},
+};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+/// Plain TCP connector for any of the clients, e.g.
+/// `MyHttpClient::new(TcpConnector::new("127.0.0.1:8080"))`.
+pub struct TcpConnector {
+    remote_endpoint: String,
+    nodelay: bool,
+    keepalive: Option<Duration>,
+    local_address: Option<SocketAddr>,
+    debug: bool,
+}
+
+impl TcpConnector {
+    /// Panics if `remote_endpoint` is not a valid remote endpoint.
+    pub fn new(remote_endpoint: impl Into<String>) -> Self {
+        let remote_endpoint = remote_endpoint.into();
+
+        if let Err(err) = RemoteEndpoint::try_parse(remote_endpoint.as_str()) {
+            panic!("Invalid remote endpoint '{}': {:?}", remote_endpoint, err);
+        }
+
+        Self {
+            remote_endpoint,
+            nodelay: false,
+            keepalive: None,
+            local_address: None,
+            debug: false,
+        }
+    }
+
+    /// Sets `TCP_NODELAY`, so small requests are not held back by Nagle's algorithm.
+    pub fn set_nodelay(&mut self, nodelay: bool) {
+        self.nodelay = nodelay;
+    }
+
+    /// Enables TCP keepalive probes after the connection was idle for `idle_time`.
+    pub fn set_keepalive(&mut self, idle_time: Duration) {
+        self.keepalive = Some(idle_time);
+    }
+
+    /// Binds the local end of every connection to `local_address` before
+    /// connecting. Only the resolved addresses of the same family are tried.
+    pub fn set_local_address(&mut self, local_address: SocketAddr) {
+        self.local_address = Some(local_address);
+    }
+
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    async fn connect_to(&self, remote_address: SocketAddr) -> std::io::Result<TcpStream> {
+        let socket = if remote_address.is_ipv4() {
+            TcpSocket::new_v4()?
+        } else {
+            TcpSocket::new_v6()?
+        };
+
+        if let Some(local_address) = self.local_address {
+            socket.bind(local_address)?;
+        }
+
+        socket.set_nodelay(self.nodelay)?;
+
+        if let Some(idle_time) = self.keepalive {
+            let keepalive = socket2::TcpKeepalive::new().with_time(idle_time);
+            socket2::SockRef::from(&socket).set_tcp_keepalive(&keepalive)?;
+        }
+
+        socket.connect(remote_address).await
+    }
+}
+
+#[async_trait::async_trait]
+impl MyHttpClientConnector<TcpStream> for TcpConnector {
+    async fn connect(&self) -> Result<TcpStream, MyHttpClientError> {
+        let host_port = self.get_remote_endpoint().get_host_port().to_string();
+
+        let remote_addresses =
+            tokio::net::lookup_host(host_port.as_str())
+                .await
+                .map_err(|err| {
+                    MyHttpClientError::CanNotConnectToRemoteHost(format!("{}: {}", host_port, err))
+                })?;
+
+        let mut last_error = None;
+
+        for remote_address in remote_addresses {
+            if let Some(local_address) = self.local_address {
+                if local_address.is_ipv4() != remote_address.is_ipv4() {
+                    continue;
+                }
+            }
+
+            match self.connect_to(remote_address).await {
+                Ok(stream) => return Ok(stream),
+                Err(err) => last_error = Some(err),
+            }
+        }
+
+        let err = match last_error {
+            Some(err) => err.to_string(),
+            None => "no address to connect to".to_string(),
+        };
+
+        Err(MyHttpClientError::CanNotConnectToRemoteHost(format!(
+            "{}: {}",
+            host_port, err
+        )))
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        RemoteEndpoint::try_parse(self.remote_endpoint.as_str()).unwrap()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    fn reunite(read: ReadHalf<TcpStream>, write: WriteHalf<TcpStream>) -> TcpStream {
+        read.unsplit(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Method;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::TcpConnector;
+    use crate::http1::{test_utils::read_until_ends_with, MyHttpClient, MyHttpRequestBuilder};
+
+    #[tokio::test]
+    async fn connects_with_socket_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, peer_address) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            peer_address
+        });
+
+        let mut connector = TcpConnector::new(remote_endpoint);
+        connector.set_nodelay(true);
+        connector.set_keepalive(Duration::from_secs(30));
+        connector.set_local_address("127.0.0.1:0".parse().unwrap());
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let peer_address = server.await.unwrap();
+        assert!(peer_address.ip().is_loopback());
+    }
+}
@@ -0,0 +1,76 @@
+//! Certificates for tests of TLS connections: a CA, a server certificate for
+//! `localhost` and a client certificate, all generated on the fly.
+
+use std::sync::Arc;
+
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, KeyPair};
+use tokio_rustls::rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    server::WebPkiClientVerifier,
+    RootCertStore, ServerConfig,
+};
+
+pub struct TestCertificates {
+    ca: CertificateDer<'static>,
+    pub server: (CertificateDer<'static>, KeyPair),
+    pub client: (CertificateDer<'static>, KeyPair),
+}
+
+impl TestCertificates {
+    pub fn generate() -> Self {
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = CertifiedIssuer::self_signed(ca_params, KeyPair::generate().unwrap()).unwrap();
+
+        let issue = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec![name.to_string()])
+                .unwrap()
+                .signed_by(&key, &*ca)
+                .unwrap();
+            (cert.der().clone(), key)
+        };
+
+        Self {
+            ca: ca.der().clone(),
+            server: issue("localhost"),
+            client: issue("client"),
+        }
+    }
+
+    pub fn get_root_store(&self) -> RootCertStore {
+        let mut root_store = RootCertStore::empty();
+        root_store.add(self.ca.clone()).unwrap();
+        root_store
+    }
+
+    pub fn get_server_config(&self, require_client_auth: bool) -> Arc<ServerConfig> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap();
+
+        let builder = if require_client_auth {
+            let verifier = WebPkiClientVerifier::builder_with_provider(
+                Arc::new(self.get_root_store()),
+                provider,
+            )
+            .build()
+            .unwrap();
+            builder.with_client_cert_verifier(verifier)
+        } else {
+            builder.with_no_client_auth()
+        };
+
+        let mut config = builder
+            .with_single_cert(vec![self.server.0.clone()], get_key(&self.server.1))
+            .unwrap();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Arc::new(config)
+    }
+}
+
+pub fn get_key(key: &KeyPair) -> PrivateKeyDer<'static> {
+    PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der()))
+}
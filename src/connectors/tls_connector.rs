@@ -0,0 +1,293 @@
+use std::{marker::PhantomData, sync::Arc};
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadHalf, WriteHalf},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{
+        self,
+        pki_types::{CertificateDer, InvalidDnsNameError, PrivateKeyDer, ServerName},
+        ClientConfig, RootCertStore,
+    },
+};
+
+use super::TcpConnector;
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+/// TLS over [`TcpConnector`] or, with [`TlsConnector::new_over`], over any other
+/// connector (e.g. a tunnel of [`super::Socks5Connector`]), verified against the
+/// given root certificates.
+///
+/// [`crate::http2::MyHttp2Client`] needs `h2` negotiated: call
+/// `set_alpn_protocols(&[b"h2"])` before connecting.
+pub struct TlsConnector<TStream = TcpStream, TConnector = TcpConnector>
+where
+    TStream: AsyncRead + AsyncWrite,
+    TConnector: MyHttpClientConnector<TStream>,
+{
+    inner_connector: TConnector,
+    root_store: Arc<RootCertStore>,
+    alpn_protocols: Vec<Vec<u8>>,
+    server_name: ServerName<'static>,
+    config: Arc<ClientConfig>,
+    _stream: PhantomData<fn() -> TStream>,
+}
+
+impl TlsConnector {
+    /// The server name sent in SNI and verified in the certificate is the host
+    /// of `remote_endpoint`; see [`Self::set_server_name`].
+    ///
+    /// Panics if `remote_endpoint` is not a valid remote endpoint or its host is
+    /// not a valid server name.
+    pub fn new(remote_endpoint: impl Into<String>, root_store: RootCertStore) -> Self {
+        Self::new_over(TcpConnector::new(remote_endpoint), root_store)
+    }
+
+    /// TCP options of the connections: nodelay, keepalive, local address, debug.
+    pub fn get_tcp_connector_mut(&mut self) -> &mut TcpConnector {
+        &mut self.inner_connector
+    }
+}
+
+impl<TStream, TConnector> TlsConnector<TStream, TConnector>
+where
+    TStream: AsyncRead + AsyncWrite,
+    TConnector: MyHttpClientConnector<TStream>,
+{
+    /// TLS over the streams of `inner_connector`. The server name is the host of
+    /// its remote endpoint, as in [`TlsConnector::new`].
+    pub fn new_over(inner_connector: TConnector, root_store: RootCertStore) -> Self {
+        let host_port = inner_connector
+            .get_remote_endpoint()
+            .get_host_port()
+            .to_string();
+        let server_name = get_server_name(host_port.as_str()).unwrap_or_else(|err| {
+            panic!("Invalid server name in '{}': {}", host_port, err);
+        });
+
+        let root_store = Arc::new(root_store);
+
+        let config = build_config(root_store.clone(), None, Vec::new())
+            .expect("Config without client auth can not fail");
+
+        Self {
+            inner_connector,
+            root_store,
+            alpn_protocols: Vec::new(),
+            server_name,
+            config: Arc::new(config),
+            _stream: PhantomData,
+        }
+    }
+
+    pub fn get_inner_connector_mut(&mut self) -> &mut TConnector {
+        &mut self.inner_connector
+    }
+
+    /// Presents `cert_chain` to servers asking for a client certificate (mTLS).
+    pub fn set_client_auth(
+        &mut self,
+        cert_chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Result<(), rustls::Error> {
+        let config = build_config(
+            self.root_store.clone(),
+            Some((cert_chain, key)),
+            self.alpn_protocols.clone(),
+        )?;
+
+        self.config = Arc::new(config);
+        Ok(())
+    }
+
+    /// Sends `server_name` in SNI and verifies the certificate against it instead
+    /// of the host of the remote endpoint, e.g. when connecting by IP address.
+    pub fn set_server_name(&mut self, server_name: &str) -> Result<(), InvalidDnsNameError> {
+        self.server_name = ServerName::try_from(server_name.to_string())?;
+        Ok(())
+    }
+
+    /// Protocols offered in ALPN, most preferred first, e.g. `&[b"h2", b"http/1.1"]`.
+    pub fn set_alpn_protocols(&mut self, alpn_protocols: &[&[u8]]) {
+        self.alpn_protocols = alpn_protocols.iter().map(|itm| itm.to_vec()).collect();
+
+        let mut config = self.config.as_ref().clone();
+        config.alpn_protocols = self.alpn_protocols.clone();
+        self.config = Arc::new(config);
+    }
+}
+
+#[async_trait::async_trait]
+impl<TStream, TConnector> MyHttpClientConnector<TlsStream<TStream>>
+    for TlsConnector<TStream, TConnector>
+where
+    TStream: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    async fn connect(&self) -> Result<TlsStream<TStream>, MyHttpClientError> {
+        let stream = self.inner_connector.connect().await?;
+
+        tokio_rustls::TlsConnector::from(self.config.clone())
+            .connect(self.server_name.clone(), stream)
+            .await
+            .map_err(|err| {
+                MyHttpClientError::CanNotConnectToRemoteHost(format!(
+                    "TLS handshake with {} failed: {}",
+                    self.inner_connector.get_remote_endpoint().get_host_port(),
+                    err
+                ))
+            })
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        self.inner_connector.get_remote_endpoint()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.inner_connector.is_debug()
+    }
+
+    fn reunite(
+        read: ReadHalf<TlsStream<TStream>>,
+        write: WriteHalf<TlsStream<TStream>>,
+    ) -> TlsStream<TStream> {
+        read.unsplit(write)
+    }
+}
+
+fn build_config(
+    root_store: Arc<RootCertStore>,
+    client_auth: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+    alpn_protocols: Vec<Vec<u8>>,
+) -> Result<ClientConfig, rustls::Error> {
+    let builder =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(root_store);
+
+    let mut config = match client_auth {
+        Some((cert_chain, key)) => builder.with_client_auth_cert(cert_chain, key)?,
+        None => builder.with_no_client_auth(),
+    };
+
+    config.alpn_protocols = alpn_protocols;
+    Ok(config)
+}
+
+fn get_server_name(host_port: &str) -> Result<ServerName<'static>, InvalidDnsNameError> {
+    let host = match host_port.rsplit_once(':') {
+        Some((host, port)) if port.bytes().all(|b| b.is_ascii_digit()) => host,
+        _ => host_port,
+    };
+
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    ServerName::try_from(host.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use http::Method;
+    use http_body_util::Full;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::TlsConnector;
+    use crate::{
+        connectors::test_certificates::{get_key, TestCertificates},
+        http1::{MyHttpClient, MyHttpRequestBuilder},
+        http2::MyHttp2Client,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn http1_over_mutual_tls() {
+        let certificates = TestCertificates::generate();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+        let acceptor = tokio_rustls::TlsAcceptor::from(certificates.get_server_config(true));
+
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut tls = acceptor.accept(socket).await.unwrap();
+            assert!(tls.get_ref().1.peer_certificates().is_some());
+
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !received.ends_with(b"\r\n\r\n") {
+                let read = tokio::io::AsyncReadExt::read(&mut tls, &mut buf)
+                    .await
+                    .unwrap();
+                assert!(read > 0);
+                received.extend_from_slice(&buf[..read]);
+            }
+
+            tls.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            tls.flush().await.unwrap();
+            let _ = tokio::io::AsyncReadExt::read(&mut tls, &mut buf).await;
+        });
+
+        // The certificate is issued for localhost, not for the IP address
+        let mut connector = TlsConnector::new(remote_endpoint, certificates.get_root_store());
+        connector.set_server_name("localhost").unwrap();
+        connector
+            .set_client_auth(
+                vec![certificates.client.0.clone()],
+                get_key(&certificates.client.1),
+            )
+            .unwrap();
+
+        let client = MyHttpClient::new(connector);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn http2_is_negotiated_with_alpn() {
+        let certificates = TestCertificates::generate();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+        let acceptor = tokio_rustls::TlsAcceptor::from(certificates.get_server_config(false));
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let tls = acceptor.accept(socket).await.unwrap();
+            assert_eq!(tls.get_ref().1.alpn_protocol(), Some(&b"h2"[..]));
+
+            let service = hyper::service::service_fn(|_req| async {
+                Ok::<_, std::convert::Infallible>(hyper::Response::new(Full::new(
+                    Bytes::from_static(b"h2"),
+                )))
+            });
+
+            let _ = hyper::server::conn::http2::Builder::new(hyper_util::rt::TokioExecutor::new())
+                .serve_connection(hyper_util::rt::TokioIo::new(tls), service)
+                .await;
+        });
+
+        let mut connector = TlsConnector::new(remote_endpoint, certificates.get_root_store());
+        connector.set_server_name("localhost").unwrap();
+        connector.set_alpn_protocols(&[b"h2"]);
+
+        let client = MyHttp2Client::new(connector);
+        let req = hyper::Request::builder()
+            .uri("https://localhost/")
+            .body(Full::new(Bytes::new()))
+            .unwrap();
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+}
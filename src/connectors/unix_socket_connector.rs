@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::{
+    io::{ReadHalf, WriteHalf},
+    net::UnixStream,
+};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+const DEFAULT_REMOTE_ENDPOINT: &str = "localhost:80";
+
+/// Connector to a server listening on a Unix domain socket, e.g. the Docker API.
+pub struct UnixSocketConnector {
+    path: PathBuf,
+    remote_endpoint: String,
+    debug: bool,
+}
+
+impl UnixSocketConnector {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            remote_endpoint: DEFAULT_REMOTE_ENDPOINT.to_string(),
+            debug: false,
+        }
+    }
+
+    /// The endpoint the clients see for this socket: it names the connection in
+    /// metrics, is the origin for redirects and the `Host` of requests built with
+    /// [`crate::http1::MyHttpClient::create_request_builder`]. `localhost:80` by
+    /// default.
+    ///
+    /// Panics if `remote_endpoint` is not a valid remote endpoint.
+    pub fn set_remote_endpoint(&mut self, remote_endpoint: impl Into<String>) {
+        let remote_endpoint = remote_endpoint.into();
+
+        if let Err(err) = RemoteEndpoint::try_parse(remote_endpoint.as_str()) {
+            panic!("Invalid remote endpoint '{}': {:?}", remote_endpoint, err);
+        }
+
+        self.remote_endpoint = remote_endpoint;
+    }
+
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+}
+
+#[async_trait::async_trait]
+impl MyHttpClientConnector<UnixStream> for UnixSocketConnector {
+    async fn connect(&self) -> Result<UnixStream, MyHttpClientError> {
+        UnixStream::connect(&self.path).await.map_err(|err| {
+            MyHttpClientError::CanNotConnectToRemoteHost(format!(
+                "{}: {}",
+                self.path.display(),
+                err
+            ))
+        })
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        RemoteEndpoint::try_parse(self.remote_endpoint.as_str()).unwrap()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    fn reunite(read: ReadHalf<UnixStream>, write: WriteHalf<UnixStream>) -> UnixStream {
+        read.unsplit(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::Method;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::UnixListener,
+    };
+
+    use super::UnixSocketConnector;
+    use crate::http1::MyHttpClient;
+
+    #[tokio::test]
+    async fn sends_request_over_unix_socket() {
+        let path =
+            std::env::temp_dir().join(format!("my-http-client-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !received.ends_with(b"\r\n\r\n") {
+                let read = socket.read(&mut buf).await.unwrap();
+                assert!(read > 0);
+                received.extend_from_slice(&buf[..read]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+            received
+        });
+
+        let client = MyHttpClient::new(UnixSocketConnector::new(&path));
+        let req = client.create_request_builder(Method::GET, "/_ping").build();
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&received).unwrap(),
+            "GET /_ping HTTP/1.1\r\nHost: localhost:80\r\n\r\n"
+        );
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
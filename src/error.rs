@@ -4,12 +4,27 @@ use std::time::Duration;
 pub enum MyHttpClientError {
     CanNotConnectToRemoteHost(String),
     UpgradedToWebSocket,
+    /// The connection was lost after the request was written, or while it was
+    /// being written: the upstream may have started executing it
     Disconnected,
+    /// The connection was lost before any byte of the request was written, so
+    /// repeating it is safe for any method
+    RequestNotSent,
     Disposed,
     RequestTimeout(Duration),
     CanNotExecuteRequest(String),
     InvalidHttpHandshake(String),
     HyperWebsocket(hyper_tungstenite::HyperWebsocket),
+    /// The redirect chain was longer than the limit of the redirect policy, which
+    /// is carried here
+    TooManyRedirects(usize),
+    /// The connection kept dropping: the request gave up after this many reconnects
+    ReconnectsExhausted(usize),
+    /// The circuit of this host and port is open: see [`crate::circuit_breaker::CircuitBreaker`]
+    CircuitBreakerOpen(String),
+    /// The limit of requests waiting for responses on one connection is reached:
+    /// see [`crate::http1::MaxInFlightMode::Reject`]
+    TooManyRequestsInFlight(usize),
 }
 
 impl MyHttpClientError {
@@ -18,6 +33,104 @@ impl MyHttpClientError {
     }
 
     pub fn is_retryable(&self) -> bool {
-        matches!(self, MyHttpClientError::Disconnected)
+        matches!(
+            self,
+            MyHttpClientError::Disconnected | MyHttpClientError::RequestNotSent
+        )
     }
 }
+
+/// A request could not be built from the given parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestBuildError {
+    /// The request target does not fit the request line: see
+    /// [`crate::http1::MyHttpRequestBuilder::try_new`]
+    InvalidRequestTarget {
+        target: String,
+        reason: &'static str,
+    },
+    /// The header name is empty or has bytes other than token characters
+    InvalidHeaderName {
+        name: String,
+    },
+    /// The header value has a control byte other than HTAB or a DEL. CR and LF
+    /// would end the header
+    InvalidHeaderValue {
+        name: String,
+        forbidden_byte: u8,
+    },
+    /// A typed helper would add a second header that must appear once
+    DuplicateHeader {
+        name: String,
+    },
+    /// The cookie name is not a token or its value has bytes outside of cookie-octet
+    InvalidCookie {
+        name: String,
+    },
+    DuplicateCookie {
+        name: String,
+    },
+    /// The last byte of a range is before its first one
+    InvalidRange {
+        start: u64,
+        end: u64,
+    },
+    /// The framing headers set by the caller do not fit the body stream
+    InvalidBodyFraming {
+        reason: &'static str,
+    },
+    /// The cargo feature of the `Content-Encoding` is not enabled
+    UnsupportedContentEncoding {
+        encoding: &'static str,
+    },
+}
+
+impl std::fmt::Display for RequestBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestBuildError::InvalidRequestTarget { target, reason } => {
+                write!(f, "Invalid request target '{}': {}", target, reason)
+            }
+            RequestBuildError::InvalidHeaderName { name } => {
+                if name.is_empty() {
+                    write!(f, "HTTP header name must not be empty")
+                } else {
+                    write!(f, "HTTP header name '{}' contains forbidden bytes", name)
+                }
+            }
+            RequestBuildError::InvalidHeaderValue {
+                name,
+                forbidden_byte,
+            } => write!(
+                f,
+                "HTTP header value{} contains forbidden control byte 0x{:02x} (header injection)",
+                if name.is_empty() {
+                    String::new()
+                } else {
+                    format!(" of '{}'", name)
+                },
+                forbidden_byte
+            ),
+            RequestBuildError::DuplicateHeader { name } => {
+                write!(f, "HTTP header '{}' is already set", name)
+            }
+            RequestBuildError::InvalidCookie { name } => write!(f, "Invalid cookie '{}'", name),
+            RequestBuildError::DuplicateCookie { name } => {
+                write!(f, "Cookie '{}' is already set", name)
+            }
+            RequestBuildError::InvalidRange { start, end } => {
+                write!(f, "Range end {} is before its start {}", end, start)
+            }
+            RequestBuildError::InvalidBodyFraming { reason } => {
+                write!(f, "Invalid request body framing: {}", reason)
+            }
+            RequestBuildError::UnsupportedContentEncoding { encoding } => write!(
+                f,
+                "Content-Encoding '{}' is not enabled by cargo features",
+                encoding
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RequestBuildError {}
@@ -1,3 +1,5 @@
+use crate::RequestBuildError;
+
 pub trait MyHttpClientHeaders {
     fn copy_to(&self, buf: &mut Vec<u8>);
 }
@@ -24,14 +26,71 @@ impl MyHttpClientHeadersBuilder {
         }
     }
 
+    /// Panics if `name` or `value` is invalid: see [`Self::try_add_header`].
     pub fn add_header(&mut self, name: &str, value: &str) -> HeaderValuePosition {
         write_header(&mut self.headers, name, value)
     }
 
+    pub fn try_add_header(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<HeaderValuePosition, RequestBuildError> {
+        try_write_header(&mut self.headers, name, value.as_bytes())
+    }
+
+    /// `Authorization: Bearer <token>`
+    pub fn bearer_auth(&mut self, token: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_bearer_auth(&mut self.headers, token)
+    }
+
+    /// `Authorization: Basic` with `user_name:password` in base64
+    pub fn basic_auth(&mut self, user_name: &str, password: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_basic_auth(&mut self.headers, user_name, password)
+    }
+
+    pub fn content_type(&mut self, content_type: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_content_type(&mut self.headers, content_type)
+    }
+
+    pub fn accept(&mut self, accept: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_accept(&mut self.headers, accept)
+    }
+
+    pub fn user_agent(&mut self, user_agent: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_user_agent(&mut self.headers, user_agent)
+    }
+
+    /// `Range: bytes=<start>-<end>`, open-ended when `end` is `None`. Both ends
+    /// are inclusive.
+    pub fn range(&mut self, start: u64, end: Option<u64>) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_range(&mut self.headers, start, end)
+    }
+
+    /// `entity_tag` is sent as is, e.g. `"abc"`, `W/"abc"` or `*`.
+    pub fn if_none_match(&mut self, entity_tag: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_if_none_match(&mut self.headers, entity_tag)
+    }
+
+    /// `time` is sent as an HTTP-date, to the second.
+    pub fn if_modified_since(
+        &mut self,
+        time: std::time::SystemTime,
+    ) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_if_modified_since(&mut self.headers, time)
+    }
+
+    /// Adds `name=value` to the `Cookie` header, which holds all cookies of the
+    /// request. Adding to an existing header moves the headers after it, so their
+    /// earlier [`HeaderValuePosition`]s no longer apply.
+    pub fn cookie(&mut self, name: &str, value: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_cookie(&mut self.headers, name, value)
+    }
+
+    /// Empty if `value_position` no longer falls on whole characters.
     pub fn get_value(&self, value_position: &HeaderValuePosition) -> &str {
-        unsafe {
-            std::str::from_utf8_unchecked(&self.headers[value_position.start..value_position.end])
-        }
+        std::str::from_utf8(&self.headers[value_position.start..value_position.end])
+            .unwrap_or_default()
     }
 
     pub fn iter(&self) -> MyHttpClientHeadersBuilderIterator<'_> {
@@ -105,24 +164,40 @@ impl<'s> Iterator for MyHttpClientHeadersBuilderIterator<'s> {
 }
 
 pub fn validate_header_name(name: &str) {
-    if name.is_empty() {
-        panic!("HTTP header name must not be empty");
-    }
-    for &b in name.as_bytes() {
-        if !is_valid_header_name_byte(b) {
-            panic!("HTTP header name contains forbidden byte 0x{:02x}", b);
-        }
+    if let Err(err) = try_validate_header_name(name) {
+        panic!("{}", err);
     }
 }
 
 pub fn validate_header_value(value: &str) {
-    for &b in value.as_bytes() {
-        if b == b'\r' || b == b'\n' || b == 0 {
-            panic!(
-                "HTTP header value contains forbidden control byte 0x{:02x} (header injection)",
-                b
-            );
-        }
+    if let Err(err) = try_validate_header_value("", value.as_bytes()) {
+        panic!("{}", err);
+    }
+}
+
+pub fn try_validate_header_name(name: &str) -> Result<(), RequestBuildError> {
+    if name.is_empty() || !name.bytes().all(is_valid_header_name_byte) {
+        return Err(RequestBuildError::InvalidHeaderName {
+            name: name.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Control bytes other than HTAB are rejected, DEL too, the same bytes
+/// `http::HeaderValue::from_bytes` rejects. Obs-text (0x80-0xFF) that is not
+/// UTF-8 is accepted.
+pub fn try_validate_header_value(name: &str, value: &[u8]) -> Result<(), RequestBuildError> {
+    match value
+        .iter()
+        .find(|&&b| (b < b' ' && b != b'\t') || b == 0x7f)
+    {
+        Some(&forbidden_byte) => Err(RequestBuildError::InvalidHeaderValue {
+            name: name.to_string(),
+            forbidden_byte,
+        }),
+        None => Ok(()),
     }
 }
 
@@ -136,12 +211,32 @@ fn is_valid_header_name_byte(b: u8) -> bool {
 }
 
 pub fn write_header(dest: &mut Vec<u8>, name: &str, value: &str) -> HeaderValuePosition {
-    validate_header_name(name);
-    validate_header_value(value);
+    match try_write_header(dest, name, value.as_bytes()) {
+        Ok(position) => position,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+pub fn try_write_header(
+    dest: &mut Vec<u8>,
+    name: &str,
+    value: &[u8],
+) -> Result<HeaderValuePosition, RequestBuildError> {
+    try_validate_header_name(name)?;
+    try_validate_header_value(name, value)?;
+    Ok(write_header_unchecked(dest, name, value))
+}
+
+/// For names and values validated already, e.g. the ones of an `http::HeaderMap`.
+pub(crate) fn write_header_unchecked(
+    dest: &mut Vec<u8>,
+    name: &str,
+    value: &[u8],
+) -> HeaderValuePosition {
     dest.extend_from_slice(name.as_bytes());
     dest.extend_from_slice(": ".as_bytes());
     let start = dest.len();
-    dest.extend_from_slice(value.as_bytes());
+    dest.extend_from_slice(value);
     let end = dest.len();
     dest.extend_from_slice(crate::CL_CR);
     HeaderValuePosition { start, end }
@@ -150,6 +245,7 @@ pub fn write_header(dest: &mut Vec<u8>, name: &str, value: &str) -> HeaderValueP
 #[cfg(test)]
 mod tests {
     use super::MyHttpClientHeadersBuilder;
+    use crate::RequestBuildError;
 
     #[test]
     fn test_iterators() {
@@ -169,4 +265,38 @@ mod tests {
 
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn invalid_headers_are_errors() {
+        let mut headers = MyHttpClientHeadersBuilder::new();
+
+        assert_eq!(
+            headers.try_add_header("Bad Name", "value").err(),
+            Some(RequestBuildError::InvalidHeaderName {
+                name: "Bad Name".to_string()
+            })
+        );
+        assert_eq!(
+            headers.try_add_header("X-Injected", "a\r\nb: c").err(),
+            Some(RequestBuildError::InvalidHeaderValue {
+                name: "X-Injected".to_string(),
+                forbidden_byte: b'\r'
+            })
+        );
+        assert!(headers.try_add_header("X-Name", "caf\u{e9}").is_ok());
+        assert_eq!(headers.as_str(), "X-Name: caf\u{e9}\r\n");
+    }
+
+    #[test]
+    fn header_values_reject_the_bytes_http_rejects() {
+        for b in 0..=u8::MAX {
+            let value = [b'a', b, b'z'];
+            assert_eq!(
+                super::try_validate_header_value("X-Byte", &value).is_ok(),
+                http::HeaderValue::from_bytes(&value).is_ok(),
+                "byte 0x{:02x}",
+                b
+            );
+        }
+    }
 }
@@ -1,11 +1,12 @@
 use std::time::Duration;
 
 use bytes::Bytes;
+use http::HeaderMap;
 
 use http_body_util::{BodyExt, StreamBody};
 use tokio::io::ReadHalf;
 
-use crate::http1::{HttpParseError, TcpBuffer, MAX_CHUNK_SIZE};
+use crate::http1::{HttpParseError, MyHttpClientLimits, TcpBuffer};
 
 #[derive(Debug, Clone, Copy)]
 pub enum ChunksReadingMode {
@@ -30,14 +31,34 @@ pub fn create_chunked_body_response(
     (sender, chunked_body_response)
 }
 
+/// Hands one piece of a streamed body to the response receiver. Fails when the
+/// caller dropped the body, which stops the reader.
+pub async fn send_body_chunk(
+    sender: &mut ChunksSender,
+    chunk: Bytes,
+) -> Result<(), HttpParseError> {
+    use futures::SinkExt;
+
+    let err = sender.send(Ok(hyper::body::Frame::data(chunk))).await;
+
+    if let Err(err) = err {
+        return Err(HttpParseError::error(format!(
+            "Error sending response chunk: {:?}",
+            err
+        )));
+    }
+
+    Ok(())
+}
+
 pub async fn read_chunked_body<TStream: tokio::io::AsyncRead>(
     read_stream: &mut ReadHalf<TStream>,
     tcp_buffer: &mut TcpBuffer,
     mut sender: futures::channel::mpsc::Sender<Result<hyper::body::Frame<Bytes>, hyper::Error>>,
     read_timeout: Duration,
     print_input_http_stream: bool,
+    limits: &MyHttpClientLimits,
 ) -> Result<(), HttpParseError> {
-    use futures::SinkExt;
     loop {
         let chunk_size = super::super::read_with_timeout::read_until_crlf(
             read_stream,
@@ -53,22 +74,26 @@ pub async fn read_chunked_body<TStream: tokio::io::AsyncRead>(
         }
 
         if chunk_size == 0 {
-            super::super::read_with_timeout::skip_exactly(
+            let trailers = read_trailers(
                 read_stream,
                 tcp_buffer,
-                2,
                 read_timeout,
                 print_input_http_stream,
+                limits,
             )
             .await?;
 
+            if !trailers.is_empty() {
+                send_trailers(&mut sender, trailers).await?;
+            }
+
             return Ok(());
         }
 
-        if chunk_size > MAX_CHUNK_SIZE {
+        if chunk_size > limits.max_chunk_size {
             return Err(HttpParseError::invalid_payload(format!(
                 "Chunk size {} exceeds limit {}",
-                chunk_size, MAX_CHUNK_SIZE
+                chunk_size, limits.max_chunk_size
             )));
         }
 
@@ -92,16 +117,7 @@ pub async fn read_chunked_body<TStream: tokio::io::AsyncRead>(
             .await?;
         }
 
-        let err = sender
-            .send(Ok(hyper::body::Frame::data(chunk.into())))
-            .await;
-
-        if let Err(err) = err {
-            return Err(HttpParseError::error(format!(
-                "Error sending response chunk: {:?}",
-                err
-            )));
-        }
+        send_body_chunk(&mut sender, chunk.into()).await?;
 
         super::super::read_with_timeout::skip_exactly(
             read_stream,
@@ -114,6 +130,79 @@ pub async fn read_chunked_body<TStream: tokio::io::AsyncRead>(
     }
 }
 
+/// Reads the trailer section after the last chunk, up to and including the
+/// empty line that ends the body.
+async fn read_trailers<TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    tcp_buffer: &mut TcpBuffer,
+    read_timeout: Duration,
+    print_input_http_stream: bool,
+    limits: &MyHttpClientLimits,
+) -> Result<HeaderMap, HttpParseError> {
+    let mut trailers = HeaderMap::new();
+    let mut trailers_count = 0;
+    let mut trailers_total_size = 0;
+
+    loop {
+        let end_of_trailers = super::super::read_with_timeout::read_line_with_limit(
+            read_stream,
+            tcp_buffer,
+            read_timeout,
+            limits.max_header_line_size,
+            |line| {
+                trailers_total_size += line.len() + crate::CL_CR.len();
+                if trailers_total_size > limits.max_trailers_total_size {
+                    return Err(HttpParseError::invalid_payload(format!(
+                        "Response trailers exceed limit {}",
+                        limits.max_trailers_total_size
+                    )));
+                }
+
+                if line.is_empty() {
+                    return Ok(true);
+                }
+
+                trailers_count += 1;
+                if trailers_count > limits.max_trailers_count {
+                    return Err(HttpParseError::invalid_payload(format!(
+                        "Response has more than {} trailers",
+                        limits.max_trailers_count
+                    )));
+                }
+
+                crate::http1::parse_http_trailer(&mut trailers, line)?;
+                Ok(false)
+            },
+            print_input_http_stream,
+        )
+        .await?;
+
+        if end_of_trailers {
+            return Ok(trailers);
+        }
+    }
+}
+
+async fn send_trailers(
+    sender: &mut ChunksSender,
+    trailers: HeaderMap,
+) -> Result<(), HttpParseError> {
+    use futures::SinkExt;
+
+    let err = sender
+        .send(Ok(hyper::body::Frame::trailers(trailers)))
+        .await;
+
+    if let Err(err) = err {
+        return Err(HttpParseError::error(format!(
+            "Error sending response trailers: {:?}",
+            err
+        )));
+    }
+
+    Ok(())
+}
+
 fn parse_chunk_size(src: &[u8]) -> Result<usize, HttpParseError> {
     let mut end_of_hex = src.len();
 
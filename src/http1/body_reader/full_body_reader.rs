@@ -2,9 +2,11 @@ use std::time::Duration;
 
 use bytes::Bytes;
 use http_body_util::combinators::BoxBody;
-use tokio::io::ReadHalf;
+use tokio::io::{AsyncReadExt, ReadHalf};
 
-use crate::http1::{HttpParseError, TcpBuffer, MAX_RESPONSE_BODY_SIZE};
+use crate::http1::{HttpParseError, MyHttpClientLimits, TcpBuffer};
+
+use super::{send_body_chunk, ChunksSender, STREAM_READ_BUFFER_SIZE};
 
 pub async fn read_full_body<TStream: tokio::io::AsyncRead>(
     read_stream: &mut ReadHalf<TStream>,
@@ -12,15 +14,16 @@ pub async fn read_full_body<TStream: tokio::io::AsyncRead>(
     builder: http::response::Builder,
     body_size: usize,
     read_timeout: Duration,
+    limits: &MyHttpClientLimits,
 ) -> Result<http::Response<BoxBody<Bytes, String>>, HttpParseError> {
     if body_size == 0 {
         return Ok(crate::utils::into_empty_body(builder));
     }
 
-    if body_size > MAX_RESPONSE_BODY_SIZE {
+    if body_size > limits.max_response_body_size {
         return Err(HttpParseError::invalid_payload(format!(
             "Response body size {} exceeds limit {}",
-            body_size, MAX_RESPONSE_BODY_SIZE
+            body_size, limits.max_response_body_size
         )));
     }
 
@@ -46,3 +49,53 @@ pub async fn read_full_body<TStream: tokio::io::AsyncRead>(
 
     Ok(crate::utils::into_body(builder, body))
 }
+
+/// Streams a `Content-Length` body through `sender` as it arrives instead of
+/// collecting it first. Used for bodies above the client's streaming threshold,
+/// so the response is handed to the caller before the last byte is read; the
+/// in-memory `max_response_body_size` limit does not apply here.
+pub async fn stream_full_body<TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    tcp_buffer: &mut TcpBuffer,
+    mut sender: ChunksSender,
+    body_size: usize,
+    read_timeout: Duration,
+) -> Result<(), HttpParseError> {
+    let mut remains_to_download = body_size;
+
+    if let Some(remain_buffer) = tcp_buffer.get_as_much_as_possible(remains_to_download) {
+        remains_to_download -= remain_buffer.len();
+        let chunk = Bytes::copy_from_slice(remain_buffer);
+        send_body_chunk(&mut sender, chunk).await?;
+    }
+
+    let mut read_buf = vec![0u8; STREAM_READ_BUFFER_SIZE];
+
+    while remains_to_download > 0 {
+        let to_read = remains_to_download.min(read_buf.len());
+        let future = read_stream.read(&mut read_buf[..to_read]);
+
+        let result = tokio::time::timeout(read_timeout, future).await;
+
+        if result.is_err() {
+            return Err(HttpParseError::ReadingTimeout(read_timeout));
+        }
+
+        match result.unwrap() {
+            Ok(0) => return Err(HttpParseError::Disconnected),
+            Ok(read) => {
+                remains_to_download -= read;
+                let chunk = Bytes::copy_from_slice(&read_buf[..read]);
+                send_body_chunk(&mut sender, chunk).await?;
+            }
+            Err(err) => {
+                return Err(HttpParseError::error(format!(
+                    "Error reading streamed body: {:?}",
+                    err
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -11,6 +11,10 @@ pub use until_close_body_reader::*;
 mod full_body_reader_inner;
 pub use full_body_reader_inner::*;
 
+/// Size of the socket reads used while streaming a length-based or
+/// close-delimited body; each read is delivered as one body frame.
+const STREAM_READ_BUFFER_SIZE: usize = 64 * 1024;
+
 #[derive(Debug)]
 pub enum BodyReader {
     LengthBased {
@@ -30,9 +34,10 @@ pub enum BodyReader {
     },
     /// A non-final interim (1xx) response other than a websocket upgrade — e.g.
     /// `100 Continue` or `103 Early Hints` (RFC 9110 §15.2). It carries no body
-    /// and must NOT complete the pending request: the read loop discards it and
-    /// keeps reading for the real (>= 200) final response.
-    Interim,
+    /// and must NOT complete the pending request: the read loop keeps reading for
+    /// the real (>= 200) final response. Carries the status and headers of the
+    /// interim response.
+    Interim(http::Response<()>),
     WebSocketUpgrade(WebSocketUpgradeBuilder),
 }
 
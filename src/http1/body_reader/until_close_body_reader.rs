@@ -4,7 +4,9 @@ use bytes::Bytes;
 use http_body_util::combinators::BoxBody;
 use tokio::io::{AsyncReadExt, ReadHalf};
 
-use crate::http1::{HttpParseError, TcpBuffer, MAX_RESPONSE_BODY_SIZE};
+use crate::http1::{HttpParseError, MyHttpClientLimits, TcpBuffer};
+
+use super::{send_body_chunk, ChunksSender, STREAM_READ_BUFFER_SIZE};
 
 /// Reads a close-delimited response body (RFC 9112 §6.3).
 ///
@@ -20,7 +22,85 @@ pub async fn read_until_close<TStream: tokio::io::AsyncRead>(
     tcp_buffer: &mut TcpBuffer,
     builder: http::response::Builder,
     read_timeout: Duration,
+    limits: &MyHttpClientLimits,
 ) -> Result<http::Response<BoxBody<Bytes, String>>, HttpParseError> {
+    let (body, eof) = collect_until_close(
+        read_stream,
+        tcp_buffer,
+        limits.max_response_body_size,
+        read_timeout,
+    )
+    .await?;
+
+    if !eof {
+        return Err(HttpParseError::invalid_payload(format!(
+            "Close-delimited response body exceeds limit {}",
+            limits.max_response_body_size
+        )));
+    }
+
+    Ok(crate::utils::into_body(builder, body))
+}
+
+pub enum UntilCloseBody {
+    /// The connection closed before the body grew past the threshold.
+    Complete(crate::HyperResponse),
+    /// The body is larger than the threshold. `head` holds what was read so far;
+    /// the rest is to be streamed with [`stream_until_close`].
+    ExceedsThreshold {
+        builder: http::response::Builder,
+        head: Vec<u8>,
+    },
+}
+
+/// Reads a close-delimited body like [`read_until_close`], but gives up
+/// collecting it once it grows past `threshold`, so the caller can switch to
+/// streaming instead of buffering an unbounded body.
+pub async fn read_until_close_or_threshold<TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    tcp_buffer: &mut TcpBuffer,
+    builder: http::response::Builder,
+    threshold: usize,
+    read_timeout: Duration,
+) -> Result<UntilCloseBody, HttpParseError> {
+    let (head, eof) = collect_until_close(read_stream, tcp_buffer, threshold, read_timeout).await?;
+
+    if eof {
+        return Ok(UntilCloseBody::Complete(crate::utils::into_body(
+            builder, head,
+        )));
+    }
+
+    Ok(UntilCloseBody::ExceedsThreshold { builder, head })
+}
+
+/// Streams the rest of a close-delimited body through `sender` until EOF.
+pub async fn stream_until_close<TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    mut sender: ChunksSender,
+    read_timeout: Duration,
+) -> Result<(), HttpParseError> {
+    let mut read_buf = vec![0u8; STREAM_READ_BUFFER_SIZE];
+
+    loop {
+        match read_with_timeout(read_stream, &mut read_buf, read_timeout).await? {
+            0 => return Ok(()),
+            read => {
+                let chunk = Bytes::copy_from_slice(&read_buf[..read]);
+                send_body_chunk(&mut sender, chunk).await?;
+            }
+        }
+    }
+}
+
+/// Collects body bytes until EOF (`true`) or until more than `max_size` bytes
+/// were collected (`false`).
+async fn collect_until_close<TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    tcp_buffer: &mut TcpBuffer,
+    max_size: usize,
+    read_timeout: Duration,
+) -> Result<(Vec<u8>, bool), HttpParseError> {
     let mut body: Vec<u8> = Vec::new();
 
     // Drain whatever the header parser already read into the buffer beyond the
@@ -32,47 +112,43 @@ pub async fn read_until_close<TStream: tokio::io::AsyncRead>(
         }
     }
 
-    if body.len() > MAX_RESPONSE_BODY_SIZE {
-        return Err(HttpParseError::invalid_payload(format!(
-            "Close-delimited response body exceeds limit {}",
-            MAX_RESPONSE_BODY_SIZE
-        )));
+    if body.len() > max_size {
+        return Ok((body, false));
     }
 
     // Heap-allocated so it does not inflate the spawned read-loop future's
     // stack frame (matches how `read_full_body` reads into a heap `Vec`).
-    let mut read_buf = vec![0u8; 65536];
+    let mut read_buf = vec![0u8; STREAM_READ_BUFFER_SIZE];
 
     loop {
-        let future = read_stream.read(&mut read_buf);
-
-        let result = tokio::time::timeout(read_timeout, future).await;
-
-        if result.is_err() {
-            return Err(HttpParseError::ReadingTimeout(read_timeout));
-        }
-
-        match result.unwrap() {
-            Ok(0) => {
-                // Connection closed cleanly: the body is complete.
-                return Ok(crate::utils::into_body(builder, body));
-            }
-            Ok(read) => {
+        match read_with_timeout(read_stream, &mut read_buf, read_timeout).await? {
+            // Connection closed cleanly: the body is complete.
+            0 => return Ok((body, true)),
+            read => {
                 body.extend_from_slice(&read_buf[..read]);
 
-                if body.len() > MAX_RESPONSE_BODY_SIZE {
-                    return Err(HttpParseError::invalid_payload(format!(
-                        "Close-delimited response body exceeds limit {}",
-                        MAX_RESPONSE_BODY_SIZE
-                    )));
+                if body.len() > max_size {
+                    return Ok((body, false));
                 }
             }
-            Err(err) => {
-                return Err(HttpParseError::error(format!(
-                    "Error reading close-delimited body: {:?}",
-                    err
-                )));
-            }
         }
     }
 }
+
+async fn read_with_timeout<TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    read_buf: &mut [u8],
+    read_timeout: Duration,
+) -> Result<usize, HttpParseError> {
+    let future = read_stream.read(read_buf);
+
+    let result = tokio::time::timeout(read_timeout, future).await;
+
+    if result.is_err() {
+        return Err(HttpParseError::ReadingTimeout(read_timeout));
+    }
+
+    result.unwrap().map_err(|err| {
+        HttpParseError::error(format!("Error reading close-delimited body: {:?}", err))
+    })
+}
@@ -13,12 +13,19 @@ pub async fn read_headers<TStream: tokio::io::AsyncRead>(
     read_timeout: Duration,
     print_input_http_stream: bool,
     request_method: Option<http::Method>,
+    limits: &MyHttpClientLimits,
 ) -> Result<BodyReader, HttpParseError> {
-    let (status_code, version) = super::read_with_timeout::read_until_crlf(
+    let mut headers_total_size: usize = 0;
+
+    let (status_code, version) = super::read_with_timeout::read_line_with_limit(
         read_stream,
         tcp_buffer,
         read_timeout,
-        parse_http_response_first_line,
+        limits.max_header_line_size,
+        |line| {
+            headers_total_size += line.len() + CRLF_LEN;
+            parse_http_response_first_line(line)
+        },
         print_input_http_stream,
     )
     .await?;
@@ -33,19 +40,30 @@ pub async fn read_headers<TStream: tokio::io::AsyncRead>(
     loop {
         let result = match tcp_buffer.read_until_crlf() {
             Some(line) => {
+                super::read_with_timeout::check_line_size(line.len(), limits.max_header_line_size)?;
+                headers_total_size += line.len() + CRLF_LEN;
+                check_headers_total_size(headers_total_size, limits)?;
+
                 if line.is_empty() {
                     break;
                 }
                 headers_count += 1;
-                if headers_count > super::MAX_RESPONSE_HEADERS_COUNT {
+                if headers_count > limits.max_headers_count {
                     return Err(HttpParseError::invalid_payload(format!(
                         "Response has more than {} headers",
-                        super::MAX_RESPONSE_HEADERS_COUNT
+                        limits.max_headers_count
                     )));
                 }
                 parse_http_header(builder, line)?
             }
             None => {
+                let pending_line_size = tcp_buffer.get_buf().len();
+                super::read_with_timeout::check_line_size(
+                    pending_line_size,
+                    limits.max_header_line_size,
+                )?;
+                check_headers_total_size(headers_total_size + pending_line_size, limits)?;
+
                 super::read_with_timeout::read_to_buffer(
                     read_stream,
                     tcp_buffer,
@@ -77,7 +95,12 @@ pub async fn read_headers<TStream: tokio::io::AsyncRead>(
         // be skipped without completing the request (RFC 9110 §15.2); the read
         // loop keeps reading for the real >= 200 response. Checked before the
         // bodyless arm below so a 1xx is *skipped* rather than *delivered empty*.
-        _ if status_code.is_informational() => Ok(BodyReader::Interim),
+        _ if status_code.is_informational() => {
+            let response = builder.body(()).map_err(|err| {
+                HttpParseError::invalid_payload(format!("Invalid interim response: {}", err))
+            })?;
+            Ok(BodyReader::Interim(response))
+        }
         // HEAD / 204 / 304 / a 2xx to CONNECT never carry a body, regardless of
         // any Content-Length or Transfer-Encoding header.
         _ if !body_expected => Ok(BodyReader::LengthBased {
@@ -95,6 +118,22 @@ pub async fn read_headers<TStream: tokio::io::AsyncRead>(
     }
 }
 
+const CRLF_LEN: usize = 2;
+
+fn check_headers_total_size(
+    headers_total_size: usize,
+    limits: &MyHttpClientLimits,
+) -> Result<(), HttpParseError> {
+    if headers_total_size > limits.max_headers_total_size {
+        return Err(HttpParseError::invalid_payload(format!(
+            "Response headers exceed limit {}",
+            limits.max_headers_total_size
+        )));
+    }
+
+    Ok(())
+}
+
 /// Determines whether a response is allowed to carry a message body, per the
 /// RFC 9112 §6.3 precedence rules that depend on the request method and the
 /// response status code.
@@ -1,4 +1,4 @@
-use http::HeaderValue;
+use http::{HeaderMap, HeaderName, HeaderValue};
 use rust_extensions::slice_of_u8_utils::SliceOfU8Ext;
 
 use crate::http1::{DetectedBodySize, HttpParseError};
@@ -8,31 +8,7 @@ pub fn parse_http_header(
     src: &[u8],
 ) -> Result<(http::response::Builder, DetectedBodySize), HttpParseError> {
     let mut body_size = DetectedBodySize::Unknown;
-    let pos = src.find_byte_pos(b':', 0);
-
-    if pos.is_none() {
-        return Err(HttpParseError::invalid_payload(
-            "Can not find separator between HTTP header and Http response",
-        ));
-    }
-
-    let pos = pos.unwrap();
-
-    let name = &src[..pos];
-    let name = std::str::from_utf8(name).map_err(|_| {
-        HttpParseError::invalid_payload(
-            "Invalid HTTP header name. Can not convert payload to UTF8 string",
-        )
-    })?;
-
-    let value = &src[pos + 1..];
-    let value_str = std::str::from_utf8(value).map_err(|_| {
-        HttpParseError::invalid_payload(
-            "Invalid HTTP value. Can not convert payload to UTF8 string",
-        )
-    })?;
-
-    let value_str = value_str.trim();
+    let (name, value_str) = split_http_header(src)?;
 
     if name.eq_ignore_ascii_case("Content-Length") {
         match value_str.parse() {
@@ -61,14 +37,61 @@ pub fn parse_http_header(
         body_size = DetectedBodySize::WebSocketUpgrade;
     }
 
-    let header_value = HeaderValue::from_str(value_str).map_err(|err| {
-        HttpParseError::invalid_payload(format!(
-            "Invalid Header value. {}: {}. Err: {}",
-            name, value_str, err
-        ))
-    })?;
+    let header_value = parse_header_value(name, value_str)?;
 
     builder = builder.header(name, header_value);
 
     Ok((builder, body_size))
 }
+
+/// Parses a trailer field of a chunked body. Trailer fields follow the syntax of
+/// header fields but do not affect the framing.
+pub fn parse_http_trailer(trailers: &mut HeaderMap, src: &[u8]) -> Result<(), HttpParseError> {
+    let (name, value_str) = split_http_header(src)?;
+
+    let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|err| {
+        HttpParseError::invalid_payload(format!("Invalid trailer name. {}. Err: {}", name, err))
+    })?;
+
+    let header_value = parse_header_value(name, value_str)?;
+    trailers.append(header_name, header_value);
+
+    Ok(())
+}
+
+fn split_http_header(src: &[u8]) -> Result<(&str, &str), HttpParseError> {
+    let pos = src.find_byte_pos(b':', 0);
+
+    if pos.is_none() {
+        return Err(HttpParseError::invalid_payload(
+            "Can not find separator between HTTP header and Http response",
+        ));
+    }
+
+    let pos = pos.unwrap();
+
+    let name = &src[..pos];
+    let name = std::str::from_utf8(name).map_err(|_| {
+        HttpParseError::invalid_payload(
+            "Invalid HTTP header name. Can not convert payload to UTF8 string",
+        )
+    })?;
+
+    let value = &src[pos + 1..];
+    let value_str = std::str::from_utf8(value).map_err(|_| {
+        HttpParseError::invalid_payload(
+            "Invalid HTTP value. Can not convert payload to UTF8 string",
+        )
+    })?;
+
+    Ok((name, value_str.trim()))
+}
+
+fn parse_header_value(name: &str, value_str: &str) -> Result<HeaderValue, HttpParseError> {
+    HeaderValue::from_str(value_str).map_err(|err| {
+        HttpParseError::invalid_payload(format!(
+            "Invalid Header value. {}: {}. Err: {}",
+            name, value_str, err
+        ))
+    })
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::MyHttpClientError;
+
+/// What [`super::MyHttpClient`] does with a request while the maximum amount of
+/// requests already waits for responses on the connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxInFlightMode {
+    /// Wait until one of them gets its response. The wait counts towards the
+    /// request timeout.
+    Wait,
+    /// Fail at once with [`MyHttpClientError::TooManyRequestsInFlight`].
+    Reject,
+}
+
+pub(crate) struct InFlightLimit {
+    semaphore: Arc<Semaphore>,
+    max_in_flight: usize,
+    mode: MaxInFlightMode,
+}
+
+impl InFlightLimit {
+    pub fn new(max_in_flight: usize, mode: MaxInFlightMode) -> Self {
+        let max_in_flight = max_in_flight.max(1);
+
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_in_flight)),
+            max_in_flight,
+            mode,
+        }
+    }
+
+    pub fn get_max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    pub fn get_mode(&self) -> MaxInFlightMode {
+        self.mode
+    }
+
+    /// The request stays in flight until the permit is dropped. The permit goes
+    /// to the queue with the request, so a caller that stops waiting does not
+    /// free its slot before the response is read.
+    pub async fn acquire(
+        &self,
+        deadline: tokio::time::Instant,
+        request_timeout: std::time::Duration,
+    ) -> Result<OwnedSemaphorePermit, MyHttpClientError> {
+        match self.mode {
+            MaxInFlightMode::Wait => {
+                let acquire = self.semaphore.clone().acquire_owned();
+                match tokio::time::timeout_at(deadline, acquire).await {
+                    Ok(permit) => Ok(permit.expect("The semaphore is never closed")),
+                    Err(_) => Err(MyHttpClientError::RequestTimeout(request_timeout)),
+                }
+            }
+            MaxInFlightMode::Reject => self
+                .semaphore
+                .clone()
+                .try_acquire_owned()
+                .map_err(|_| MyHttpClientError::TooManyRequestsInFlight(self.max_in_flight)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use http::Method;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::MaxInFlightMode;
+    use crate::{
+        http1::{test_utils::*, MyHttpClient, MyHttpRequestBuilder},
+        MyHttpClientError,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn request_above_limit_is_rejected() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = socket.read_to_end(&mut received).await;
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_max_in_flight(1, MaxInFlightMode::Reject);
+        let client = Arc::new(client);
+
+        let first_client = client.clone();
+        let first = tokio::spawn(async move {
+            let req = MyHttpRequestBuilder::new(Method::GET, "/first").build();
+            first_client.do_request(&req, TIMEOUT).await
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/second").build();
+        let err = client.do_request(&req, TIMEOUT).await.err().unwrap();
+        assert!(matches!(err, MyHttpClientError::TooManyRequestsInFlight(1)));
+
+        assert_eq!(first.await.unwrap().unwrap().status(), 200);
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn next_request_waits_without_pipelining() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            for _ in 0..2 {
+                let mut received = Vec::new();
+                assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+
+                // Nothing else may arrive before the response
+                let mut buf = [0u8; 64];
+                let read =
+                    tokio::time::timeout(Duration::from_millis(100), socket.read(&mut buf)).await;
+                assert!(read.is_err());
+
+                socket
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.disable_pipelining();
+        let client = Arc::new(client);
+
+        let first_client = client.clone();
+        let first = tokio::spawn(async move {
+            let req = MyHttpRequestBuilder::new(Method::GET, "/first").build();
+            first_client.do_request(&req, TIMEOUT).await
+        });
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/second").build();
+        assert_eq!(
+            client.do_request(&req, TIMEOUT).await.unwrap().status(),
+            200
+        );
+        assert_eq!(first.await.unwrap().unwrap().status(), 200);
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn timed_out_request_stays_in_flight_until_its_response() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+
+            // The first caller times out, its request still waits for the response
+            let mut buf = [0u8; 64];
+            let read =
+                tokio::time::timeout(Duration::from_millis(400), socket.read(&mut buf)).await;
+            assert!(read.is_err());
+
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            assert!(received.starts_with(b"GET /second "));
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = socket.read_to_end(&mut received).await;
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.disable_pipelining();
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/first").build();
+        let err = client
+            .do_request(&req, Duration::from_millis(100))
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, MyHttpClientError::RequestTimeout(_)));
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/second").build();
+        assert_eq!(
+            client.do_request(&req, TIMEOUT).await.unwrap().status(),
+            200
+        );
+
+        drop(client);
+        server.await.unwrap();
+    }
+}
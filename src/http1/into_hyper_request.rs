@@ -8,17 +8,33 @@ use rust_extensions::{slice_of_u8_utils::SliceOfU8Ext, str_utils::StrUtils};
 use super::*;
 
 impl MyHttpRequest {
+    /// Panics for a request built with
+    /// [`MyHttpRequestBuilder::build_with_stream`]: its body can only be sent by
+    /// [`MyHttpClient`].
     pub fn to_hyper_h1_request(&self) -> hyper::Request<Full<Bytes>> {
+        self.panic_if_body_stream();
+
         build_h1_headers(&self.headers)
             .body(Full::new(self.body.clone()))
             .unwrap()
     }
 
+    /// Panics for a request built with
+    /// [`MyHttpRequestBuilder::build_with_stream`], same as
+    /// [`Self::to_hyper_h1_request`].
     pub fn to_hyper_h2_request(&self, is_https: bool) -> hyper::Request<Full<Bytes>> {
+        self.panic_if_body_stream();
+
         build_h2_headers(&self.headers, is_https)
             .body(Full::new(self.body.clone()))
             .unwrap()
     }
+
+    fn panic_if_body_stream(&self) {
+        if self.body_stream.is_some() {
+            panic!("A request with a streamed body can not be converted to a hyper request: it would be sent without its body");
+        }
+    }
 }
 
 fn build_h1_headers(headers: &[u8]) -> Builder {
@@ -45,7 +61,7 @@ fn build_h1_headers(headers: &[u8]) -> Builder {
         let line = &headers[index..line_end_index];
         let (name, value) = extract_name_and_value(line);
 
-        builder = builder.header(name.trim(), value.trim());
+        builder = builder.header(name.trim(), value.trim_ascii());
 
         index = line_end_index + crate::CL_CR.len();
     }
@@ -79,9 +95,9 @@ fn build_h2_headers(headers: &[u8], is_https: bool) -> Builder {
         let (name, value) = extract_name_and_value(line);
 
         if name.eq_case_insensitive("host") {
-            host = Some(value.trim());
+            host = std::str::from_utf8(value.trim_ascii()).ok();
         } else {
-            builder = builder.header(name.trim(), value.trim());
+            builder = builder.header(name.trim(), value.trim_ascii());
         }
 
         index = line_end_index + crate::CL_CR.len();
@@ -121,25 +137,16 @@ fn extract_http_method_and_uri(line: &[u8]) -> (http::Method, &str) {
     (http::Method::from_str(method).unwrap(), path)
 }
 
-fn extract_name_and_value(line: &[u8]) -> (&str, &str) {
+// Values stay bytes: they may carry obs-text that is not UTF-8
+fn extract_name_and_value(line: &[u8]) -> (&str, &[u8]) {
     match line.find_byte_pos(b':', 0) {
         Some(header_separator_index) => {
             let name = &line[..header_separator_index];
             let value = &line[header_separator_index + 1..];
 
-            unsafe {
-                let name = std::str::from_utf8_unchecked(name);
-                let value = std::str::from_utf8_unchecked(value);
-
-                (name, value)
-            }
-        }
-        None => {
-            unsafe {
-                let name = std::str::from_utf8_unchecked(line);
-                (name, "")
-            }
+            (std::str::from_utf8(name).unwrap_or_default(), value)
         }
+        None => (std::str::from_utf8(line).unwrap_or_default(), &[]),
     }
 }
 
@@ -162,7 +169,10 @@ mod tests {
 
     use http::{Method, Version};
 
-    use crate::{http1::MyHttpRequest, MyHttpClientHeadersBuilder};
+    use crate::{
+        http1::{MyHttpRequest, MyHttpRequestBuilder},
+        MyHttpClientHeadersBuilder,
+    };
 
     #[test]
     fn test_converting() {
@@ -200,4 +210,19 @@ mod tests {
 
         println!("{:?}", body);
     }
+
+    #[tokio::test]
+    async fn obs_text_header_values_are_kept() {
+        let mut builder = MyHttpRequestBuilder::new(Method::GET, "/");
+        builder
+            .try_append_header_bytes("x-legacy", b"caf\xe9")
+            .unwrap();
+        let req = builder.build();
+
+        let hyper_request = req.to_hyper_h1_request();
+        assert_eq!(hyper_request.headers()["x-legacy"].as_bytes(), b"caf\xe9");
+
+        let req = MyHttpRequest::from_hyper_request(hyper_request).await;
+        assert!(req.headers.ends_with(b"x-legacy: caf\xe9\r\n"));
+    }
 }
@@ -2,20 +2,33 @@ mod my_http_client;
 use std::time::Duration;
 
 pub use my_http_client::*;
+mod my_http_client_redirect;
+mod my_http_client_retry;
 mod detected_body_size;
 pub use detected_body_size::*;
+mod my_http_client_limits;
+pub use my_http_client_limits::*;
 mod my_http_client_inner;
 pub use my_http_client_inner::*;
+mod my_http_client_pool;
+pub use my_http_client_pool::*;
+mod in_flight_limit;
+pub use in_flight_limit::*;
 mod queue_of_requests;
 mod read_loop;
 mod write_loop;
+mod write_payload;
 pub use queue_of_requests::*;
 mod my_http_response;
 pub use my_http_response::*;
 
 mod my_http_request;
 pub use my_http_request::*;
+mod my_http_request_body_stream;
+pub use my_http_request_body_stream::*;
 mod my_http_request_builder;
+mod my_http_request_trailers;
+pub use my_http_request_trailers::*;
 
 mod my_http_client_connection_context;
 pub use my_http_client_connection_context::*;
@@ -41,18 +54,28 @@ pub mod into_hyper_request;
 
 #[cfg(test)]
 mod response_framing_tests;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 const CONTENT_LENGTH_HEADER_NAME: &str = "content-length";
 
 pub const MAX_RESPONSE_BODY_SIZE: usize = 100 * 1024 * 1024;
 pub const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
 pub const MAX_RESPONSE_HEADERS_COUNT: usize = 256;
+pub const MAX_RESPONSE_HEADER_LINE_SIZE: usize = 64 * 1024;
+pub const MAX_RESPONSE_HEADERS_TOTAL_SIZE: usize = 256 * 1024;
+pub const MAX_RESPONSE_TRAILERS_COUNT: usize = 64;
+pub const MAX_RESPONSE_TRAILERS_TOTAL_SIZE: usize = 64 * 1024;
 
 /// Upper bound on consecutive interim (1xx) responses accepted before a final
 /// response, guarding against a server that pins the read loop with an endless
 /// stream of 1xx messages.
 pub const MAX_INTERIM_RESPONSES: usize = 32;
 
+/// Largest response body read and discarded for a request nobody waits for
+/// anymore, so the connection stays usable for the requests pipelined after it.
+pub const MAX_ABANDONED_RESPONSE_BODY_SIZE: usize = 1024 * 1024;
+
 /// Case-insensitive search for an HTTP header in a serialized request buffer.
 /// Skips the request line; matches lines starting with `\r\n<name>` followed by `:` (or OWS+`:`).
 pub(crate) fn headers_contains(buf: &[u8], name: &str) -> bool {
@@ -82,6 +105,78 @@ pub(crate) fn headers_contains(buf: &[u8], name: &str) -> bool {
     false
 }
 
+/// Trimmed value of the first line of header `name` (case-insensitive) in a
+/// serialized request buffer.
+pub(crate) fn get_header_value<'s>(buf: &'s [u8], name: &str) -> Option<&'s [u8]> {
+    let position = get_header_value_position(buf, name)?;
+    Some(&buf[position.start..position.end])
+}
+
+/// Same as [`get_header_value`], as a position in `buf`. The buffer may start
+/// with a request line or with the first header line: a request line never
+/// reads as `<name>:`.
+pub(crate) fn get_header_value_position(
+    buf: &[u8],
+    name: &str,
+) -> Option<crate::HeaderValuePosition> {
+    let mut line_start = 0;
+
+    while line_start < buf.len() {
+        let line_end = match buf[line_start..]
+            .windows(2)
+            .position(|itm| itm == crate::CL_CR)
+        {
+            Some(pos) => line_start + pos,
+            None => buf.len(),
+        };
+
+        let line = &buf[line_start..line_end];
+
+        if let Some(colon) = line.iter().position(|b| *b == b':') {
+            if line[..colon]
+                .trim_ascii_end()
+                .eq_ignore_ascii_case(name.as_bytes())
+            {
+                let value = &line[colon + 1..];
+                let start = line_start + colon + 1 + value.len() - value.trim_ascii_start().len();
+                return Some(crate::HeaderValuePosition {
+                    start,
+                    end: start + value.trim_ascii().len(),
+                });
+            }
+        }
+
+        line_start = line_end + 2;
+    }
+
+    None
+}
+
+/// Removes every line of header `name` (case-insensitive) from a serialized
+/// request buffer. The request line is never touched.
+pub(crate) fn remove_header(buf: &mut Vec<u8>, name: &str) {
+    let mut line_start = match buf.windows(2).position(|itm| itm == crate::CL_CR) {
+        Some(pos) => pos + 2,
+        None => return,
+    };
+
+    while line_start < buf.len() {
+        let line_end = match buf[line_start..]
+            .windows(2)
+            .position(|itm| itm == crate::CL_CR)
+        {
+            Some(pos) => line_start + pos + 2,
+            None => buf.len(),
+        };
+
+        if headers_contains(&buf[line_start - 2..line_end], name) {
+            buf.drain(line_start..line_end);
+        } else {
+            line_start = line_end;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum HttpParseError {
     GetMoreData,
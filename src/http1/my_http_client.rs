@@ -1,11 +1,24 @@
 use std::sync::{atomic::AtomicU64, Arc};
 
-use crate::{MyHttpClientConnector, MyHttpClientError};
-
-use super::{HttpTask, MyHttpClientDisconnection, MyHttpRequest, MyHttpResponse};
+use crate::{
+    circuit_breaker::CircuitBreaker,
+    redirect::{RedirectConnectorFactory, RedirectPolicy},
+    retry::RetryPolicy,
+    MyHttpClientConnector, MyHttpClientError,
+};
+
+use super::{
+    HttpTask, InFlightLimit, MaxInFlightMode, MyHttpClientDisconnection, MyHttpRequest,
+    MyHttpRequestBuilder, MyHttpResponse,
+};
 
 use super::MyHttpClientInner;
 
+const DEFAULT_MAX_RECONNECTS: usize = 4;
+const DEFAULT_RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(50);
+const MAX_RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+const DEFAULT_EXPECT_CONTINUE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
 lazy_static::lazy_static! {
     pub static ref CONNECTION_ID: Arc<AtomicU64> = {
         Arc::new(AtomicU64::new(0))
@@ -16,11 +29,23 @@ pub struct MyHttpClient<
     TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
     TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
 > {
-    inner: Arc<MyHttpClientInner<TStream>>,
-    connector: TConnector,
+    pub(super) inner: Arc<MyHttpClientInner<TStream>>,
+    pub(super) connector: TConnector,
     send_to_socket_timeout: std::time::Duration,
     connect_timeout: std::time::Duration,
     read_from_stream_timeout: std::time::Duration,
+    stream_body_threshold: Option<usize>,
+    max_reconnects: usize,
+    reconnect_backoff: std::time::Duration,
+    expect_continue_timeout: std::time::Duration,
+    in_flight_limit: Option<InFlightLimit>,
+    limits: super::MyHttpClientLimits,
+    decompress_responses: bool,
+    pub(super) redirect_policy: Option<RedirectPolicy>,
+    pub(super) redirect_connector_factory:
+        Option<Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>>,
+    pub(super) retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync + 'static>>,
+    pub(super) circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl<
@@ -42,6 +67,17 @@ impl<
             send_to_socket_timeout: std::time::Duration::from_secs(30),
             connect_timeout: std::time::Duration::from_secs(5),
             read_from_stream_timeout: std::time::Duration::from_secs(120),
+            stream_body_threshold: None,
+            max_reconnects: DEFAULT_MAX_RECONNECTS,
+            reconnect_backoff: DEFAULT_RECONNECT_BACKOFF,
+            expect_continue_timeout: DEFAULT_EXPECT_CONTINUE_TIMEOUT,
+            in_flight_limit: None,
+            limits: Default::default(),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
         }
     }
 
@@ -62,9 +98,33 @@ impl<
             send_to_socket_timeout: std::time::Duration::from_secs(30),
             connect_timeout: std::time::Duration::from_secs(5),
             read_from_stream_timeout: std::time::Duration::from_secs(120),
+            stream_body_threshold: None,
+            max_reconnects: DEFAULT_MAX_RECONNECTS,
+            reconnect_backoff: DEFAULT_RECONNECT_BACKOFF,
+            expect_continue_timeout: DEFAULT_EXPECT_CONTINUE_TIMEOUT,
+            in_flight_limit: None,
+            limits: Default::default(),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
         }
     }
 
+    /// Same as [`MyHttpRequestBuilder::new`], with `Host` set to the remote endpoint
+    /// of the connector unless the caller appends one.
+    pub fn create_request_builder(
+        &self,
+        method: http::Method,
+        path_and_query: &str,
+    ) -> MyHttpRequestBuilder {
+        let mut builder = MyHttpRequestBuilder::new(method, path_and_query);
+        let remote_endpoint = self.connector.get_remote_endpoint();
+        builder.set_default_host(remote_endpoint.get_host_port().as_str());
+        builder
+    }
+
     pub fn set_connect_timeout(&mut self, connect_timeout: std::time::Duration) {
         self.connect_timeout = connect_timeout;
     }
@@ -76,10 +136,136 @@ impl<
         self.read_from_stream_timeout = read_from_stream_timeout;
     }
 
+    /// `Content-Length` and close-delimited response bodies larger than
+    /// `threshold` bytes are streamed to the caller the way chunked bodies are,
+    /// instead of being collected in memory first. Such bodies are not subject to
+    /// [`super::MyHttpClientLimits::max_response_body_size`]. Must be set before
+    /// `connect()`.
+    pub fn set_stream_body_threshold(&mut self, threshold: usize) {
+        self.stream_body_threshold = Some(threshold);
+    }
+
+    /// How many times one request may reconnect after finding the connection
+    /// dropped before it fails with [`MyHttpClientError::ReconnectsExhausted`].
+    /// The first connect of a client counts too, so `0` makes a new client unusable.
+    pub fn set_max_reconnects(&mut self, max_reconnects: usize) {
+        self.max_reconnects = max_reconnects;
+    }
+
+    /// Pause before the second reconnect of one request, doubled before every next
+    /// one up to 1 second. The first reconnect happens at once.
+    pub fn set_reconnect_backoff(&mut self, reconnect_backoff: std::time::Duration) {
+        self.reconnect_backoff = reconnect_backoff;
+    }
+
+    /// How long the body of an `Expect: 100-continue` request waits for
+    /// `100 Continue` before it is sent anyway. Default is 1 second.
+    pub fn set_expect_continue_timeout(&mut self, expect_continue_timeout: std::time::Duration) {
+        self.expect_continue_timeout = expect_continue_timeout;
+    }
+
+    /// At most `max_in_flight` requests are sent to the connection before their
+    /// responses arrive; `mode` decides what happens to the next ones. Without it
+    /// every request is pipelined at once. A request counts until its response
+    /// body is read, even if its caller stopped waiting earlier.
+    pub fn set_max_in_flight(&mut self, max_in_flight: usize, mode: MaxInFlightMode) {
+        self.in_flight_limit = Some(InFlightLimit::new(max_in_flight, mode));
+    }
+
+    /// Sends the next request only once the response to the previous one arrived.
+    pub fn disable_pipelining(&mut self) {
+        self.set_max_in_flight(1, MaxInFlightMode::Wait);
+    }
+
+    /// Response limits of this client. Must be set before `connect()`.
+    pub fn set_limits(&mut self, limits: super::MyHttpClientLimits) {
+        self.limits = limits;
+    }
+
+    /// Sends `Accept-Encoding` with the codings enabled by cargo features (unless
+    /// the request has its own) and decodes responses with a supported
+    /// `Content-Encoding`, removing that header and `Content-Length`. The decoded
+    /// size is capped by [`super::MyHttpClientLimits::max_decoded_body_size`].
+    pub fn set_decompress_responses(&mut self, decompress_responses: bool) {
+        self.decompress_responses = decompress_responses;
+    }
+
+    /// Makes `do_request` follow redirects. See [`RedirectPolicy`].
+    pub fn set_redirect_policy(&mut self, redirect_policy: RedirectPolicy) {
+        self.redirect_policy = Some(redirect_policy);
+    }
+
+    /// Connectors for cross-origin redirects. Without a factory such redirects
+    /// are returned to the caller even if the policy allows them.
+    pub fn set_redirect_connector_factory(
+        &mut self,
+        factory: Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>,
+    ) {
+        self.redirect_connector_factory = Some(factory);
+    }
+
+    /// Makes `do_request` repeat failed attempts. See [`RetryPolicy`].
+    pub fn set_retry_policy(&mut self, retry_policy: Arc<dyn RetryPolicy + Send + Sync + 'static>) {
+        self.retry_policy = Some(retry_policy);
+    }
+
+    /// Fails requests fast while the endpoint is down. One breaker can be shared
+    /// by many clients. See [`CircuitBreaker`].
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    /// A client for another endpoint with the same settings, used for a
+    /// cross-origin redirect hop.
+    pub(super) fn create_for_connector(&self, connector: TConnector) -> Self {
+        let inner = Arc::new(MyHttpClientInner::new(
+            connector.get_remote_endpoint().get_host_port().to_string(),
+            self.inner.metrics.clone(),
+        ));
+
+        Self {
+            inner,
+            connector,
+            send_to_socket_timeout: self.send_to_socket_timeout,
+            connect_timeout: self.connect_timeout,
+            read_from_stream_timeout: self.read_from_stream_timeout,
+            stream_body_threshold: self.stream_body_threshold,
+            max_reconnects: self.max_reconnects,
+            reconnect_backoff: self.reconnect_backoff,
+            expect_continue_timeout: self.expect_continue_timeout,
+            in_flight_limit: self.in_flight_limit.as_ref().map(|in_flight_limit| {
+                InFlightLimit::new(
+                    in_flight_limit.get_max_in_flight(),
+                    in_flight_limit.get_mode(),
+                )
+            }),
+            limits: self.limits,
+            decompress_responses: self.decompress_responses,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: self.retry_policy.clone(),
+            circuit_breaker: self.circuit_breaker.clone(),
+        }
+    }
+
+    /// Lock-free check whether the client holds a connection that can take new
+    /// requests right now. The client connects lazily, so `false` does not mean
+    /// it is unusable: the next request reconnects.
+    pub fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
     pub async fn connect(&self) -> Result<(), MyHttpClientError> {
+        self.connect_with_timeout(self.connect_timeout).await
+    }
+
+    async fn connect_with_timeout(
+        &self,
+        connect_timeout: std::time::Duration,
+    ) -> Result<(), MyHttpClientError> {
         let connect_feature = self.connector.connect();
 
-        let connect_result = tokio::time::timeout(self.connect_timeout, connect_feature).await;
+        let connect_result = tokio::time::timeout(connect_timeout, connect_feature).await;
 
         if connect_result.is_err() {
             return Err(MyHttpClientError::CanNotConnectToRemoteHost(format!(
@@ -88,7 +274,7 @@ impl<
                     .get_remote_endpoint()
                     .get_host_port()
                     .as_str(),
-                self.connect_timeout
+                connect_timeout
             )));
         }
 
@@ -128,12 +314,19 @@ impl<
         let (reader, writer) = tokio::io::split(stream);
 
         self.inner
-            .new_connection(current_connection_id, writer, self.send_to_socket_timeout)
+            .new_connection(
+                current_connection_id,
+                writer,
+                self.send_to_socket_timeout,
+                self.expect_continue_timeout,
+            )
             .await;
 
         let debug = self.connector.is_debug();
 
         let read_from_stream_timeout = self.read_from_stream_timeout;
+        let stream_body_threshold = self.stream_body_threshold;
+        let limits = self.limits;
 
         let inner_cloned = self.inner.clone();
         tokio::spawn(async move {
@@ -148,15 +341,17 @@ impl<
                     current_connection_id,
                     inner_cloned.clone(),
                     read_from_stream_timeout,
+                    stream_body_threshold,
+                    limits,
                 )
                 .await;
 
                 if let Err(err) = &resp {
                     if let Some(invalid_payload_reason) = err.as_invalid_payload() {
-                        let task = inner_cloned.pop_request(current_connection_id, false);
+                        let request = inner_cloned.pop_request(current_connection_id, false);
 
-                        if let Some(mut task) = task {
-                            task.set_error(MyHttpClientError::CanNotExecuteRequest(
+                        if let Some(mut request) = request {
+                            request.task.set_error(MyHttpClientError::CanNotExecuteRequest(
                                 invalid_payload_reason.to_string(),
                             ));
                         }
@@ -186,8 +381,8 @@ impl<
                     inner.read_loop_stopped(current_connection_id).await;
                 }
                 Err(err) => {
-                    if let Some(mut task) = inner.pop_request(current_connection_id, false) {
-                        task.set_error(MyHttpClientError::CanNotExecuteRequest(
+                    if let Some(mut request) = inner.pop_request(current_connection_id, false) {
+                        request.task.set_error(MyHttpClientError::CanNotExecuteRequest(
                             "Request is panicked".to_string(),
                         ));
                     }
@@ -211,12 +406,29 @@ impl<
         request: &MyHttpRequest,
         request_timeout: std::time::Duration,
     ) -> Result<(HttpTask<TStream>, u64), MyHttpClientError> {
+        // The request timeout covers reconnects as well, not only the awaiting
+        let deadline = tokio::time::Instant::now() + request_timeout;
+        let mut reconnects = 0;
+
+        // Kept over reconnects until the request is queued. From then on it is
+        // released once the response is read, even if this caller stops waiting
+        let mut in_flight_permit = None;
+
         loop {
-            let err = match self.inner.send(request).await {
-                Ok((awaiter, connection_id)) => {
+            if in_flight_permit.is_none() {
+                if let Some(in_flight_limit) = self.in_flight_limit.as_ref() {
+                    in_flight_permit =
+                        Some(in_flight_limit.acquire(deadline, request_timeout).await?);
+                }
+            }
+
+            let err = match self.inner.send(request, &mut in_flight_permit).await {
+                // The guard is dropped on timeout or cancellation, so the read loop
+                // discards the response instead of dropping the connection
+                Ok((awaiter, _abandoned_guard, connection_id)) => {
                     let await_feature = awaiter.get_result();
 
-                    let result = tokio::time::timeout(request_timeout, await_feature).await;
+                    let result = tokio::time::timeout_at(deadline, await_feature).await;
 
                     if result.is_err() {
                         return Err(MyHttpClientError::RequestTimeout(request_timeout));
@@ -232,12 +444,53 @@ impl<
                 Err(err) => err,
             };
 
-            if err.is_retryable() {
-                self.connect().await?;
-                continue;
+            if !err.is_retryable() {
+                return Err(err);
             }
 
-            return Err(err);
+            // A written request may already be executed by the upstream
+            if matches!(err, MyHttpClientError::Disconnected)
+                && !request.get_method().is_idempotent()
+            {
+                return Err(err);
+            }
+
+            if reconnects >= self.max_reconnects {
+                return Err(MyHttpClientError::ReconnectsExhausted(reconnects));
+            }
+
+            if reconnects > 0 {
+                let backoff = self
+                    .reconnect_backoff
+                    .saturating_mul(1 << (reconnects - 1).min(16))
+                    .min(MAX_RECONNECT_BACKOFF);
+
+                if tokio::time::Instant::now() + backoff >= deadline {
+                    return Err(MyHttpClientError::RequestTimeout(request_timeout));
+                }
+
+                tokio::time::sleep(backoff).await;
+            }
+
+            reconnects += 1;
+
+            // Not cancelled by the deadline: an interrupted connect could leave the
+            // write loop half set up, so the dial itself is shortened instead
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                return Err(MyHttpClientError::RequestTimeout(request_timeout));
+            }
+
+            if let Err(err) = self
+                .connect_with_timeout(self.connect_timeout.min(remaining))
+                .await
+            {
+                if tokio::time::Instant::now() >= deadline {
+                    return Err(MyHttpClientError::RequestTimeout(request_timeout));
+                }
+
+                return Err(err);
+            }
         }
     }
 
@@ -246,6 +499,28 @@ impl<
         req: &MyHttpRequest,
         request_timeout: std::time::Duration,
     ) -> Result<MyHttpResponse<TStream>, MyHttpClientError> {
+        match self.redirect_policy {
+            Some(redirect_policy) => {
+                self.do_request_with_redirects(req, redirect_policy, request_timeout)
+                    .await
+            }
+            None => self.do_single_request(req, request_timeout).await,
+        }
+    }
+
+    pub(super) async fn send_single_request(
+        &self,
+        req: &MyHttpRequest,
+        request_timeout: std::time::Duration,
+    ) -> Result<MyHttpResponse<TStream>, MyHttpClientError> {
+        let with_accept_encoding = if self.decompress_responses {
+            add_accept_encoding(req)
+        } else {
+            None
+        };
+
+        let req = with_accept_encoding.as_ref().unwrap_or(req);
+
         let response = self.send_payload(req, request_timeout).await;
 
         let (task, connection_id) = match response {
@@ -257,6 +532,15 @@ impl<
 
         match task {
             HttpTask::Response(response) => {
+                let response = if self.decompress_responses {
+                    crate::compression::decompress_response(
+                        response,
+                        self.limits.max_decoded_body_size,
+                    )
+                } else {
+                    response
+                };
+
                 Ok(MyHttpResponse::Response(response))
             }
             HttpTask::WebsocketUpgrade {
@@ -279,6 +563,24 @@ impl<
     }
 }
 
+/// Copy of the request with `Accept-Encoding` listing the supported codings, or
+/// `None` if the request already has the header or no codec feature is enabled.
+fn add_accept_encoding(req: &MyHttpRequest) -> Option<MyHttpRequest> {
+    if super::headers_contains(&req.headers, "accept-encoding") {
+        return None;
+    }
+
+    let accept_encoding = crate::compression::ContentEncoding::get_accept_encoding()?;
+
+    let mut result = req.clone();
+    crate::headers::write_header(
+        &mut result.headers,
+        "Accept-Encoding",
+        accept_encoding.as_str(),
+    );
+    Some(result)
+}
+
 impl<
         TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
         TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
@@ -305,3 +607,343 @@ impl<
         Self::new(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use http::Method;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::MyHttpClient;
+    use crate::{
+        http1::{test_utils::*, MyHttpRequestBuilder},
+        MyHttpClientError,
+    };
+
+    #[tokio::test]
+    async fn upstream_closing_every_connection_exhausts_reconnects() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let mut accepted = 0;
+            while let Ok((socket, _)) = listener.accept().await {
+                accepted += 1;
+                drop(socket);
+            }
+            accepted
+        });
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_max_reconnects(3);
+        client.set_reconnect_backoff(Duration::from_millis(10));
+
+        let started = Instant::now();
+        let err = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(err, MyHttpClientError::ReconnectsExhausted(3)));
+        // Pauses of 10 and 20 ms between the three connects
+        assert!(started.elapsed() >= Duration::from_millis(30));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn request_timeout_covers_reconnects() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_max_reconnects(100);
+        client.set_reconnect_backoff(Duration::from_millis(100));
+
+        let err = client
+            .do_request(&req, Duration::from_millis(300))
+            .await
+            .err()
+            .unwrap();
+
+        assert!(matches!(err, MyHttpClientError::RequestTimeout(_)));
+    }
+
+    fn expect_continue_request(mut builder: MyHttpRequestBuilder) -> crate::http1::MyHttpRequest {
+        builder.set_expect_continue();
+        builder.build_with_body(b"hello".to_vec())
+    }
+
+    #[tokio::test]
+    async fn body_is_sent_after_100_continue() {
+        let (listener, connector) = bind_local().await;
+        let host = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .await
+                .unwrap();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"hello").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            received
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_expect_continue_timeout(Duration::from_secs(5));
+        let builder = client.create_request_builder(Method::PUT, "/upload");
+
+        let response = client
+            .do_request(&expect_continue_request(builder), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&received).unwrap(),
+            format!(
+                "PUT /upload HTTP/1.1\r\nExpect: 100-continue\r\nHost: {}\r\nContent-Length: 5\r\n\r\nhello",
+                host
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn body_is_skipped_after_early_final_response() {
+        let (listener, connector) = bind_local().await;
+        let host = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            // The client closes the connection instead of sending the body
+            socket.read_to_end(&mut received).await.unwrap();
+            received
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_expect_continue_timeout(Duration::from_secs(5));
+        let builder = client.create_request_builder(Method::PUT, "/upload");
+
+        let response = client
+            .do_request(&expect_continue_request(builder), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        let received = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            std::str::from_utf8(&received).unwrap(),
+            format!(
+                "PUT /upload HTTP/1.1\r\nExpect: 100-continue\r\nHost: {}\r\nContent-Length: 5\r\n\r\n",
+                host
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn waiting_for_100_continue_does_not_block_other_requests() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let _ = read_until_ends_with(&mut socket, &mut received, b"never").await;
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_expect_continue_timeout(Duration::from_secs(5));
+        let client = std::sync::Arc::new(client);
+
+        let first_client = client.clone();
+        tokio::spawn(async move {
+            let _ = first_client
+                .do_request(
+                    &expect_continue_request(MyHttpRequestBuilder::new(Method::PUT, "/upload")),
+                    Duration::from_secs(10),
+                )
+                .await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/next").build();
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            client.do_request(&req, Duration::from_millis(200)),
+        )
+        .await
+        .expect("The request waited for 100 Continue of another request");
+
+        assert!(matches!(result, Err(MyHttpClientError::RequestTimeout(_))));
+    }
+
+    #[tokio::test]
+    async fn request_queued_behind_skipped_body_is_sent_again() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            socket
+                .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            socket.read_to_end(&mut received).await.unwrap();
+            // Neither the body nor the queued request was written
+            assert!(received.ends_with(b"\r\n\r\n"));
+
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            assert!(received.starts_with(b"GET /next "));
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = socket.read_to_end(&mut received).await;
+        });
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_expect_continue_timeout(Duration::from_secs(5));
+        let client = std::sync::Arc::new(client);
+
+        let first_client = client.clone();
+        let first = tokio::spawn(async move {
+            first_client
+                .do_request(
+                    &expect_continue_request(MyHttpRequestBuilder::new(Method::PUT, "/upload")),
+                    Duration::from_secs(5),
+                )
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/next").build();
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        assert_eq!(first.await.unwrap().unwrap().status(), 401);
+    }
+
+    #[tokio::test]
+    async fn early_hints_are_passed_to_interim_response_handler() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n")
+                .await
+                .unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = socket.read_to_end(&mut received).await;
+        });
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        let mut builder = MyHttpRequestBuilder::new(Method::GET, "/");
+        builder.set_interim_response_handler(move |response| {
+            let _ = sender.send((response.status(), response.headers().clone()));
+        });
+        let req = builder.build();
+
+        let client = MyHttpClient::new(connector);
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let (status, headers) = receiver.try_recv().unwrap();
+        assert_eq!(status, 103);
+        assert_eq!(headers.get("link").unwrap(), "</style.css>; rel=preload");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn abandoned_request_keeps_pipelined_requests_alive() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(
+                read_until_ends_with(&mut socket, &mut received, b"/fast HTTP/1.1\r\n\r\n").await
+            );
+            // Answer once the first caller gave up
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            socket
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nslow\r\n0\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nfast")
+                .await
+                .unwrap();
+            let _ = socket.read_to_end(&mut received).await;
+        });
+
+        let client = std::sync::Arc::new(MyHttpClient::new(connector));
+
+        let slow_client = client.clone();
+        let slow = tokio::spawn(async move {
+            let req = MyHttpRequestBuilder::new(Method::GET, "/slow").build();
+            slow_client
+                .do_request(&req, Duration::from_millis(100))
+                .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/fast").build();
+        let response = client
+            .do_request(&req, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let err = slow.await.unwrap().err().unwrap();
+        assert!(matches!(err, MyHttpClientError::RequestTimeout(_)));
+
+        server.abort();
+    }
+}
@@ -1,13 +1,86 @@
-use std::sync::Arc;
+use std::{
+    collections::VecDeque,
+    sync::{atomic::AtomicBool, Arc},
+};
 
 use tokio::io::WriteHalf;
 
+use super::{MyHttpRequestBodyStreamBoxed, MyHttpRequestTrailers};
+
+/// A piece of the outgoing byte stream. Streamed request bodies are kept as
+/// separate entries so requests pipelined after them are written only once the
+/// body has been sent in full.
+pub enum PayloadToDeliver {
+    Bytes {
+        bytes: Vec<u8>,
+        /// `written` flags of the requests starting in `bytes`. Set right before
+        /// the bytes are written.
+        request_starts: Vec<Arc<AtomicBool>>,
+    },
+    BodyStream {
+        stream: MyHttpRequestBodyStreamBoxed,
+        content_length: Option<u64>,
+        trailers: Option<MyHttpRequestTrailers>,
+    },
+    /// Holds back the body of an `Expect: 100-continue` request until the read
+    /// loop answers or `timeout` passes, in which case the body is sent anyway.
+    AwaitContinue {
+        receiver: tokio::sync::oneshot::Receiver<bool>,
+        timeout: std::time::Duration,
+    },
+}
+
 pub struct MyHttpClientConnectionContext<
     TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
 > {
     pub write_stream: Option<WriteHalf<TStream>>,
-    pub queue_to_deliver: Option<Vec<u8>>,
+    pub queue_to_deliver: VecDeque<PayloadToDeliver>,
     pub send_to_socket_timeout: std::time::Duration,
+    pub expect_continue_timeout: std::time::Duration,
+    /// Set once a body was skipped after an early final response: the server may
+    /// still wait for it, so nothing else is written to this connection.
+    pub writes_stopped: bool,
+    /// Set while the write loop writes without the state lock. Dropped together
+    /// with the context on disconnect, which aborts that write.
+    pub flush_abort: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static>
+    MyHttpClientConnectionContext<TStream>
+{
+    /// Appends bytes to the last pending block, or starts a new one after a
+    /// streamed body.
+    pub fn push_bytes(&mut self, write: impl FnOnce(&mut Vec<u8>)) {
+        if !matches!(
+            self.queue_to_deliver.back(),
+            Some(PayloadToDeliver::Bytes { .. })
+        ) {
+            self.queue_to_deliver.push_back(PayloadToDeliver::Bytes {
+                bytes: Vec::new(),
+                request_starts: Vec::new(),
+            });
+        }
+
+        if let Some(PayloadToDeliver::Bytes { bytes, .. }) = self.queue_to_deliver.back_mut() {
+            write(bytes);
+        }
+    }
+
+    /// Same as [`Self::push_bytes`] for the first bytes of a request: `written`
+    /// is set once they are about to be written.
+    pub fn push_request_start(
+        &mut self,
+        written: Arc<AtomicBool>,
+        write: impl FnOnce(&mut Vec<u8>),
+    ) {
+        self.push_bytes(write);
+
+        if let Some(PayloadToDeliver::Bytes { request_starts, .. }) =
+            self.queue_to_deliver.back_mut()
+        {
+            request_starts.push(written);
+        }
+    }
 }
 
 pub struct WebSocketContextModel {
@@ -1,4 +1,5 @@
 use std::{
+    collections::VecDeque,
     sync::{
         atomic::{AtomicBool, AtomicU64, Ordering},
         Arc,
@@ -16,8 +17,9 @@ use tokio::{
 use crate::{MyHttpClientDisconnect, MyHttpClientError};
 
 use super::{
-    write_loop::WriteLoopEvent, HttpAwaiterTask, HttpAwaitingTask, MyHttpClientConnectionContext,
-    MyHttpRequest, QueueOfRequests, WebSocketContextModel,
+    write_loop::WriteLoopEvent, AbandonedRequestGuard, HttpAwaiterTask,
+    MyHttpClientConnectionContext, MyHttpRequest, PayloadToDeliver, PoppedRequest,
+    QueueOfRequests, WebSocketContextModel,
 };
 
 pub enum WritePartState<
@@ -29,17 +31,43 @@ pub enum WritePartState<
     Disposed,
 }
 
+/// The write half and the queued payload, taken out of the state so they are
+/// written without holding the state lock.
+pub struct PendingWrite<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+> {
+    write_stream: WriteHalf<TStream>,
+    payload: VecDeque<PayloadToDeliver>,
+    send_to_socket_timeout: Duration,
+    aborted: tokio::sync::oneshot::Receiver<()>,
+}
+
+enum WriteOutcome {
+    Written,
+    /// The body of an `Expect: 100-continue` request is not sent. Whatever is
+    /// left of the payload is never written to this connection.
+    BodySkipped,
+    Failed,
+}
+
 impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static>
     WritePartState<TStream>
 {
-    pub fn get_payload_to_send(
-        &mut self,
-    ) -> Option<(&mut WriteHalf<TStream>, Vec<u8>, Duration)> {
+    pub fn take_pending_write(&mut self) -> Option<PendingWrite<TStream>> {
         match self {
             WritePartState::Connected(inner) => {
-                let payload = inner.queue_to_deliver.take()?;
-                let write_stream = inner.write_stream.as_mut().unwrap();
-                Some((write_stream, payload, inner.send_to_socket_timeout))
+                if inner.queue_to_deliver.is_empty() || inner.writes_stopped {
+                    return None;
+                }
+                let write_stream = inner.write_stream.take()?;
+                let (abort_sender, aborted) = tokio::sync::oneshot::channel();
+                inner.flush_abort = Some(abort_sender);
+                Some(PendingWrite {
+                    write_stream,
+                    payload: std::mem::take(&mut inner.queue_to_deliver),
+                    send_to_socket_timeout: inner.send_to_socket_timeout,
+                    aborted,
+                })
             }
             WritePartState::UpgradedToWebSocket(_) => None,
             WritePartState::Disconnected => None,
@@ -61,7 +89,8 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
             WritePartState::Connected(inner) => Ok(inner),
             WritePartState::UpgradedToWebSocket(_) => Err(MyHttpClientError::UpgradedToWebSocket),
 
-            WritePartState::Disconnected => Err(MyHttpClientError::Disconnected),
+            // Nothing of the request is written yet
+            WritePartState::Disconnected => Err(MyHttpClientError::RequestNotSent),
             WritePartState::Disposed => Err(MyHttpClientError::Disposed),
         }
     }
@@ -77,6 +106,9 @@ pub struct MyHttpClientInner<
 
     connection_id: AtomicU64,
     waiting_ws_upgrade: AtomicBool,
+    /// Lock-free mirror of `state` being `Connected`. Written only under the
+    /// state lock, so Relaxed is sufficient.
+    connected: AtomicBool,
     pub queue_of_requests: QueueOfRequests<TStream>,
 
     pub metrics: Option<Arc<dyn super::MyHttpClientMetrics + Send + Sync + 'static>>,
@@ -94,6 +126,7 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
             state: Mutex::new((WritePartState::Disconnected, None)),
             connection_id: AtomicU64::new(0),
             waiting_ws_upgrade: AtomicBool::new(false),
+            connected: AtomicBool::new(false),
             queue_of_requests: QueueOfRequests::new(),
 
             metrics,
@@ -116,6 +149,7 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
         connection_id: u64,
         write_stream: WriteHalf<TStream>,
         send_to_socket_timeout: std::time::Duration,
+        expect_continue_timeout: std::time::Duration,
     ) {
         let mut state = self.state.lock().await;
 
@@ -128,11 +162,15 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
 
         state.0 = WritePartState::Connected(MyHttpClientConnectionContext {
             write_stream: Some(write_stream),
-            queue_to_deliver: None,
+            queue_to_deliver: VecDeque::new(),
             send_to_socket_timeout,
+            expect_continue_timeout,
+            writes_stopped: false,
+            flush_abort: None,
         });
 
         self.waiting_ws_upgrade.store(false, Ordering::Relaxed);
+        self.connected.store(true, Ordering::Relaxed);
         self.connection_id.store(connection_id, Ordering::Release);
 
         if let Some(metrics) = self.metrics.as_ref() {
@@ -144,41 +182,104 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
         self.connection_id.load(Ordering::Acquire) == connection_id
     }
 
+    /// Lock-free check whether the write half is connected and usable for new
+    /// requests. `false` after a disconnect, a websocket upgrade or dispose.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// `in_flight_permit` is taken once the request is queued.
     pub async fn send(
         &self,
         req: &MyHttpRequest,
-    ) -> Result<(HttpAwaiterTask<TStream>, u64), MyHttpClientError> {
+        in_flight_permit: &mut Option<tokio::sync::OwnedSemaphorePermit>,
+    ) -> Result<(HttpAwaiterTask<TStream>, AbandonedRequestGuard, u64), MyHttpClientError> {
         let mut writer = self.state.lock().await;
 
-        let (awaiter, connection_id) = {
+        let (awaiter, abandoned_guard, connection_id) = {
             let connection_context = writer.0.unwrap_as_connected_mut()?;
+
+            // Taken before the request is queued: a stream consumed by an earlier
+            // send can not be replayed, and must not leave an orphan awaiter behind
+            let body_stream = match req.body_stream.as_ref() {
+                Some(body_stream) => match body_stream.take_stream() {
+                    Some(stream) => Some((
+                        stream,
+                        body_stream.get_content_length(),
+                        body_stream.take_trailers(),
+                    )),
+                    None => {
+                        return Err(MyHttpClientError::CanNotExecuteRequest(
+                            "Request body stream is already consumed".to_string(),
+                        ));
+                    }
+                },
+                None => None,
+            };
+
             let mut task = TaskCompletion::new();
             let awaiter = task.get_awaiter();
 
-            self.queue_of_requests.push(req.get_method(), task);
-
-            match connection_context.queue_to_deliver.as_mut() {
-                Some(vec) => {
-                    req.write_to(vec);
-                }
-                None => {
-                    let mut vec = Vec::new();
-                    req.write_to(&mut vec);
-                    connection_context.queue_to_deliver = Some(vec);
+            let (continue_sender, continue_receiver) = if req.expects_continue() {
+                let (continue_sender, receiver) = tokio::sync::oneshot::channel();
+                (Some(continue_sender), Some(receiver))
+            } else {
+                (None, None)
+            };
+
+            let written = Arc::new(AtomicBool::new(false));
+
+            let abandoned_guard = self.queue_of_requests.push_with_handlers(
+                req.get_method(),
+                task,
+                continue_sender,
+                req.interim_response_handler.clone(),
+                written.clone(),
+                in_flight_permit.take(),
+            );
+
+            match continue_receiver {
+                Some(receiver) => {
+                    connection_context.push_request_start(written, |vec| req.write_headers_to(vec));
+                    connection_context
+                        .queue_to_deliver
+                        .push_back(PayloadToDeliver::AwaitContinue {
+                            receiver,
+                            timeout: connection_context.expect_continue_timeout,
+                        });
+
+                    if !req.body.is_empty() {
+                        connection_context.push_bytes(|vec| vec.extend_from_slice(&req.body));
+                    }
                 }
+                None => connection_context.push_request_start(written, |vec| req.write_to(vec)),
+            }
+
+            if let Some((stream, content_length, trailers)) = body_stream {
+                connection_context
+                    .queue_to_deliver
+                    .push_back(PayloadToDeliver::BodyStream {
+                        stream,
+                        content_length,
+                        trailers,
+                    });
             }
 
-            (awaiter, self.connection_id.load(Ordering::Relaxed))
+            (
+                awaiter,
+                abandoned_guard,
+                self.connection_id.load(Ordering::Relaxed),
+            )
         };
 
-        let _ = writer
-            .1
-            .as_ref()
-            .unwrap()
-            .send(WriteLoopEvent::Flush(connection_id))
-            .await;
+        // Sent without the state lock: a full channel waits for the write loop,
+        // which takes the lock once its current write is done
+        let sender = writer.1.clone().unwrap();
+        drop(writer);
+
+        let _ = sender.send(WriteLoopEvent::Flush(connection_id)).await;
 
-        Ok((awaiter, connection_id))
+        Ok((awaiter, abandoned_guard, connection_id))
     }
 
     pub async fn upgrade_to_websocket(
@@ -193,15 +294,25 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
                     return Err(MyHttpClientError::Disconnected);
                 }
 
-                let result = context.write_stream.take();
-
+                // Taken by the write loop while a request is still being written
+                let result = match context.write_stream.take() {
+                    Some(write_stream) => write_stream,
+                    None => {
+                        return Err(MyHttpClientError::CanNotExecuteRequest(
+                            "Can not upgrade to websocket while a request is being written"
+                                .to_string(),
+                        ));
+                    }
+                };
+
+                self.connected.store(false, Ordering::Relaxed);
                 state.0 =
                     WritePartState::UpgradedToWebSocket(WebSocketContextModel::new(self.name.clone()));
 
                 if let Some(metrics) = self.metrics.as_ref() {
                     metrics.upgraded_to_websocket(&self.name);
                 }
-                Ok(result.unwrap())
+                Ok(result)
             }
             WritePartState::UpgradedToWebSocket(_) => Err(MyHttpClientError::UpgradedToWebSocket),
             WritePartState::Disconnected => Err(MyHttpClientError::Disconnected),
@@ -220,11 +331,38 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
         self.queue_of_requests.peek_front_method()
     }
 
+    /// See [`QueueOfRequests::notify_front_interim`].
+    pub fn notify_interim(&self, connection_id: u64, response: &http::Response<()>) {
+        if self.connection_id.load(Ordering::Acquire) != connection_id {
+            return;
+        }
+
+        self.queue_of_requests.notify_front_interim(response);
+    }
+
+    /// See [`QueueOfRequests::notify_front_continue`].
+    pub fn notify_continue(&self, connection_id: u64, send_body: bool) -> bool {
+        if self.connection_id.load(Ordering::Acquire) != connection_id {
+            return false;
+        }
+
+        self.queue_of_requests.notify_front_continue(send_body)
+    }
+
+    /// See [`QueueOfRequests::front_is_abandoned`].
+    pub fn front_request_is_abandoned(&self, connection_id: u64) -> bool {
+        if self.connection_id.load(Ordering::Acquire) != connection_id {
+            return false;
+        }
+
+        self.queue_of_requests.front_is_abandoned()
+    }
+
     pub fn pop_request(
         &self,
         connection_id: u64,
         web_socket_upgrade: bool,
-    ) -> Option<HttpAwaitingTask<TStream>> {
+    ) -> Option<PoppedRequest<TStream>> {
         if self.connection_id.load(Ordering::Acquire) != connection_id {
             return None;
         }
@@ -237,37 +375,71 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
     }
 
     pub async fn flush(&self, connection_id: u64) {
-        let mut state = self.state.lock().await;
+        let mut pending = {
+            let mut state = self.state.lock().await;
 
-        if self.connection_id.load(Ordering::Relaxed) != connection_id {
-            return;
-        }
+            if self.connection_id.load(Ordering::Relaxed) != connection_id {
+                return;
+            }
 
-        let mut has_error = false;
-        if let Some((stream, payload, send_to_socket_timeout)) = state.0.get_payload_to_send() {
-            for chunk in payload.chunks(1024 * 1024) {
-                let future = stream.write_all(chunk);
+            match state.0.take_pending_write() {
+                Some(pending) => pending,
+                None => return,
+            }
+        };
 
-                let result = tokio::time::timeout(send_to_socket_timeout, future).await;
+        // Written without the state lock, so a slow body stream or the wait for
+        // 100 Continue does not hold back new requests, disconnect or dispose.
+        // Those drop the connection context, which aborts the write.
+        let outcome = {
+            let write = write_pending_payload(
+                &mut pending.write_stream,
+                &mut pending.payload,
+                pending.send_to_socket_timeout,
+            );
+
+            match futures::future::select(std::pin::pin!(write), &mut pending.aborted).await {
+                futures::future::Either::Left((outcome, _)) => Some(outcome),
+                futures::future::Either::Right(_) => None,
+            }
+        };
 
-                if result.is_err() {
-                    has_error = true;
-                    break;
-                }
+        let mut state = self.state.lock().await;
 
-                let result = result.unwrap();
+        let context = match &mut state.0 {
+            WritePartState::Connected(context)
+                if outcome.is_some()
+                    && self.connection_id.load(Ordering::Relaxed) == connection_id =>
+            {
+                context
+            }
+            // Disconnected, disposed or reconnected while writing
+            _ => {
+                drop(state);
+                let _ = pending.write_stream.shutdown().await;
+                return;
+            }
+        };
 
-                if result.is_err() {
-                    has_error = true;
-                    break;
+        context.flush_abort = None;
+        context.write_stream = Some(pending.write_stream);
+
+        match outcome.unwrap() {
+            WriteOutcome::Written => {}
+            WriteOutcome::BodySkipped => {
+                // The requests behind the skipped body stay queued: the read loop
+                // closes the connection after the early response, which fails
+                // them with a retryable error
+                context.writes_stopped = true;
+                while let Some(item) = pending.payload.pop_back() {
+                    context.queue_to_deliver.push_front(item);
                 }
             }
-        }
-
-        if has_error {
-            self.connection_id.store(0, Ordering::Release);
-            self.process_disconnect(&mut state.0, WritePartState::Disconnected)
-                .await;
+            WriteOutcome::Failed => {
+                self.connection_id.store(0, Ordering::Release);
+                self.process_disconnect(&mut state.0, WritePartState::Disconnected)
+                    .await;
+            }
         }
     }
 
@@ -313,6 +485,7 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
             _ => {}
         }
 
+        self.connected.store(false, Ordering::Relaxed);
         *state = new_status;
     }
 
@@ -342,12 +515,73 @@ impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'stat
         self.process_disconnect(&mut state.0, WritePartState::Disposed)
             .await;
 
-        if let Some(sender) = state.1.as_ref() {
+        let sender = state.1.clone();
+        drop(state);
+
+        if let Some(sender) = sender {
             let _ = sender.send(WriteLoopEvent::Close).await;
         }
     }
 }
 
+/// Writes `payload` item by item. Items after a skipped body are left in it.
+async fn write_pending_payload<TStream: tokio::io::AsyncWrite>(
+    write_stream: &mut WriteHalf<TStream>,
+    payload: &mut VecDeque<PayloadToDeliver>,
+    send_to_socket_timeout: Duration,
+) -> WriteOutcome {
+    while let Some(item) = payload.pop_front() {
+        let result = match item {
+            PayloadToDeliver::Bytes {
+                bytes,
+                request_starts,
+            } => {
+                for written in request_starts {
+                    written.store(true, Ordering::Relaxed);
+                }
+
+                super::write_payload::write_bytes(write_stream, &bytes, send_to_socket_timeout)
+                    .await
+            }
+            PayloadToDeliver::BodyStream {
+                stream: body,
+                content_length,
+                trailers,
+            } => {
+                super::write_payload::write_body_stream(
+                    write_stream,
+                    body,
+                    content_length,
+                    trailers,
+                    send_to_socket_timeout,
+                )
+                .await
+            }
+            PayloadToDeliver::AwaitContinue { receiver, timeout } => {
+                let send_body = match tokio::time::timeout(timeout, receiver).await {
+                    Ok(Ok(send_body)) => send_body,
+                    // The connection is gone
+                    Ok(Err(_)) => false,
+                    // RFC 9110 §10.1.1: no need to wait for 100 Continue forever
+                    Err(_) => true,
+                };
+
+                if !send_body {
+                    return WriteOutcome::BodySkipped;
+                }
+
+                Ok(())
+            }
+        };
+
+        if result.is_err() {
+            return WriteOutcome::Failed;
+        }
+    }
+
+    WriteOutcome::Written
+}
+
 impl<TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static> Drop
     for MyHttpClientInner<TStream>
 {
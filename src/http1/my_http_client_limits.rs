@@ -0,0 +1,54 @@
+/// Limits applied to the responses read by one [`super::MyHttpClient`].
+///
+/// Defaults match the crate-wide constants (`MAX_RESPONSE_BODY_SIZE`,
+/// `MAX_CHUNK_SIZE`, ...), so a client that never calls `set_limits` behaves as
+/// before. A response breaking a limit fails the pending request with
+/// [`crate::MyHttpClientError::CanNotExecuteRequest`] and drops the connection.
+#[derive(Debug, Clone, Copy)]
+pub struct MyHttpClientLimits {
+    /// Largest `Content-Length` or close-delimited body collected in memory.
+    /// Bodies streamed because of `set_stream_body_threshold` are not limited.
+    pub max_response_body_size: usize,
+    /// Largest single chunk of a chunked body.
+    pub max_chunk_size: usize,
+    /// Maximum amount of header lines in one response.
+    pub max_headers_count: usize,
+    /// Maximum length of the status line or of one header line, without CRLF.
+    pub max_header_line_size: usize,
+    /// Maximum size of the whole header block: status line, header lines and
+    /// their CRLFs. It can not exceed the read buffer size (512 KiB) in practice.
+    pub max_headers_total_size: usize,
+    /// Maximum amount of trailer fields after the last chunk of a chunked body.
+    pub max_trailers_count: usize,
+    /// Maximum size of the trailer section with its CRLFs. Each trailer line is
+    /// also subject to `max_header_line_size`.
+    pub max_trailers_total_size: usize,
+    /// Maximum amount of consecutive interim (1xx) responses before the final one.
+    pub max_interim_responses: usize,
+    /// Largest body of a response whose caller stopped waiting (timed out) that
+    /// is read and discarded to keep the connection. A larger or close-delimited
+    /// one drops the connection, failing the requests pipelined after it with
+    /// [`crate::MyHttpClientError::Disconnected`].
+    pub max_abandoned_body_size: usize,
+    /// Largest body produced by decoding a `Content-Encoding` when
+    /// `set_decompress_responses` is on, streamed bodies included. Going over it
+    /// ends the body with an error.
+    pub max_decoded_body_size: usize,
+}
+
+impl Default for MyHttpClientLimits {
+    fn default() -> Self {
+        Self {
+            max_response_body_size: super::MAX_RESPONSE_BODY_SIZE,
+            max_chunk_size: super::MAX_CHUNK_SIZE,
+            max_headers_count: super::MAX_RESPONSE_HEADERS_COUNT,
+            max_header_line_size: super::MAX_RESPONSE_HEADER_LINE_SIZE,
+            max_headers_total_size: super::MAX_RESPONSE_HEADERS_TOTAL_SIZE,
+            max_trailers_count: super::MAX_RESPONSE_TRAILERS_COUNT,
+            max_trailers_total_size: super::MAX_RESPONSE_TRAILERS_TOTAL_SIZE,
+            max_interim_responses: super::MAX_INTERIM_RESPONSES,
+            max_abandoned_body_size: super::MAX_ABANDONED_RESPONSE_BODY_SIZE,
+            max_decoded_body_size: crate::compression::MAX_DECODED_BODY_SIZE,
+        }
+    }
+}
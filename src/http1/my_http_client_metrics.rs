@@ -9,4 +9,16 @@ pub trait MyHttpClientMetrics {
     fn write_thread_stop(&self, name: &str);
     fn upgraded_to_websocket(&self, name: &str);
     fn websocket_is_disconnected(&self, name: &str);
+
+    /// A failed attempt of a request is repeated after `delay` according to the
+    /// client's [`crate::retry::RetryPolicy`]. `attempt` is the number of the failed one.
+    fn request_retried(&self, _name: &str, _attempt: usize, _delay: std::time::Duration) {}
+
+    /// The circuit of the endpoint this client talks to changed its state.
+    fn circuit_breaker_state_changed(
+        &self,
+        _name: &str,
+        _state: crate::circuit_breaker::CircuitBreakerState,
+    ) {
+    }
 }
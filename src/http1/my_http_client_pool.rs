@@ -0,0 +1,644 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    circuit_breaker::CircuitBreaker,
+    redirect::{RedirectConnectorFactory, RedirectPolicy},
+    retry::RetryPolicy,
+    MyHttpClientConnector, MyHttpClientError,
+};
+
+use super::{
+    MaxInFlightMode, MyHttpClient, MyHttpClientLimits, MyHttpClientMetrics, MyHttpRequest,
+    MyHttpResponse,
+};
+
+/// One pooled connection. Each [`MyHttpClient`] owns exactly one socket, so the
+/// pool holds one client per connection and tracks how many requests are
+/// currently running through it.
+struct MyHttpClientPoolConnection<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+> {
+    client: MyHttpClient<TStream, Arc<TConnector>>,
+    in_flight: AtomicUsize,
+    /// Micros since the pool was created when the last request finished
+    last_used_micros: AtomicU64,
+}
+
+struct MyHttpClientPoolState<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+> {
+    connections: Vec<Arc<MyHttpClientPoolConnection<TStream, TConnector>>>,
+    /// Dials in progress. Counted against `max_connections` so a burst of
+    /// concurrent callers can not open more connections than allowed.
+    connecting: usize,
+}
+
+/// A pool of HTTP/1 connections to one remote endpoint.
+///
+/// A single [`MyHttpClient`] pipelines every request onto one socket, so one slow
+/// response head-of-line blocks every caller sharing it. The pool spreads
+/// requests over up to `max_connections` clients built from one connector: an
+/// idle connection is preferred, a new one is dialed while below the limit, and
+/// the least loaded one is used once the limit is reached.
+///
+/// A connection stays taken by a request until its response body is read or
+/// dropped.
+///
+/// Connections that lost their socket (read loop stopped, write error, websocket
+/// upgrade) and connections idle longer than the idle timeout are dropped from
+/// the pool on the next request. The pool runs no background task: call
+/// [`Self::remove_idle_connections`] to close idle ones without a request.
+pub struct MyHttpClientPool<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+> {
+    connector: Arc<TConnector>,
+    state: parking_lot::Mutex<MyHttpClientPoolState<TStream, TConnector>>,
+    max_connections: usize,
+    idle_timeout: Duration,
+    connect_timeout: Option<Duration>,
+    read_from_stream_timeout: Option<Duration>,
+    stream_body_threshold: Option<usize>,
+    max_reconnects: Option<usize>,
+    reconnect_backoff: Option<Duration>,
+    expect_continue_timeout: Option<Duration>,
+    max_in_flight: Option<(usize, MaxInFlightMode)>,
+    limits: MyHttpClientLimits,
+    decompress_responses: bool,
+    redirect_policy: Option<RedirectPolicy>,
+    redirect_connector_factory:
+        Option<Arc<dyn RedirectConnectorFactory<TStream, Arc<TConnector>> + Send + Sync + 'static>>,
+    retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync + 'static>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    metrics: Option<Arc<dyn MyHttpClientMetrics + Send + Sync + 'static>>,
+    dial_finished: tokio::sync::Notify,
+    created: Instant,
+}
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > MyHttpClientPool<TStream, TConnector>
+{
+    pub fn new(connector: TConnector, max_connections: usize) -> Self {
+        Self {
+            connector: Arc::new(connector),
+            state: parking_lot::Mutex::new(MyHttpClientPoolState {
+                connections: Vec::new(),
+                connecting: 0,
+            }),
+            max_connections: max_connections.max(1),
+            idle_timeout: Duration::from_secs(60),
+            connect_timeout: None,
+            read_from_stream_timeout: None,
+            stream_body_threshold: None,
+            max_reconnects: None,
+            reconnect_backoff: None,
+            expect_continue_timeout: None,
+            max_in_flight: None,
+            limits: MyHttpClientLimits::default(),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
+            metrics: None,
+            dial_finished: tokio::sync::Notify::new(),
+            created: Instant::now(),
+        }
+    }
+
+    pub fn new_with_metrics(
+        connector: TConnector,
+        max_connections: usize,
+        metrics: Arc<dyn MyHttpClientMetrics + Send + Sync + 'static>,
+    ) -> Self {
+        let mut result = Self::new(connector, max_connections);
+        result.metrics = Some(metrics);
+        result
+    }
+
+    /// Connections without requests for longer than this are closed by the next
+    /// request or by [`Self::remove_idle_connections`]. Must be set before the
+    /// pool is shared.
+    pub fn set_idle_timeout(&mut self, idle_timeout: Duration) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    /// Applied to every connection the pool dials from now on.
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = Some(connect_timeout);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_read_from_stream_timeout`].
+    pub fn set_read_from_stream_timeout(&mut self, read_from_stream_timeout: Duration) {
+        self.read_from_stream_timeout = Some(read_from_stream_timeout);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_stream_body_threshold`].
+    pub fn set_stream_body_threshold(&mut self, threshold: usize) {
+        self.stream_body_threshold = Some(threshold);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_max_reconnects`].
+    pub fn set_max_reconnects(&mut self, max_reconnects: usize) {
+        self.max_reconnects = Some(max_reconnects);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_reconnect_backoff`].
+    pub fn set_reconnect_backoff(&mut self, reconnect_backoff: Duration) {
+        self.reconnect_backoff = Some(reconnect_backoff);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_expect_continue_timeout`].
+    pub fn set_expect_continue_timeout(&mut self, expect_continue_timeout: Duration) {
+        self.expect_continue_timeout = Some(expect_continue_timeout);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_max_in_flight`].
+    pub fn set_max_in_flight(&mut self, max_in_flight: usize, mode: MaxInFlightMode) {
+        self.max_in_flight = Some((max_in_flight, mode));
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::disable_pipelining`].
+    pub fn disable_pipelining(&mut self) {
+        self.set_max_in_flight(1, MaxInFlightMode::Wait);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_limits`].
+    pub fn set_limits(&mut self, limits: MyHttpClientLimits) {
+        self.limits = limits;
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_decompress_responses`].
+    pub fn set_decompress_responses(&mut self, decompress_responses: bool) {
+        self.decompress_responses = decompress_responses;
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_redirect_policy`].
+    pub fn set_redirect_policy(&mut self, redirect_policy: RedirectPolicy) {
+        self.redirect_policy = Some(redirect_policy);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_redirect_connector_factory`].
+    pub fn set_redirect_connector_factory(
+        &mut self,
+        factory: Arc<
+            dyn RedirectConnectorFactory<TStream, Arc<TConnector>> + Send + Sync + 'static,
+        >,
+    ) {
+        self.redirect_connector_factory = Some(factory);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_retry_policy`].
+    pub fn set_retry_policy(&mut self, retry_policy: Arc<dyn RetryPolicy + Send + Sync + 'static>) {
+        self.retry_policy = Some(retry_policy);
+    }
+
+    /// Applied to every connection the pool dials from now on. See
+    /// [`MyHttpClient::set_circuit_breaker`].
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    pub fn get_max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    /// Amount of connections currently held by the pool, including ones that are
+    /// still being dialed.
+    pub fn get_connections_amount(&self) -> usize {
+        let state = self.state.lock();
+        state.connections.len() + state.connecting
+    }
+
+    /// Drops connections that lost their socket or are idle for longer than the
+    /// idle timeout. Requests do it on their own; call it from a timer to close
+    /// idle connections of a pool that gets no requests.
+    pub fn remove_idle_connections(&self) {
+        let mut state = self.state.lock();
+        self.remove_unusable_connections(&mut state);
+    }
+
+    pub async fn do_request(
+        &self,
+        req: &MyHttpRequest,
+        request_timeout: Duration,
+    ) -> Result<MyHttpResponse<TStream>, MyHttpClientError> {
+        let lease = self.get_connection().await?;
+        let response = lease
+            .connection
+            .client
+            .do_request(req, request_timeout)
+            .await?;
+
+        // The body is read from the leased connection, so it stays leased until
+        // the body ends
+        match response {
+            MyHttpResponse::Response(response) => Ok(MyHttpResponse::Response(
+                crate::body_guard::hold_until_body_ends(response, lease),
+            )),
+            MyHttpResponse::WebSocketUpgrade { .. } => Ok(response),
+        }
+    }
+
+    async fn get_connection(
+        &self,
+    ) -> Result<MyHttpClientPoolLease<TStream, TConnector>, MyHttpClientError> {
+        loop {
+            let dial_finished = {
+                let mut state = self.state.lock();
+                self.remove_unusable_connections(&mut state);
+
+                if let Some(connection) = state.connections.iter().find(|itm| {
+                    itm.client.is_connected() && itm.in_flight.load(Ordering::Relaxed) == 0
+                }) {
+                    return Ok(self.lease(connection.clone()));
+                }
+
+                if state.connections.len() + state.connecting < self.max_connections {
+                    state.connecting += 1;
+                    break;
+                }
+
+                // At the limit: share the least loaded connection, preferring the
+                // connected ones over ones that are about to reconnect
+                let connection = state
+                    .connections
+                    .iter()
+                    .min_by_key(|itm| {
+                        (
+                            !itm.client.is_connected(),
+                            itm.in_flight.load(Ordering::Relaxed),
+                        )
+                    })
+                    .cloned();
+
+                if let Some(connection) = connection {
+                    return Ok(self.lease(connection));
+                }
+
+                // Every slot is taken by a dial in progress. Created under the lock,
+                // so a dial finishing right after it is released is not missed
+                self.dial_finished.notified()
+            };
+
+            dial_finished.await;
+        }
+
+        let dial_slot = MyHttpClientPoolDialSlot { pool: self };
+
+        let client = self.create_client();
+        client.connect().await?;
+
+        let connection = Arc::new(MyHttpClientPoolConnection {
+            client,
+            in_flight: AtomicUsize::new(0),
+            last_used_micros: AtomicU64::new(self.now_micros()),
+        });
+
+        let lease = self.lease(connection.clone());
+
+        self.state.lock().connections.push(connection);
+
+        drop(dial_slot);
+
+        Ok(lease)
+    }
+
+    fn create_client(&self) -> MyHttpClient<TStream, Arc<TConnector>> {
+        let mut client = match self.metrics.as_ref() {
+            Some(metrics) => {
+                MyHttpClient::new_with_metrics(self.connector.clone(), metrics.clone())
+            }
+            None => MyHttpClient::new(self.connector.clone()),
+        };
+
+        if let Some(connect_timeout) = self.connect_timeout {
+            client.set_connect_timeout(connect_timeout);
+        }
+
+        if let Some(read_from_stream_timeout) = self.read_from_stream_timeout {
+            client.set_read_from_stream_timeout(read_from_stream_timeout);
+        }
+
+        if let Some(threshold) = self.stream_body_threshold {
+            client.set_stream_body_threshold(threshold);
+        }
+
+        if let Some(max_reconnects) = self.max_reconnects {
+            client.set_max_reconnects(max_reconnects);
+        }
+
+        if let Some(reconnect_backoff) = self.reconnect_backoff {
+            client.set_reconnect_backoff(reconnect_backoff);
+        }
+
+        if let Some(expect_continue_timeout) = self.expect_continue_timeout {
+            client.set_expect_continue_timeout(expect_continue_timeout);
+        }
+
+        if let Some((max_in_flight, mode)) = self.max_in_flight {
+            client.set_max_in_flight(max_in_flight, mode);
+        }
+
+        client.set_limits(self.limits);
+        client.set_decompress_responses(self.decompress_responses);
+
+        if let Some(redirect_policy) = self.redirect_policy {
+            client.set_redirect_policy(redirect_policy);
+        }
+
+        if let Some(factory) = self.redirect_connector_factory.clone() {
+            client.set_redirect_connector_factory(factory);
+        }
+
+        if let Some(retry_policy) = self.retry_policy.clone() {
+            client.set_retry_policy(retry_policy);
+        }
+
+        if let Some(circuit_breaker) = self.circuit_breaker.clone() {
+            client.set_circuit_breaker(circuit_breaker);
+        }
+
+        client
+    }
+
+    /// Drops connections that lost their socket and connections idle for longer
+    /// than `idle_timeout`. Connections with requests in flight are kept: a
+    /// disconnected one reconnects on its own inside `do_request`.
+    fn remove_unusable_connections(&self, state: &mut MyHttpClientPoolState<TStream, TConnector>) {
+        let now = self.now_micros();
+        let idle_timeout = self.idle_timeout.as_micros() as u64;
+
+        state.connections.retain(|itm| {
+            if itm.in_flight.load(Ordering::Relaxed) > 0 {
+                return true;
+            }
+
+            if !itm.client.is_connected() {
+                return false;
+            }
+
+            now.saturating_sub(itm.last_used_micros.load(Ordering::Relaxed)) < idle_timeout
+        });
+    }
+
+    fn lease(
+        &self,
+        connection: Arc<MyHttpClientPoolConnection<TStream, TConnector>>,
+    ) -> MyHttpClientPoolLease<TStream, TConnector> {
+        connection.in_flight.fetch_add(1, Ordering::Relaxed);
+        MyHttpClientPoolLease {
+            pool_created: self.created,
+            connection,
+        }
+    }
+
+    fn now_micros(&self) -> u64 {
+        self.created.elapsed().as_micros() as u64
+    }
+}
+
+/// A connection taken from the pool for one request. Dropping it (including when
+/// the request future is cancelled) returns the connection to the pool. Owns
+/// what it needs, so it can go with the response body.
+struct MyHttpClientPoolLease<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+> {
+    /// `created` of the pool, the origin of `last_used_micros`
+    pool_created: Instant,
+    connection: Arc<MyHttpClientPoolConnection<TStream, TConnector>>,
+}
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > Drop for MyHttpClientPoolLease<TStream, TConnector>
+{
+    fn drop(&mut self) {
+        self.connection.last_used_micros.store(
+            self.pool_created.elapsed().as_micros() as u64,
+            Ordering::Relaxed,
+        );
+        self.connection.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Releases the reserved dial slot when the dial finishes, fails or is cancelled.
+struct MyHttpClientPoolDialSlot<
+    'p,
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+> {
+    pool: &'p MyHttpClientPool<TStream, TConnector>,
+}
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > Drop for MyHttpClientPoolDialSlot<'_, TStream, TConnector>
+{
+    fn drop(&mut self) {
+        self.pool.state.lock().connecting -= 1;
+        self.pool.dial_finished.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use http::Method;
+    use http_body_util::BodyExt;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::http1::{test_utils::TestConnector, MyHttpClientPool, MyHttpRequestBuilder};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Answers every request with `ok` after `delay`, counting accepted sockets.
+    async fn start_server(delay: Duration) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host_port = listener.local_addr().unwrap().to_string();
+        let accepted = Arc::new(AtomicUsize::new(0));
+
+        let accepted_cloned = accepted.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                accepted_cloned.fetch_add(1, Ordering::SeqCst);
+
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let mut received = Vec::new();
+                    loop {
+                        let read = match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => read,
+                        };
+                        received.extend_from_slice(&buf[..read]);
+
+                        while let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                            received.drain(..end + 4);
+                            tokio::time::sleep(delay).await;
+                            if socket
+                                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                                .await
+                                .is_err()
+                            {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        (host_port, accepted)
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_spread_over_connections_up_to_max() {
+        let (host_port, accepted) = start_server(Duration::from_millis(200)).await;
+        let pool = Arc::new(MyHttpClientPool::new(TestConnector { host_port }, 2));
+
+        let mut tasks = Vec::new();
+        for _ in 0..4 {
+            let pool = pool.clone();
+            tasks.push(tokio::spawn(async move {
+                let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+                pool.do_request(&req, TIMEOUT).await.unwrap().status()
+            }));
+        }
+
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 200);
+        }
+
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.get_connections_amount(), 2);
+    }
+
+    #[tokio::test]
+    async fn idle_connection_is_reused() {
+        let (host_port, accepted) = start_server(Duration::ZERO).await;
+        let pool = MyHttpClientPool::new(TestConnector { host_port }, 4);
+
+        for _ in 0..3 {
+            let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+            let response = pool.do_request(&req, TIMEOUT).await.unwrap();
+            assert_eq!(response.status(), 200);
+        }
+
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn idle_connections_are_shrunk_after_timeout() {
+        let (host_port, accepted) = start_server(Duration::ZERO).await;
+        let mut pool = MyHttpClientPool::new(TestConnector { host_port }, 4);
+        pool.set_idle_timeout(Duration::from_millis(50));
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+        pool.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(pool.get_connections_amount(), 1);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        pool.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(pool.get_connections_amount(), 1);
+        assert_eq!(accepted.load(Ordering::SeqCst), 2);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        pool.remove_idle_connections();
+        assert_eq!(pool.get_connections_amount(), 0);
+    }
+
+    #[tokio::test]
+    async fn connection_stays_leased_while_body_is_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host_port = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let mut received = Vec::new();
+                    loop {
+                        let read = match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => read,
+                        };
+                        received.extend_from_slice(&buf[..read]);
+
+                        while let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") {
+                            received.drain(..end + 4);
+                            let _ = socket
+                                .write_all(
+                                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nok\r\n",
+                                )
+                                .await;
+                            tokio::time::sleep(Duration::from_millis(200)).await;
+                            let _ = socket.write_all(b"0\r\n\r\n").await;
+                        }
+                    }
+                });
+            }
+        });
+
+        let pool = MyHttpClientPool::new(TestConnector { host_port }, 4);
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+
+        let streaming = pool.do_request(&req, TIMEOUT).await.unwrap();
+
+        // The first connection is still reading its body
+        let second = pool.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(pool.get_connections_amount(), 2);
+
+        for response in [streaming, second] {
+            let body = response
+                .into_response()
+                .into_body()
+                .collect()
+                .await
+                .unwrap();
+            assert_eq!(body.to_bytes().as_ref(), b"ok");
+        }
+
+        pool.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(pool.get_connections_amount(), 2);
+    }
+}
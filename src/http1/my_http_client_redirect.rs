@@ -0,0 +1,422 @@
+use std::time::Duration;
+
+use crate::{
+    redirect::{RedirectChain, RedirectOrigin, RedirectPolicy, RedirectStep},
+    MyHttpClientConnector, MyHttpClientError,
+};
+
+use super::{MyHttpClient, MyHttpRequest, MyHttpResponse};
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > MyHttpClient<TStream, TConnector>
+{
+    pub(super) async fn do_request_with_redirects(
+        &self,
+        req: &MyHttpRequest,
+        redirect_policy: RedirectPolicy,
+        request_timeout: Duration,
+    ) -> Result<MyHttpResponse<TStream>, MyHttpClientError> {
+        let host_port = self
+            .connector
+            .get_remote_endpoint()
+            .get_host_port()
+            .to_string();
+        let mut chain = RedirectChain::new(
+            redirect_policy,
+            RedirectOrigin::from_host_port(host_port.as_str()),
+        );
+
+        // Client of the origin the chain moved to; `None` while on our own origin
+        let mut cross_origin_client: Option<Self> = None;
+        let mut next_request: Option<MyHttpRequest> = None;
+
+        loop {
+            let req = next_request.as_ref().unwrap_or(req);
+
+            let response = match cross_origin_client.as_ref() {
+                Some(client) => client.do_single_request(req, request_timeout).await?,
+                None => self.do_single_request(req, request_timeout).await?,
+            };
+
+            let response = match response {
+                MyHttpResponse::Response(response) => response,
+                MyHttpResponse::WebSocketUpgrade { .. } => return Ok(response),
+            };
+
+            // Only origin-form targets are rewritten: an absolute-form request
+            // through a forward proxy gets the redirect as is
+            if !req.get_path_and_query().starts_with('/') {
+                return Ok(into_response(response, cross_origin_client));
+            }
+
+            // A streamed body is gone once sent
+            let hop = chain.get_next_hop(
+                &response,
+                &req.get_method(),
+                req.get_path_and_query(),
+                req.body_stream.is_none(),
+                self.redirect_connector_factory.as_ref(),
+            )?;
+
+            let hop = match hop {
+                Some(hop) => hop,
+                None => return Ok(into_response(response, cross_origin_client)),
+            };
+
+            crate::utils::drain_body(response, request_timeout).await;
+
+            next_request = Some(create_redirect_request(
+                req,
+                &hop.step,
+                hop.target_origin.as_ref(),
+            ));
+
+            if let Some(connector) = hop.connector {
+                cross_origin_client = Some(self.create_for_connector(connector));
+            }
+
+            chain.follow(hop.target_origin);
+        }
+    }
+}
+
+fn into_response<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+>(
+    response: crate::HyperResponse,
+    cross_origin_client: Option<MyHttpClient<TStream, TConnector>>,
+) -> MyHttpResponse<TStream> {
+    match cross_origin_client {
+        Some(client) => MyHttpResponse::Response(crate::body_guard::hold_until_body_ends(
+            response,
+            Box::new(client),
+        )),
+        None => MyHttpResponse::Response(response),
+    }
+}
+
+/// Next request of a redirect chain. A hop to `target_origin` gets its `Host`
+/// and loses the [`crate::redirect::CROSS_ORIGIN_HEADERS`].
+fn create_redirect_request(
+    req: &MyHttpRequest,
+    step: &RedirectStep,
+    target_origin: Option<&RedirectOrigin>,
+) -> MyHttpRequest {
+    let line_end = req
+        .headers
+        .windows(2)
+        .position(|itm| itm == crate::CL_CR)
+        .unwrap_or(req.headers.len());
+
+    let request_line = std::str::from_utf8(&req.headers[..line_end]).unwrap_or_default();
+    let version = request_line.rsplit(' ').next().unwrap_or("HTTP/1.1");
+
+    let mut headers = format!(
+        "{} {} {}\r\n",
+        step.method,
+        step.path_and_query.as_str(),
+        version
+    )
+    .into_bytes();
+
+    let mut pos = (line_end + crate::CL_CR.len()).min(req.headers.len());
+
+    while pos < req.headers.len() {
+        let end = match req.headers[pos..]
+            .windows(2)
+            .position(|itm| itm == crate::CL_CR)
+        {
+            Some(end) => pos + end + crate::CL_CR.len(),
+            None => req.headers.len(),
+        };
+
+        let line = &req.headers[pos..end];
+        pos = end;
+
+        let name_end = line.iter().position(|b| *b == b':').unwrap_or(line.len());
+        let name = std::str::from_utf8(&line[..name_end])
+            .unwrap_or_default()
+            .trim();
+
+        if target_origin.is_some()
+            && (name.eq_ignore_ascii_case("host")
+                || crate::redirect::CROSS_ORIGIN_HEADERS
+                    .iter()
+                    .any(|header| name.eq_ignore_ascii_case(header.as_str())))
+        {
+            continue;
+        }
+
+        if !step.keep_body
+            && crate::redirect::BODY_HEADERS
+                .iter()
+                .any(|body_header| name.eq_ignore_ascii_case(body_header.as_str()))
+        {
+            continue;
+        }
+
+        headers.extend_from_slice(line);
+    }
+
+    if let Some(target_origin) = target_origin {
+        crate::headers::write_header(&mut headers, "Host", target_origin.authority.as_str());
+    }
+
+    MyHttpRequest {
+        headers,
+        body: if step.keep_body {
+            req.body.clone()
+        } else {
+            bytes::Bytes::new()
+        },
+        body_stream: None,
+        interim_response_handler: req.interim_response_handler.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use http::Method;
+    use http_body_util::BodyExt;
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use crate::{
+        http1::{test_utils::*, MyHttpClient, MyHttpRequestBuilder, MyHttpResponse},
+        redirect::{RedirectConnectorFactory, RedirectOrigin, RedirectPolicy, RedirectStep},
+        MyHttpClientError,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    struct TestRedirectConnectorFactory;
+
+    impl RedirectConnectorFactory<tokio::net::TcpStream, TestConnector>
+        for TestRedirectConnectorFactory
+    {
+        fn create_connector(
+            &self,
+            origin: &RedirectOrigin,
+        ) -> Result<TestConnector, MyHttpClientError> {
+            Ok(TestConnector {
+                host_port: origin.authority.to_string(),
+            })
+        }
+    }
+
+    /// Answers each request ending with the matching terminator with the
+    /// matching response on one connection and returns what was received.
+    fn serve(
+        listener: TcpListener,
+        exchanges: Vec<(&'static [u8], &'static [u8])>,
+    ) -> tokio::task::JoinHandle<Vec<String>> {
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut result = Vec::new();
+            for (terminator, response) in exchanges {
+                let mut received = Vec::new();
+                assert!(read_until_ends_with(&mut socket, &mut received, terminator).await);
+                result.push(String::from_utf8(received).unwrap());
+                socket.write_all(response).await.unwrap();
+            }
+            result
+        })
+    }
+
+    async fn get_body(response: MyHttpResponse<tokio::net::TcpStream>) -> Vec<u8> {
+        match response {
+            MyHttpResponse::Response(response) => response
+                .into_body()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes()
+                .to_vec(),
+            MyHttpResponse::WebSocketUpgrade { .. } => panic!("Unexpected upgrade"),
+        }
+    }
+
+    #[tokio::test]
+    async fn post_after_302_becomes_get_without_body() {
+        let (listener, connector) = bind_local().await;
+
+        let server = serve(
+            listener,
+            vec![
+                (
+                    b"abc",
+                    b"HTTP/1.1 302 Found\r\nLocation: /new\r\nContent-Length: 5\r\n\r\nmoved",
+                ),
+                (
+                    b"\r\n\r\n",
+                    b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ndone",
+                ),
+            ],
+        );
+
+        let mut builder = MyHttpRequestBuilder::new(Method::POST, "/old");
+        builder.append_header("Host", "localhost");
+        builder.append_header("Content-Type", "text/plain");
+        let req = builder.build_with_body(b"abc".to_vec());
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_redirect_policy(RedirectPolicy::default());
+
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(get_body(response).await, b"done");
+
+        let received = server.await.unwrap();
+        assert_eq!(received[1], "GET /new HTTP/1.1\r\nHost: localhost\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn post_after_307_keeps_method_and_body() {
+        let (listener, connector) = bind_local().await;
+
+        let server = serve(
+            listener,
+            vec![
+                (
+                    b"abc",
+                    b"HTTP/1.1 307 Temporary Redirect\r\nLocation: new?a=1\r\nContent-Length: 0\r\n\r\n",
+                ),
+                (b"abc", b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"),
+            ],
+        );
+
+        let mut builder = MyHttpRequestBuilder::new(Method::POST, "/api/old");
+        builder.append_header("Host", "localhost");
+        let req = builder.build_with_body(b"abc".to_vec());
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_redirect_policy(RedirectPolicy::default());
+
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(get_body(response).await, b"");
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            received[1],
+            "POST /api/new?a=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 3\r\n\r\nabc"
+        );
+    }
+
+    #[tokio::test]
+    async fn redirect_loop_fails_with_too_many_redirects() {
+        let (listener, connector) = bind_local().await;
+
+        let redirect: &'static [u8] =
+            b"HTTP/1.1 301 Moved Permanently\r\nLocation: /loop\r\nContent-Length: 0\r\n\r\n";
+        let _server = serve(
+            listener,
+            vec![
+                (b"\r\n\r\n", redirect),
+                (b"\r\n\r\n", redirect),
+                (b"\r\n\r\n", redirect),
+            ],
+        );
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/loop").build();
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_redirect_policy(RedirectPolicy {
+            max_redirects: 2,
+            allow_cross_origin: false,
+        });
+
+        let err = client.do_request(&req, TIMEOUT).await.err().unwrap();
+        assert!(matches!(err, MyHttpClientError::TooManyRedirects(2)));
+    }
+
+    #[tokio::test]
+    async fn cross_origin_redirect_drops_credentials() {
+        let (listener, connector) = bind_local().await;
+        let (other_listener, other_connector) = bind_local().await;
+
+        let location = format!(
+            "HTTP/1.1 302 Found\r\nLocation: http://{}/landing\r\nContent-Length: 0\r\n\r\n",
+            other_connector.host_port
+        );
+        let location: &'static [u8] = Box::leak(location.into_bytes().into_boxed_slice());
+
+        let _server = serve(
+            listener,
+            vec![(b"\r\n\r\n", location), (b"\r\n\r\n", location)],
+        );
+        let other_server = serve(
+            other_listener,
+            vec![(
+                b"\r\n\r\n",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nthere",
+            )],
+        );
+
+        let mut builder = MyHttpRequestBuilder::new(Method::GET, "/start");
+        builder.append_header("Host", "localhost");
+        builder.append_header("Authorization", "Bearer secret");
+        builder.append_header("Cookie", "session=secret");
+        builder.append_header("Proxy-Authorization", "Basic c2VjcmV0");
+        builder.append_header("Accept", "*/*");
+        let req = builder.build();
+
+        // Not allowed: the 3xx is returned as is
+        let mut client = MyHttpClient::new(TestConnector {
+            host_port: connector.host_port.clone(),
+        });
+        client.set_redirect_policy(RedirectPolicy::default());
+        client.set_redirect_connector_factory(Arc::new(TestRedirectConnectorFactory));
+
+        match client.do_request(&req, TIMEOUT).await.unwrap() {
+            MyHttpResponse::Response(response) => assert_eq!(response.status(), 302),
+            MyHttpResponse::WebSocketUpgrade { .. } => panic!("Unexpected upgrade"),
+        }
+
+        client.set_redirect_policy(RedirectPolicy {
+            allow_cross_origin: true,
+            ..Default::default()
+        });
+
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(get_body(response).await, b"there");
+
+        let received = other_server.await.unwrap();
+        assert_eq!(
+            received[0],
+            format!(
+                "GET /landing HTTP/1.1\r\nAccept: */*\r\nHost: {}\r\n\r\n",
+                other_connector.host_port
+            )
+        );
+    }
+
+    #[test]
+    fn redirect_without_body_drops_the_headers_describing_it() {
+        let mut builder = MyHttpRequestBuilder::new(Method::PUT, "/old");
+        builder.append_header("Host", "localhost");
+        builder.append_header("Content-Type", "text/plain");
+        builder.append_header("Trailer", "x-checksum");
+        builder.set_expect_continue();
+        let req = builder.build_with_body(b"abc".to_vec());
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert(http::header::LOCATION, "/new".parse().unwrap());
+        let step = RedirectStep::from_response(
+            http::StatusCode::SEE_OTHER,
+            &headers,
+            &req.get_method(),
+            req.get_path_and_query(),
+        )
+        .unwrap();
+
+        let result = super::create_redirect_request(&req, &step, None);
+        assert_eq!(
+            std::str::from_utf8(&result.headers).unwrap(),
+            "GET /new HTTP/1.1\r\nHost: localhost\r\n"
+        );
+        assert!(result.body.is_empty());
+    }
+}
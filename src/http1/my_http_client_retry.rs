@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use crate::{retry::RetryReason, MyHttpClientConnector, MyHttpClientError};
+
+use super::{MyHttpClient, MyHttpRequest, MyHttpResponse};
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > MyHttpClient<TStream, TConnector>
+{
+    /// One request-response exchange, repeated according to the retry policy.
+    pub(super) async fn do_single_request(
+        &self,
+        req: &MyHttpRequest,
+        request_timeout: Duration,
+    ) -> Result<MyHttpResponse<TStream>, MyHttpClientError> {
+        let retry_policy = match self.retry_policy.as_ref() {
+            // A streamed body is gone once sent
+            Some(retry_policy) if req.body_stream.is_none() => retry_policy,
+            _ => return self.send_attempt(req, request_timeout).await,
+        };
+
+        let method = req.get_method();
+        let mut attempt = 1;
+
+        loop {
+            let delay = match self.send_attempt(req, request_timeout).await {
+                Ok(MyHttpResponse::Response(response)) => {
+                    let delay = crate::retry::get_response_retry_delay(
+                        retry_policy.as_ref(),
+                        &method,
+                        attempt,
+                        response.status(),
+                        response.headers(),
+                    );
+
+                    match delay {
+                        Some(delay) => {
+                            crate::utils::drain_body(response, request_timeout).await;
+                            delay
+                        }
+                        None => return Ok(MyHttpResponse::Response(response)),
+                    }
+                }
+                Ok(response) => return Ok(response),
+                Err(err) => {
+                    let reason = RetryReason::Error(&err);
+                    match retry_policy.get_retry_delay(&method, attempt, &reason) {
+                        Some(delay) => delay,
+                        None => return Err(err),
+                    }
+                }
+            };
+
+            if let Some(metrics) = self.inner.metrics.as_ref() {
+                metrics.request_retried(self.inner.name.as_str(), attempt, delay);
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// One attempt, let through or failed fast by the circuit breaker.
+    async fn send_attempt(
+        &self,
+        req: &MyHttpRequest,
+        request_timeout: Duration,
+    ) -> Result<MyHttpResponse<TStream>, MyHttpClientError> {
+        let circuit_breaker = match self.circuit_breaker.as_ref() {
+            Some(circuit_breaker) => circuit_breaker,
+            None => return self.send_single_request(req, request_timeout).await,
+        };
+
+        let state_changed = |state| {
+            if let Some(metrics) = self.inner.metrics.as_ref() {
+                metrics.circuit_breaker_state_changed(self.inner.name.as_str(), state);
+            }
+        };
+
+        circuit_breaker
+            .execute(
+                self.inner.name.as_str(),
+                self.send_single_request(req, request_timeout),
+                state_changed,
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use http::Method;
+    use tokio::io::AsyncWriteExt;
+
+    use crate::{
+        http1::{test_utils::*, MyHttpClient, MyHttpRequestBuilder, MyHttpResponse},
+        retry::ExponentialBackoffRetryPolicy,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn create_policy() -> Arc<ExponentialBackoffRetryPolicy> {
+        Arc::new(ExponentialBackoffRetryPolicy {
+            initial_delay: Duration::from_millis(1),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn service_unavailable_is_retried() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            for response in [
+                &b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 4\r\n\r\nbusy"[..],
+                &b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n"[..],
+            ] {
+                assert!(read_until_ends_with(&mut socket, &mut received, b"\r\n\r\n").await);
+                socket.write_all(response).await.unwrap();
+                received.clear();
+            }
+        });
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/").build();
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_retry_policy(create_policy());
+
+        match client.do_request(&req, TIMEOUT).await.unwrap() {
+            MyHttpResponse::Response(response) => assert_eq!(response.status(), 200),
+            MyHttpResponse::WebSocketUpgrade { .. } => panic!("Unexpected upgrade"),
+        }
+
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn post_is_not_retried_on_service_unavailable() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"abc").await);
+            socket
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            // A retry would hang here until the request timeout
+            let _ = read_until_ends_with(&mut socket, &mut received, b"never").await;
+        });
+
+        let req = MyHttpRequestBuilder::new(Method::POST, "/").build_with_body(b"abc".to_vec());
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_retry_policy(create_policy());
+
+        match client.do_request(&req, TIMEOUT).await.unwrap() {
+            MyHttpResponse::Response(response) => assert_eq!(response.status(), 503),
+            MyHttpResponse::WebSocketUpgrade { .. } => panic!("Unexpected upgrade"),
+        }
+    }
+
+    #[tokio::test]
+    async fn written_post_is_not_sent_again_after_disconnect() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"abc").await);
+            drop(socket);
+
+            // A second attempt would dial again
+            tokio::time::timeout(Duration::from_millis(300), listener.accept())
+                .await
+                .is_err()
+        });
+
+        let req = MyHttpRequestBuilder::new(Method::POST, "/").build_with_body(b"abc".to_vec());
+
+        let mut client = MyHttpClient::new(connector);
+        client.set_retry_policy(create_policy());
+
+        let err = client.do_request(&req, TIMEOUT).await.err().unwrap();
+        assert!(matches!(err, crate::MyHttpClientError::Disconnected));
+        assert!(server.await.unwrap());
+    }
+}
@@ -2,14 +2,27 @@ use bytes::Bytes;
 use http::{Method, Version};
 use http_body_util::{BodyExt, Full};
 use std::fmt::Write;
+use std::sync::Arc;
+
+/// Receives the status and headers of every interim (1xx) response, e.g.
+/// `103 Early Hints`, while the request waits for its final response. It runs on
+/// the read loop of the connection, so it must not block.
+pub type MyHttpInterimResponseHandler = Arc<dyn Fn(&http::Response<()>) + Send + Sync + 'static>;
 
 #[derive(Clone)]
 pub struct MyHttpRequest {
     pub headers: Vec<u8>,
     pub body: Bytes,
+    /// Body sent from a stream as it is produced. When set, `body` is empty and
+    /// the conversions to hyper requests carry no body.
+    pub body_stream: Option<super::MyHttpRequestBodyStream>,
+    /// Interim responses are dropped when not set.
+    pub interim_response_handler: Option<MyHttpInterimResponseHandler>,
 }
 
 impl MyHttpRequest {
+    /// `path_and_query` is written to the request line as is: see [`Self::try_new`]
+    /// for a validated one.
     pub fn new<Headers: crate::MyHttpClientHeaders>(
         method: Method,
         path_and_query: &str,
@@ -20,6 +33,8 @@ impl MyHttpRequest {
         let mut result = Self {
             headers: create_headers(method, path_and_query, version).into_bytes(),
             body: body.into(),
+            body_stream: None,
+            interim_response_handler: None,
         };
 
         headers_src.copy_to(&mut result.headers);
@@ -37,6 +52,26 @@ impl MyHttpRequest {
         result
     }
 
+    /// Same as [`Self::new`], failing if `path_and_query` is not a valid request
+    /// target (see [`super::MyHttpRequestBuilder::try_new`]).
+    pub fn try_new<Headers: crate::MyHttpClientHeaders>(
+        method: Method,
+        path_and_query: &str,
+        version: Version,
+        headers_src: &Headers,
+        body: Vec<u8>,
+    ) -> Result<Self, crate::RequestBuildError> {
+        super::validate_request_target(&method, path_and_query)?;
+        Ok(Self::new(
+            method,
+            path_and_query,
+            version,
+            headers_src,
+            body,
+        ))
+    }
+
+    /// Header values are copied as bytes, so obs-text values are kept as is.
     pub async fn from_hyper_request(req: hyper::Request<Full<Bytes>>) -> Self {
         let (parts, body) = req.into_parts();
 
@@ -52,12 +87,8 @@ impl MyHttpRequest {
 
         let mut headers = headers.into_bytes();
 
-        for header in parts.headers.iter() {
-            crate::headers::write_header(
-                &mut headers,
-                header.0.as_str(),
-                header.1.to_str().unwrap(),
-            );
+        for (name, value) in parts.headers.iter() {
+            crate::headers::write_header_unchecked(&mut headers, name.as_str(), value.as_bytes());
         }
 
         let body_as_bytes = body.collect().await.unwrap().to_bytes();
@@ -65,13 +96,30 @@ impl MyHttpRequest {
         Self {
             headers,
             body: body_as_bytes,
+            body_stream: None,
+            interim_response_handler: None,
         }
     }
 
     pub fn write_to(&self, writer: &mut Vec<u8>) {
+        self.write_headers_to(writer);
+        writer.extend_from_slice(&self.body);
+    }
+
+    pub fn write_headers_to(&self, writer: &mut Vec<u8>) {
         writer.extend_from_slice(&self.headers);
         writer.extend_from_slice(crate::CL_CR);
-        writer.extend_from_slice(&self.body);
+    }
+
+    /// The request has a body and `Expect: 100-continue`, so the body is sent
+    /// only once the server answers `100 Continue`.
+    pub fn expects_continue(&self) -> bool {
+        if self.body.is_empty() && self.body_stream.is_none() {
+            return false;
+        }
+
+        super::get_header_value(&self.headers, "expect")
+            .is_some_and(|value| value.eq_ignore_ascii_case(b"100-continue"))
     }
 
     /// Extracts the HTTP method from the serialized request line (the first
@@ -86,6 +134,19 @@ impl MyHttpRequest {
 
         Method::from_bytes(&self.headers[..end]).unwrap_or(Method::GET)
     }
+
+    /// Request target of the serialized request line (the second token).
+    pub fn get_path_and_query(&self) -> &str {
+        let line_end = self
+            .headers
+            .windows(2)
+            .position(|itm| itm == crate::CL_CR)
+            .unwrap_or(self.headers.len());
+
+        let line = std::str::from_utf8(&self.headers[..line_end]).unwrap_or_default();
+
+        line.split(' ').nth(1).unwrap_or("/")
+    }
 }
 
 fn create_headers(method: Method, path_and_query: &str, version: Version) -> String {
@@ -0,0 +1,320 @@
+use std::{pin::Pin, sync::Arc};
+
+use bytes::Bytes;
+use futures::Stream;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::MyHttpRequestTrailers;
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+pub type MyHttpRequestBodyStreamBoxed =
+    Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static>>;
+
+/// A request body that is sent to the socket as it is produced instead of being
+/// buffered in memory first.
+///
+/// With a known `content_length` the body is sent as is after a `Content-Length`
+/// header; otherwise it is sent with `Transfer-Encoding: chunked`. The write loop
+/// pulls the next piece only once the previous one reached the socket, so a slow
+/// upstream slows down the producer instead of growing a buffer.
+///
+/// A stream can be sent only once. Clones share the same stream, and a request
+/// whose stream was already consumed (e.g. by a send that hit a disconnect) fails
+/// with [`crate::MyHttpClientError::CanNotExecuteRequest`] instead of being replayed.
+#[derive(Clone)]
+pub struct MyHttpRequestBodyStream {
+    stream: Arc<parking_lot::Mutex<Option<MyHttpRequestBodyStreamBoxed>>>,
+    content_length: Option<u64>,
+    trailers: Arc<parking_lot::Mutex<Option<MyHttpRequestTrailers>>>,
+}
+
+impl MyHttpRequestBodyStream {
+    pub fn from_stream(
+        stream: impl Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+        content_length: Option<u64>,
+    ) -> Self {
+        Self {
+            stream: Arc::new(parking_lot::Mutex::new(Some(Box::pin(stream)))),
+            content_length,
+            trailers: Arc::new(parking_lot::Mutex::new(None)),
+        }
+    }
+
+    pub fn from_async_read(
+        reader: impl AsyncRead + Send + 'static,
+        content_length: Option<u64>,
+    ) -> Self {
+        let reader = Box::pin(reader);
+
+        let stream = futures::stream::unfold(Some(reader), |reader| async move {
+            let mut reader = reader?;
+            let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+            match reader.read(&mut buffer).await {
+                Ok(0) => None,
+                Ok(read) => {
+                    buffer.truncate(read);
+                    Some((Ok(Bytes::from(buffer)), Some(reader)))
+                }
+                // The error ends the stream
+                Err(err) => Some((Err(err), None)),
+            }
+        });
+
+        Self::from_stream(stream, content_length)
+    }
+
+    pub fn get_content_length(&self) -> Option<u64> {
+        self.content_length
+    }
+
+    pub fn is_chunked(&self) -> bool {
+        self.content_length.is_none()
+    }
+
+    pub fn is_consumed(&self) -> bool {
+        self.stream.lock().is_none()
+    }
+
+    pub(crate) fn take_stream(&self) -> Option<MyHttpRequestBodyStreamBoxed> {
+        self.stream.lock().take()
+    }
+
+    pub(crate) fn set_trailers(&self, trailers: MyHttpRequestTrailers) {
+        *self.trailers.lock() = Some(trailers);
+    }
+
+    pub(crate) fn take_trailers(&self) -> Option<MyHttpRequestTrailers> {
+        self.trailers.lock().take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use http::Method;
+    use tokio::io::AsyncWriteExt;
+
+    use super::MyHttpRequestBodyStream;
+    use crate::{
+        http1::{test_utils::*, MyHttpClient, MyHttpRequestBuilder},
+        MyHttpClientError, RequestBuildError,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn body_stream_without_length_is_sent_chunked() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"0\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            received
+        });
+
+        let body = futures::stream::iter(vec![
+            Ok(Bytes::from_static(b"Hello")),
+            Ok(Bytes::new()),
+            Ok(Bytes::from_static(b", streamed world")),
+        ]);
+
+        let mut builder = MyHttpRequestBuilder::new(Method::POST, "/upload");
+        builder.append_header("Host", "localhost");
+        let req = builder.build_with_stream(MyHttpRequestBodyStream::from_stream(body, None));
+
+        let client = MyHttpClient::new(connector);
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&received).unwrap(),
+            "POST /upload HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n10\r\n, streamed world\r\n0\r\n\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn body_stream_with_length_is_sent_as_is() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"0123456789").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            received
+        });
+
+        let reader = std::io::Cursor::new(b"0123456789".to_vec());
+        let req = MyHttpRequestBuilder::new(Method::PUT, "/file")
+            .build_with_stream(MyHttpRequestBodyStream::from_async_read(reader, Some(10)));
+
+        let client = MyHttpClient::new(connector);
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&received).unwrap(),
+            "PUT /file HTTP/1.1\r\nContent-Length: 10\r\n\r\n0123456789"
+        );
+    }
+
+    #[tokio::test]
+    async fn trailers_are_sent_after_the_last_chunk() {
+        let (listener, connector) = bind_local().await;
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            assert!(read_until_ends_with(&mut socket, &mut received, b"f7ff9e8b\r\n\r\n").await);
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            received
+        });
+
+        let body = futures::stream::iter(vec![Ok(Bytes::from_static(b"Hello"))]);
+
+        let mut builder = MyHttpRequestBuilder::new(Method::POST, "/upload");
+        builder.set_trailers_producer(&["x-checksum"], || {
+            let mut trailers = http::HeaderMap::new();
+            trailers.insert("x-checksum", http::HeaderValue::from_static("f7ff9e8b"));
+            trailers
+        });
+        let req = builder.build_with_stream(MyHttpRequestBodyStream::from_stream(body, None));
+
+        let client = MyHttpClient::new(connector);
+        let response = client.do_request(&req, TIMEOUT).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let received = server.await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&received).unwrap(),
+            "POST /upload HTTP/1.1\r\nTrailer: x-checksum\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\nx-checksum: f7ff9e8b\r\n\r\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "chunked body stream")]
+    fn trailers_with_known_length_panic() {
+        let mut builder = MyHttpRequestBuilder::new(Method::PUT, "/file");
+        builder.set_trailers(http::HeaderMap::new());
+
+        let reader = std::io::Cursor::new(b"0123456789".to_vec());
+        builder.build_with_stream(MyHttpRequestBodyStream::from_async_read(reader, Some(10)));
+    }
+
+    #[test]
+    fn caller_framing_headers_must_fit_the_body_stream() {
+        let body_with_length = || {
+            MyHttpRequestBodyStream::from_async_read(std::io::Cursor::new(vec![0u8; 10]), Some(10))
+        };
+        let chunked_body =
+            || MyHttpRequestBodyStream::from_stream(futures::stream::iter(Vec::new()), None);
+
+        for (name, value, body) in [
+            ("Content-Length", "11", body_with_length()),
+            ("Transfer-Encoding", "chunked", body_with_length()),
+            ("Content-Length", "10", chunked_body()),
+        ] {
+            let mut builder = MyHttpRequestBuilder::new(Method::PUT, "/file");
+            builder.append_header(name, value);
+
+            match builder.try_build_with_stream(body) {
+                Err(RequestBuildError::InvalidBodyFraming { .. }) => {}
+                Err(err) => panic!("Unexpected error: {}", err),
+                Ok(_) => panic!("{}: {} was accepted", name, value),
+            }
+        }
+
+        let mut builder = MyHttpRequestBuilder::new(Method::PUT, "/file");
+        builder.append_header("Content-Length", "10");
+        let req = builder.try_build_with_stream(body_with_length()).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&req.headers).unwrap(),
+            "PUT /file HTTP/1.1\r\nContent-Length: 10\r\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "streamed body")]
+    fn streamed_request_is_not_converted_to_hyper() {
+        let body = futures::stream::iter(vec![Ok(Bytes::from_static(b"lost"))]);
+        MyHttpRequestBuilder::new(Method::POST, "/")
+            .build_with_stream(MyHttpRequestBodyStream::from_stream(body, None))
+            .to_hyper_h1_request();
+    }
+
+    #[tokio::test]
+    async fn stalled_body_stream_does_not_block_other_requests() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let _ = read_until_ends_with(&mut socket, &mut received, b"never").await;
+        });
+
+        let client = std::sync::Arc::new(MyHttpClient::new(connector));
+
+        let stalled_client = client.clone();
+        tokio::spawn(async move {
+            let body = futures::stream::pending();
+            let req = MyHttpRequestBuilder::new(Method::POST, "/upload")
+                .build_with_stream(MyHttpRequestBodyStream::from_stream(body, None));
+            let _ = stalled_client.do_request(&req, TIMEOUT).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let req = MyHttpRequestBuilder::new(Method::GET, "/next").build();
+        let result = tokio::time::timeout(
+            Duration::from_secs(2),
+            client.do_request(&req, Duration::from_millis(200)),
+        )
+        .await
+        .expect("The request was blocked by the body stream");
+
+        assert!(matches!(result, Err(MyHttpClientError::RequestTimeout(_))));
+    }
+
+    #[tokio::test]
+    async fn consumed_body_stream_is_not_replayed() {
+        let (listener, connector) = bind_local().await;
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            read_until_ends_with(&mut socket, &mut received, b"0\r\n\r\n").await;
+            let _ = socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await;
+            // Keep the connection open for the second attempt
+            let _ = read_until_ends_with(&mut socket, &mut received, b"never").await;
+        });
+
+        let body = futures::stream::iter(vec![Ok(Bytes::from_static(b"once"))]);
+        let req = MyHttpRequestBuilder::new(Method::POST, "/")
+            .build_with_stream(MyHttpRequestBodyStream::from_stream(body, None));
+
+        let client = MyHttpClient::new(connector);
+        client.do_request(&req, TIMEOUT).await.unwrap();
+        assert!(req.body_stream.as_ref().unwrap().is_consumed());
+
+        let err = client.do_request(&req, TIMEOUT).await.err().unwrap();
+        assert!(matches!(err, MyHttpClientError::CanNotExecuteRequest(_)));
+    }
+}
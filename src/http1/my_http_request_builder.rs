@@ -1,13 +1,27 @@
-use http::Method;
+use std::str::FromStr;
 
-use super::MyHttpRequest;
-use crate::headers::{validate_header_name, validate_header_value};
+use bytes::Bytes;
+use http::{HeaderMap, Method, Uri};
+
+use super::{
+    MyHttpInterimResponseHandler, MyHttpRequest, MyHttpRequestBodyStream, MyHttpRequestTrailers,
+};
+use crate::compression::ContentEncoding;
+use crate::headers::try_write_header;
+use crate::RequestBuildError;
 
 pub struct MyHttpRequestBuilder {
     headers: Vec<u8>,
+    body_compression: Option<ContentEncoding>,
+    trailers: Option<MyHttpRequestTrailers>,
+    interim_response_handler: Option<MyHttpInterimResponseHandler>,
+    /// `Host` written at build time unless the caller appended one
+    host: Option<String>,
 }
 
 impl MyHttpRequestBuilder {
+    /// Panics only on bytes that would break the request line (CR, LF, NUL or
+    /// space); the target is sent as is otherwise. [`Self::try_new`] validates it.
     pub fn new(method: Method, path_and_query: &str) -> Self {
         for &b in path_and_query.as_bytes() {
             if b == b'\r' || b == b'\n' || b == 0 || b == b' ' {
@@ -17,26 +31,230 @@ impl MyHttpRequestBuilder {
                 );
             }
         }
+
+        Self::create(method, path_and_query, None)
+    }
+
+    /// Request with an origin-form target (`/path?query`). `CONNECT` also takes an
+    /// authority-form target (`host:port`) and `OPTIONS` takes `*`.
+    pub fn try_new(method: Method, path_and_query: &str) -> Result<Self, RequestBuildError> {
+        validate_request_target(&method, path_and_query)?;
+        Ok(Self::create(method, path_and_query, None))
+    }
+
+    /// Panics if `uri` is not a valid absolute URI: see [`Self::try_new_absolute`].
+    pub fn new_absolute(method: Method, uri: &str) -> Self {
+        match Self::try_new_absolute(method, uri) {
+            Ok(builder) => builder,
+            Err(err) => panic!("Can not create request builder: {}", err),
+        }
+    }
+
+    /// Request with an absolute-form target (`http://host/path?query`), as plain
+    /// HTTP forward proxies expect. `Host` is set from the authority of `uri`
+    /// unless appended explicitly. Redirects of such requests are not followed.
+    pub fn try_new_absolute(method: Method, uri: &str) -> Result<Self, RequestBuildError> {
+        let (target, host) =
+            parse_absolute_form(uri).map_err(|reason| RequestBuildError::InvalidRequestTarget {
+                target: uri.to_string(),
+                reason,
+            })?;
+
+        Ok(Self::create(method, target.as_str(), Some(host)))
+    }
+
+    fn create(method: Method, target: &str, host: Option<String>) -> Self {
         let mut headers = Vec::new();
         headers.extend_from_slice(method.as_str().as_bytes());
         headers.push(b' ');
-        headers.extend_from_slice(path_and_query.as_bytes());
+        headers.extend_from_slice(target.as_bytes());
         headers.push(b' ');
         headers.extend_from_slice(b"HTTP/1.1\r\n");
-        Self { headers }
+        Self {
+            headers,
+            body_compression: None,
+            trailers: None,
+            interim_response_handler: None,
+            host,
+        }
+    }
+
+    /// `Host` sent unless the caller appends one.
+    /// [`super::MyHttpClient::create_request_builder`] sets it from the remote
+    /// endpoint of the connector.
+    pub fn set_default_host(&mut self, host: &str) {
+        self.host = Some(host.to_string());
     }
 
+    /// Panics if `name` or `value` is invalid: see [`Self::try_append_header`].
     pub fn append_header(&mut self, name: &str, value: &str) {
-        validate_header_name(name);
-        validate_header_value(value);
-        self.headers.extend_from_slice(name.as_bytes());
-        self.headers.push(b':');
-        self.headers.push(b' ');
-        self.headers.extend_from_slice(value.as_bytes());
-        self.headers.extend_from_slice(crate::CL_CR);
+        if let Err(err) = self.try_append_header(name, value) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Fails if `name` is not a token or `value` has a control byte other than
+    /// HTAB or a DEL.
+    pub fn try_append_header(&mut self, name: &str, value: &str) -> Result<(), RequestBuildError> {
+        self.try_append_header_bytes(name, value.as_bytes())
+    }
+
+    /// Same as [`Self::try_append_header`] for a value sent as opaque bytes, e.g.
+    /// obs-text (0x80-0xFF) that is not UTF-8.
+    pub fn try_append_header_bytes(
+        &mut self,
+        name: &str,
+        value: &[u8],
+    ) -> Result<(), RequestBuildError> {
+        try_write_header(&mut self.headers, name, value)?;
+        Ok(())
+    }
+
+    /// `Authorization: Bearer <token>`
+    pub fn bearer_auth(&mut self, token: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_bearer_auth(&mut self.headers, token)
+    }
+
+    /// `Authorization: Basic` with `user_name:password` in base64
+    pub fn basic_auth(&mut self, user_name: &str, password: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_basic_auth(&mut self.headers, user_name, password)
+    }
+
+    pub fn content_type(&mut self, content_type: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_content_type(&mut self.headers, content_type)
+    }
+
+    pub fn accept(&mut self, accept: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_accept(&mut self.headers, accept)
+    }
+
+    pub fn user_agent(&mut self, user_agent: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_user_agent(&mut self.headers, user_agent)
+    }
+
+    /// `Range: bytes=<start>-<end>`, open-ended when `end` is `None`. Both ends
+    /// are inclusive.
+    pub fn range(&mut self, start: u64, end: Option<u64>) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_range(&mut self.headers, start, end)
+    }
+
+    /// `entity_tag` is sent as is, e.g. `"abc"`, `W/"abc"` or `*`.
+    pub fn if_none_match(&mut self, entity_tag: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_if_none_match(&mut self.headers, entity_tag)
+    }
+
+    /// `time` is sent as an HTTP-date, to the second.
+    pub fn if_modified_since(
+        &mut self,
+        time: std::time::SystemTime,
+    ) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_if_modified_since(&mut self.headers, time)
+    }
+
+    /// Adds `name=value` to the `Cookie` header, which holds all cookies of the
+    /// request.
+    pub fn cookie(&mut self, name: &str, value: &str) -> Result<(), RequestBuildError> {
+        crate::typed_headers::write_cookie(&mut self.headers, name, value)
+    }
+
+    /// Adds `Expect: 100-continue`: the body is sent once the server answers
+    /// `100 Continue`, and not sent at all if it answers with a final status
+    /// first. See [`super::MyHttpClient::set_expect_continue_timeout`].
+    pub fn set_expect_continue(&mut self) {
+        self.append_header("Expect", "100-continue");
+    }
+
+    /// Calls `handler` with every interim (1xx) response, e.g. the `Link` headers
+    /// of `103 Early Hints`, before `do_request` returns the final response.
+    /// Send them to a channel from `handler` to consume them elsewhere.
+    pub fn set_interim_response_handler(
+        &mut self,
+        handler: impl Fn(&http::Response<()>) + Send + Sync + 'static,
+    ) {
+        self.interim_response_handler = Some(std::sync::Arc::new(handler));
+    }
+
+    /// Makes [`Self::build_with_body`] compress a non-empty body with `encoding`,
+    /// set `Content-Encoding` and recompute `Content-Length`. The body is sent as
+    /// is if the request already has a `Content-Encoding` header.
+    ///
+    /// Fails if the cargo feature of `encoding` is not enabled.
+    pub fn try_set_body_compression(
+        &mut self,
+        encoding: ContentEncoding,
+    ) -> Result<(), RequestBuildError> {
+        if !encoding.is_supported() {
+            return Err(RequestBuildError::UnsupportedContentEncoding {
+                encoding: encoding.as_str(),
+            });
+        }
+
+        self.body_compression = Some(encoding);
+        Ok(())
+    }
+
+    /// Same as [`Self::try_set_body_compression`]. Panics if the cargo feature of
+    /// `encoding` is not enabled.
+    pub fn set_body_compression(&mut self, encoding: ContentEncoding) {
+        if let Err(err) = self.try_set_body_compression(encoding) {
+            panic!("{}", err);
+        }
+    }
+
+    /// Sends `trailers` after the last chunk of the body and declares their names
+    /// in a `Trailer` header. Trailers need a chunked body: build the request with
+    /// [`Self::build_with_stream`] and a stream without a content length, other
+    /// builds panic.
+    pub fn set_trailers(&mut self, trailers: HeaderMap) {
+        let names: Vec<&str> = trailers.keys().map(|name| name.as_str()).collect();
+        self.declare_trailers(&names);
+        self.trailers = Some(MyHttpRequestTrailers::Fields(trailers));
+    }
+
+    /// Same as [`Self::set_trailers`], with the trailers produced by `producer`
+    /// once the body stream has ended. `declared_names` go to the `Trailer` header.
+    pub fn set_trailers_producer(
+        &mut self,
+        declared_names: &[&str],
+        producer: impl FnOnce() -> HeaderMap + Send + 'static,
+    ) {
+        self.declare_trailers(declared_names);
+        self.trailers = Some(MyHttpRequestTrailers::Producer(Box::new(producer)));
+    }
+
+    fn declare_trailers(&mut self, names: &[&str]) {
+        if !names.is_empty() && !super::headers_contains(&self.headers, "trailer") {
+            self.append_header("Trailer", names.join(", ").as_str());
+        }
     }
 
-    pub fn build_with_body(mut self, body: Vec<u8>) -> MyHttpRequest {
+    fn append_host_if_missing(&mut self) {
+        if let Some(host) = self.host.take() {
+            if !super::headers_contains(&self.headers, "host") {
+                self.append_header("Host", host.as_str());
+            }
+        }
+    }
+
+    fn panic_if_trailers_set(&self) {
+        if self.trailers.is_some() {
+            panic!("Request trailers can only be sent with a chunked body stream");
+        }
+    }
+
+    pub fn build_with_body(mut self, mut body: Vec<u8>) -> MyHttpRequest {
+        self.panic_if_trailers_set();
+        self.append_host_if_missing();
+
+        if let Some(encoding) = self.body_compression {
+            if !body.is_empty() && !super::headers_contains(&self.headers, "content-encoding") {
+                body = crate::compression::compress(encoding, &body)
+                    .expect("Compressing into memory can not fail");
+                self.append_header("Content-Encoding", encoding.as_str());
+                super::remove_header(&mut self.headers, "content-length");
+            }
+        }
+
         if !body.is_empty() && !super::headers_contains(&self.headers, "content-length") {
             self.append_header("Content-Length", body.len().to_string().as_str());
         }
@@ -44,13 +262,287 @@ impl MyHttpRequestBuilder {
         MyHttpRequest {
             headers: self.headers,
             body: body.into(),
+            body_stream: None,
+            interim_response_handler: self.interim_response_handler,
         }
     }
 
-    pub fn build(self) -> MyHttpRequest {
+    /// Builds a request whose body is sent from `body` as it is produced. A body
+    /// with a known length gets a `Content-Length` header, any other is sent with
+    /// `Transfer-Encoding: chunked`.
+    ///
+    /// Fails if the caller already set a `Content-Length` other than the length
+    /// of `body`, set `Content-Length` for a chunked body or `Transfer-Encoding`
+    /// for a body with a length, or set trailers for a body with a length.
+    pub fn try_build_with_stream(
+        mut self,
+        body: MyHttpRequestBodyStream,
+    ) -> Result<MyHttpRequest, RequestBuildError> {
+        let content_length_header =
+            super::get_header_value(&self.headers, "content-length").map(|itm| itm.to_vec());
+        let has_transfer_encoding = super::headers_contains(&self.headers, "transfer-encoding");
+
+        match body.get_content_length() {
+            Some(content_length) => {
+                if has_transfer_encoding {
+                    return Err(RequestBuildError::InvalidBodyFraming {
+                        reason: "Transfer-Encoding is set for a body stream with a length",
+                    });
+                }
+
+                if let Some(value) = content_length_header.as_ref() {
+                    if value.as_slice() != content_length.to_string().as_bytes() {
+                        return Err(RequestBuildError::InvalidBodyFraming {
+                            reason: "Content-Length does not match the length of the body stream",
+                        });
+                    }
+                }
+            }
+            None => {
+                if content_length_header.is_some() {
+                    return Err(RequestBuildError::InvalidBodyFraming {
+                        reason: "Content-Length is set for a chunked body stream",
+                    });
+                }
+            }
+        }
+
+        if self.trailers.is_some() && !body.is_chunked() {
+            return Err(RequestBuildError::InvalidBodyFraming {
+                reason: "Request trailers can only be sent with a chunked body stream",
+            });
+        }
+
+        self.append_host_if_missing();
+
+        if let Some(trailers) = self.trailers.take() {
+            body.set_trailers(trailers);
+        }
+
+        match body.get_content_length() {
+            Some(content_length) => {
+                if content_length_header.is_none() {
+                    self.append_header("Content-Length", content_length.to_string().as_str());
+                }
+            }
+            None => {
+                if !has_transfer_encoding {
+                    self.append_header("Transfer-Encoding", "chunked");
+                }
+            }
+        }
+
+        Ok(MyHttpRequest {
+            headers: self.headers,
+            body: Bytes::new(),
+            body_stream: Some(body),
+            interim_response_handler: self.interim_response_handler,
+        })
+    }
+
+    /// Same as [`Self::try_build_with_stream`]. Panics if the headers do not fit
+    /// the body stream.
+    pub fn build_with_stream(self, body: MyHttpRequestBodyStream) -> MyHttpRequest {
+        match self.try_build_with_stream(body) {
+            Ok(request) => request,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    pub fn build(mut self) -> MyHttpRequest {
+        self.panic_if_trailers_set();
+        self.append_host_if_missing();
+
         MyHttpRequest {
             headers: self.headers,
             body: Vec::new().into(),
+            body_stream: None,
+            interim_response_handler: self.interim_response_handler,
+        }
+    }
+}
+
+/// Origin-form for all methods, authority-form for `CONNECT` and `*` for `OPTIONS`.
+pub(super) fn validate_request_target(
+    method: &Method,
+    target: &str,
+) -> Result<(), RequestBuildError> {
+    let valid = if *method == Method::CONNECT && !target.starts_with('/') {
+        validate_authority_form(target)
+    } else if *method == Method::OPTIONS && target == "*" {
+        Ok(())
+    } else {
+        validate_origin_form(target)
+    };
+
+    valid.map_err(|reason| RequestBuildError::InvalidRequestTarget {
+        target: target.to_string(),
+        reason,
+    })
+}
+
+/// Visible ASCII without `#`: anything else must be percent-encoded.
+fn validate_target_bytes(target: &str) -> Result<(), &'static str> {
+    if target.bytes().any(|b| !(0x21..=0x7e).contains(&b)) {
+        return Err("contains whitespace, control or non-ASCII bytes");
+    }
+
+    if target.contains('#') {
+        return Err("contains a fragment");
+    }
+
+    Ok(())
+}
+
+fn validate_origin_form(target: &str) -> Result<(), &'static str> {
+    if !target.starts_with('/') {
+        return Err("origin-form target must start with '/'");
+    }
+
+    validate_target_bytes(target)?;
+
+    http::uri::PathAndQuery::from_str(target).map_err(|_| "malformed path and query")?;
+
+    Ok(())
+}
+
+fn validate_authority_form(target: &str) -> Result<(), &'static str> {
+    validate_target_bytes(target)?;
+
+    let authority = http::uri::Authority::from_str(target).map_err(|_| "malformed authority")?;
+
+    if authority.port_u16().is_none() || target.contains('@') {
+        return Err("authority-form target must be host:port");
+    }
+
+    Ok(())
+}
+
+/// Returns the target to send and the `Host` value of an absolute URI.
+fn parse_absolute_form(uri: &str) -> Result<(String, String), &'static str> {
+    validate_target_bytes(uri)?;
+
+    let uri = Uri::from_str(uri).map_err(|_| "malformed absolute URI")?;
+
+    match uri.scheme_str() {
+        Some("http") | Some("https") => {}
+        Some(_) => return Err("scheme must be http or https"),
+        None => return Err("absolute-form target needs a scheme"),
+    }
+
+    let authority = match uri.authority() {
+        Some(authority) if !authority.host().is_empty() => authority,
+        _ => return Err("absolute-form target needs a host"),
+    };
+
+    if authority.as_str().contains('@') {
+        return Err("user info is not allowed in the target");
+    }
+
+    let host = authority.as_str().to_string();
+
+    // `path()` is `/` when the URI has none, so `http://host?q` is sent as `http://host/?q`
+    let mut target = format!("{}://{}{}", uri.scheme_str().unwrap(), host, uri.path());
+    if let Some(query) = uri.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+
+    Ok((target, host))
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Method;
+
+    use super::MyHttpRequestBuilder;
+    use crate::RequestBuildError;
+
+    fn request_line_and_headers(builder: MyHttpRequestBuilder) -> String {
+        String::from_utf8(builder.build().headers).unwrap()
+    }
+
+    #[test]
+    fn malformed_targets_are_errors() {
+        for target in ["", "path", "/a b", "/a\r\nX: y", "/a#b", "/caf\u{e9}"] {
+            match MyHttpRequestBuilder::try_new(Method::GET, target) {
+                Err(RequestBuildError::InvalidRequestTarget {
+                    target: err_target, ..
+                }) => {
+                    assert_eq!(err_target, target)
+                }
+                Err(err) => panic!("Unexpected error: {}", err),
+                Ok(_) => panic!("Target '{}' was accepted", target),
+            }
+        }
+
+        assert!(MyHttpRequestBuilder::try_new(Method::GET, "/a/b?c=d%20e").is_ok());
+        assert!(MyHttpRequestBuilder::try_new(Method::OPTIONS, "*").is_ok());
+        assert!(MyHttpRequestBuilder::try_new(Method::CONNECT, "example.com:443").is_ok());
+        assert!(MyHttpRequestBuilder::try_new(Method::CONNECT, "example.com").is_err());
+        assert!(MyHttpRequestBuilder::try_new_absolute(Method::GET, "/path").is_err());
+        assert!(MyHttpRequestBuilder::try_new_absolute(Method::GET, "ftp://host/").is_err());
+        assert!(MyHttpRequestBuilder::try_new_absolute(Method::GET, "http://u:p@host/").is_err());
+    }
+
+    #[test]
+    fn new_sends_targets_it_accepted_before() {
+        for target in ["", "path", "/a#b", "/caf\u{e9}"] {
+            let builder = MyHttpRequestBuilder::new(Method::GET, target);
+            assert!(request_line_and_headers(builder)
+                .starts_with(&format!("GET {} HTTP/1.1\r\n", target)));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "request line injection")]
+    fn new_rejects_request_line_injection() {
+        MyHttpRequestBuilder::new(Method::GET, "/a HTTP/1.1\r\nX: y");
+    }
+
+    #[test]
+    fn absolute_form_sets_host() {
+        let builder =
+            MyHttpRequestBuilder::try_new_absolute(Method::GET, "http://example.com:8080/a?b=c")
+                .unwrap();
+        assert_eq!(
+            request_line_and_headers(builder),
+            "GET http://example.com:8080/a?b=c HTTP/1.1\r\nHost: example.com:8080\r\n"
+        );
+
+        let builder = MyHttpRequestBuilder::new_absolute(Method::GET, "http://example.com");
+        assert_eq!(
+            request_line_and_headers(builder),
+            "GET http://example.com/ HTTP/1.1\r\nHost: example.com\r\n"
+        );
+
+        let builder = MyHttpRequestBuilder::new_absolute(Method::GET, "http://example.com?x=1");
+        assert_eq!(
+            request_line_and_headers(builder),
+            "GET http://example.com/?x=1 HTTP/1.1\r\nHost: example.com\r\n"
+        );
+
+        let mut builder = MyHttpRequestBuilder::new_absolute(Method::GET, "http://example.com/");
+        builder.append_header("Host", "other.example.com");
+        assert_eq!(
+            request_line_and_headers(builder),
+            "GET http://example.com/ HTTP/1.1\r\nHost: other.example.com\r\n"
+        );
+    }
+
+    #[test]
+    fn body_compression_without_its_feature_is_an_error() {
+        for encoding in crate::compression::ContentEncoding::ALL {
+            let mut builder = MyHttpRequestBuilder::new(Method::POST, "/upload");
+
+            match builder.try_set_body_compression(encoding) {
+                Ok(()) => assert!(encoding.is_supported()),
+                Err(RequestBuildError::UnsupportedContentEncoding { encoding: name }) => {
+                    assert!(!encoding.is_supported());
+                    assert_eq!(name, encoding.as_str());
+                }
+                Err(err) => panic!("Unexpected error: {}", err),
+            }
         }
     }
 }
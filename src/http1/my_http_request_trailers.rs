@@ -0,0 +1,28 @@
+use http::HeaderMap;
+
+/// Trailer fields sent after the last chunk of a streamed request body.
+pub enum MyHttpRequestTrailers {
+    Fields(HeaderMap),
+    /// Called once the body stream has ended, e.g. to send a digest of the body
+    /// computed while it was streamed.
+    Producer(Box<dyn FnOnce() -> HeaderMap + Send + 'static>),
+}
+
+impl MyHttpRequestTrailers {
+    pub fn into_fields(self) -> HeaderMap {
+        match self {
+            MyHttpRequestTrailers::Fields(fields) => fields,
+            MyHttpRequestTrailers::Producer(producer) => producer(),
+        }
+    }
+
+    /// Serializes the trailer section without the empty line that ends it.
+    pub(crate) fn write_to(self, dest: &mut Vec<u8>) {
+        for (name, value) in self.into_fields().iter() {
+            dest.extend_from_slice(name.as_str().as_bytes());
+            dest.extend_from_slice(b": ");
+            dest.extend_from_slice(value.as_bytes());
+            dest.extend_from_slice(crate::CL_CR);
+        }
+    }
+}
@@ -1,12 +1,19 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use bytes::Bytes;
 use http::Method;
 use http_body_util::combinators::BoxBody;
 use parking_lot::Mutex;
 use rust_extensions::{TaskCompletion, TaskCompletionAwaiter};
-use tokio::io::ReadHalf;
+use tokio::{io::ReadHalf, sync::OwnedSemaphorePermit};
 
+use super::MyHttpInterimResponseHandler;
 use crate::MyHttpClientError;
 
 pub type HttpAwaitingTask<TStream> = TaskCompletion<HttpTask<TStream>, MyHttpClientError>;
@@ -48,6 +55,36 @@ impl<TStream: tokio::io::AsyncRead + Send + Sync + 'static> HttpTask<TStream> {
 struct QueuedRequest<TStream: tokio::io::AsyncRead + Send + Sync + 'static> {
     method: Method,
     task: HttpAwaitingTask<TStream>,
+    /// Set while the body of an `Expect: 100-continue` request waits for the
+    /// server: `true` sends the body, `false` skips it.
+    continue_sender: Option<tokio::sync::oneshot::Sender<bool>>,
+    interim_response_handler: Option<MyHttpInterimResponseHandler>,
+    abandoned: Arc<AtomicBool>,
+    /// Set once the first byte of the request is about to be written
+    written: Arc<AtomicBool>,
+    in_flight_permit: Option<OwnedSemaphorePermit>,
+}
+
+/// A request taken off the queue to get its response.
+pub struct PoppedRequest<TStream: tokio::io::AsyncRead + Send + Sync + 'static> {
+    pub task: HttpAwaitingTask<TStream>,
+    /// See [`super::MyHttpClient::set_max_in_flight`]. The read loop holds it
+    /// until the body of the response is read, streamed bodies included.
+    pub in_flight_permit: Option<OwnedSemaphorePermit>,
+}
+
+/// Held by the caller waiting for a queued request. Dropping it while the
+/// request is still queued (the caller timed out or was cancelled) marks the
+/// request as abandoned, so the read loop discards its response.
+pub struct AbandonedRequestGuard {
+    abandoned: Arc<AtomicBool>,
+}
+
+impl Drop for AbandonedRequestGuard {
+    fn drop(&mut self) {
+        // Harmless once the response is delivered: the request is no longer queued
+        self.abandoned.store(true, Ordering::Relaxed);
+    }
 }
 
 pub struct QueueOfRequests<TStream: tokio::io::AsyncRead + Send + Sync + 'static> {
@@ -67,12 +104,80 @@ impl<TStream: tokio::io::AsyncRead + Send + Sync + 'static> QueueOfRequests<TStr
         }
     }
 
-    pub fn push(&self, method: Method, task: HttpAwaitingTask<TStream>) {
-        self.queue.lock().push_back(QueuedRequest { method, task });
+    pub fn push(&self, method: Method, task: HttpAwaitingTask<TStream>) -> AbandonedRequestGuard {
+        self.push_with_handlers(
+            method,
+            task,
+            None,
+            None,
+            Arc::new(AtomicBool::new(true)),
+            None,
+        )
+    }
+
+    pub fn push_with_handlers(
+        &self,
+        method: Method,
+        task: HttpAwaitingTask<TStream>,
+        continue_sender: Option<tokio::sync::oneshot::Sender<bool>>,
+        interim_response_handler: Option<MyHttpInterimResponseHandler>,
+        written: Arc<AtomicBool>,
+        in_flight_permit: Option<OwnedSemaphorePermit>,
+    ) -> AbandonedRequestGuard {
+        let abandoned = Arc::new(AtomicBool::new(false));
+
+        self.queue.lock().push_back(QueuedRequest {
+            method,
+            task,
+            continue_sender,
+            interim_response_handler,
+            abandoned: abandoned.clone(),
+            written,
+            in_flight_permit,
+        });
+
+        AbandonedRequestGuard { abandoned }
     }
 
-    pub fn pop(&self) -> Option<HttpAwaitingTask<TStream>> {
-        self.queue.lock().pop_front().map(|itm| itm.task)
+    /// Nobody waits for the response of the front request anymore.
+    pub fn front_is_abandoned(&self) -> bool {
+        self.queue
+            .lock()
+            .front()
+            .is_some_and(|itm| itm.abandoned.load(Ordering::Relaxed))
+    }
+
+    /// Passes an interim (1xx) response to the handler of the front request.
+    pub fn notify_front_interim(&self, response: &http::Response<()>) {
+        let handler = match self.queue.lock().front() {
+            Some(itm) => itm.interim_response_handler.clone(),
+            None => None,
+        };
+
+        if let Some(handler) = handler {
+            handler(response);
+        }
+    }
+
+    /// Tells the write side whether to send the body of the front request if it
+    /// is waiting for `100 Continue`. Returns `false` if nothing was waiting.
+    pub fn notify_front_continue(&self, send_body: bool) -> bool {
+        let continue_sender = match self.queue.lock().front_mut() {
+            Some(itm) => itm.continue_sender.take(),
+            None => None,
+        };
+
+        match continue_sender {
+            Some(continue_sender) => continue_sender.send(send_body).is_ok(),
+            None => false,
+        }
+    }
+
+    pub fn pop(&self) -> Option<PoppedRequest<TStream>> {
+        self.queue.lock().pop_front().map(|itm| PoppedRequest {
+            task: itm.task,
+            in_flight_permit: itm.in_flight_permit,
+        })
     }
 
     /// Returns the method of the request at the front of the queue (the one
@@ -83,10 +188,18 @@ impl<TStream: tokio::io::AsyncRead + Send + Sync + 'static> QueueOfRequests<TStr
         self.queue.lock().front().map(|itm| itm.method.clone())
     }
 
+    /// Fails every queued request: with [`MyHttpClientError::RequestNotSent`] if
+    /// none of it was written, so it can be repeated on a new connection.
     pub fn notify_connection_lost(&self) {
         let mut queue = self.queue.lock();
         while let Some(mut itm) = queue.pop_front() {
-            let _ = itm.task.try_set_error(MyHttpClientError::Disconnected);
+            let err = if itm.written.load(Ordering::Relaxed) {
+                MyHttpClientError::Disconnected
+            } else {
+                MyHttpClientError::RequestNotSent
+            };
+
+            let _ = itm.task.try_set_error(err);
         }
     }
 }
@@ -1,8 +1,9 @@
 use std::{sync::Arc, time::Duration};
 
-use super::{HttpParseError, TcpBuffer};
+use super::{HttpParseError, MyHttpClientLimits, TcpBuffer};
 
-use super::{BodyReader, HttpTask, MyHttpClientInner};
+use super::{BodyReader, HttpTask, MyHttpClientInner, UntilCloseBody};
+use http_body_util::BodyExt;
 use tokio::io::ReadHalf;
 
 pub async fn read_loop<
@@ -12,12 +13,19 @@ pub async fn read_loop<
     connection_id: u64,
     inner: Arc<MyHttpClientInner<TStream>>,
     read_timeout: Duration,
+    stream_body_threshold: Option<usize>,
+    limits: MyHttpClientLimits,
 ) -> Result<(), HttpParseError> {
     let mut do_read_to_buffer = true;
 
     // Consecutive interim (1xx) responses seen before the current final response.
     let mut interim_count: usize = 0;
 
+    // The body of an `Expect: 100-continue` request was skipped after an early
+    // final response; the server may still wait for it, so the connection ends
+    // once that response is read.
+    let mut close_after_response = false;
+
     let mut tcp_buffer = TcpBuffer::new();
 
     let print_input_http_stream = if let Ok(value) = std::env::var("DEBUG_HTTP_INPUT_STREAM") {
@@ -27,6 +35,10 @@ pub async fn read_loop<
         false
     };
     while inner.is_my_connection_id(connection_id) {
+        if close_after_response {
+            return Ok(());
+        }
+
         if do_read_to_buffer || tcp_buffer.is_empty() {
             super::read_with_timeout::read_to_buffer(
                 &mut read_stream,
@@ -49,99 +61,224 @@ pub async fn read_loop<
             read_timeout,
             print_input_http_stream,
             request_method,
+            &limits,
         )
         .await
         {
-            Ok(body_reader) => match body_reader {
-                BodyReader::Interim => {
-                    // A non-final 1xx response (e.g. 100 Continue / 103 Early
-                    // Hints). Discard it WITHOUT popping the request and keep
-                    // reading for the real final response, which still belongs
-                    // to the same front-of-queue request. Bound the count so a
-                    // server cannot pin the read loop with endless 1xx messages.
-                    interim_count += 1;
-                    if interim_count > super::MAX_INTERIM_RESPONSES {
-                        return Err(HttpParseError::invalid_payload(format!(
-                            "Received more than {} consecutive interim (1xx) responses",
-                            super::MAX_INTERIM_RESPONSES
-                        )));
-                    }
-                    continue;
+            Ok(body_reader) => {
+                if !matches!(body_reader, BodyReader::Interim(_))
+                    && inner.notify_continue(connection_id, false)
+                {
+                    close_after_response = true;
                 }
-                BodyReader::LengthBased { builder, body_size } => {
+
+                if !matches!(body_reader, BodyReader::Interim(_))
+                    && inner.front_request_is_abandoned(connection_id)
+                {
                     interim_count = 0;
-                    let response = super::body_reader::read_full_body(
+                    // Still in flight until its response is discarded
+                    let _abandoned_request = inner.pop_request(connection_id, false);
+
+                    let keep_connection = discard_abandoned_response(
                         &mut read_stream,
                         &mut tcp_buffer,
-                        builder,
-                        body_size,
+                        body_reader,
                         read_timeout,
+                        print_input_http_stream,
+                        &limits,
                     )
                     .await?;
 
-                    let request = inner.pop_request(connection_id, false);
-                    if let Some(mut request) = request {
-                        let result = request.try_set_ok(HttpTask::Response(response));
+                    if !keep_connection {
+                        return Ok(());
+                    }
+
+                    continue;
+                }
 
-                        if result.is_err() {
-                            return Ok(());
+                match body_reader {
+                    BodyReader::Interim(response) => {
+                        // A non-final 1xx response (e.g. 100 Continue / 103 Early
+                        // Hints). Pass it to the interim handler of the request
+                        // (if any) WITHOUT popping the request and keep reading
+                        // for the real final response, which still belongs to
+                        // the same front-of-queue request. Bound the count so a
+                        // server cannot pin the read loop with endless 1xx messages.
+                        inner.notify_interim(connection_id, &response);
+
+                        if response.status() == http::StatusCode::CONTINUE {
+                            inner.notify_continue(connection_id, true);
                         }
+
+                        interim_count += 1;
+                        if interim_count > limits.max_interim_responses {
+                            return Err(HttpParseError::invalid_payload(format!(
+                                "Received more than {} consecutive interim (1xx) responses",
+                                limits.max_interim_responses
+                            )));
+                        }
+                        continue;
                     }
-                }
-                BodyReader::UntilClose { builder } => {
-                    // Close-delimited body: read to EOF, hand back the response,
-                    // then stop. The stream is consumed by the close, so this
-                    // connection must not be reused for keep-alive. Returning
-                    // Ok(()) lets `read_loop_stopped` transition it to
-                    // Disconnected so the next send reconnects.
-                    let response = super::body_reader::read_until_close(
-                        &mut read_stream,
-                        &mut tcp_buffer,
-                        builder,
-                        read_timeout,
-                    )
-                    .await?;
+                    BodyReader::LengthBased { builder, body_size } => {
+                        interim_count = 0;
+
+                        if stream_body_threshold.is_some_and(|threshold| body_size > threshold) {
+                            let (sender, response) =
+                                super::body_reader::create_chunked_body_response(builder);
+
+                            // Still in flight until its body is streamed
+                            let mut request = inner.pop_request(connection_id, false);
+                            if let Some(request) = request.as_mut() {
+                                let result = request.task.try_set_ok(HttpTask::Response(response));
+
+                                if result.is_err() {
+                                    return Ok(());
+                                }
+                            }
+
+                            super::body_reader::stream_full_body(
+                                &mut read_stream,
+                                &mut tcp_buffer,
+                                sender,
+                                body_size,
+                                read_timeout,
+                            )
+                            .await?;
+
+                            drop(request);
+                            continue;
+                        }
 
-                    let request = inner.pop_request(connection_id, false);
-                    if let Some(mut request) = request {
-                        let _ = request.try_set_ok(HttpTask::Response(response));
+                        let response = super::body_reader::read_full_body(
+                            &mut read_stream,
+                            &mut tcp_buffer,
+                            builder,
+                            body_size,
+                            read_timeout,
+                            &limits,
+                        )
+                        .await?;
+
+                        let request = inner.pop_request(connection_id, false);
+                        if let Some(mut request) = request {
+                            let result = request.task.try_set_ok(HttpTask::Response(response));
+
+                            if result.is_err() {
+                                return Ok(());
+                            }
+                        }
                     }
+                    BodyReader::UntilClose { builder } => {
+                        // Close-delimited body: read to EOF, hand back the response,
+                        // then stop. The stream is consumed by the close, so this
+                        // connection must not be reused for keep-alive. Returning
+                        // Ok(()) lets `read_loop_stopped` transition it to
+                        // Disconnected so the next send reconnects.
+                        let response = match stream_body_threshold {
+                            Some(threshold) => {
+                                match super::body_reader::read_until_close_or_threshold(
+                                    &mut read_stream,
+                                    &mut tcp_buffer,
+                                    builder,
+                                    threshold,
+                                    read_timeout,
+                                )
+                                .await?
+                                {
+                                    UntilCloseBody::Complete(response) => response,
+                                    UntilCloseBody::ExceedsThreshold { builder, head } => {
+                                        let (mut sender, response) =
+                                            super::body_reader::create_chunked_body_response(
+                                                builder,
+                                            );
 
-                    return Ok(());
-                }
-                BodyReader::Chunked { response, sender } => {
-                    interim_count = 0;
-                    let request = inner.pop_request(connection_id, false);
-                    if let Some(mut request) = request {
-                        let result = request.try_set_ok(HttpTask::Response(response));
+                                        // Still in flight until its body is streamed
+                                        let mut request = inner.pop_request(connection_id, false);
+                                        if let Some(request) = request.as_mut() {
+                                            let result = request
+                                                .task
+                                                .try_set_ok(HttpTask::Response(response));
+
+                                            if result.is_err() {
+                                                return Ok(());
+                                            }
+                                        }
+
+                                        super::body_reader::send_body_chunk(
+                                            &mut sender,
+                                            head.into(),
+                                        )
+                                        .await?;
+
+                                        super::body_reader::stream_until_close(
+                                            &mut read_stream,
+                                            sender,
+                                            read_timeout,
+                                        )
+                                        .await?;
 
-                        if result.is_err() {
-                            return Ok(());
+                                        drop(request);
+                                        return Ok(());
+                                    }
+                                }
+                            }
+                            None => {
+                                super::body_reader::read_until_close(
+                                    &mut read_stream,
+                                    &mut tcp_buffer,
+                                    builder,
+                                    read_timeout,
+                                    &limits,
+                                )
+                                .await?
+                            }
+                        };
+
+                        let request = inner.pop_request(connection_id, false);
+                        if let Some(mut request) = request {
+                            let _ = request.task.try_set_ok(HttpTask::Response(response));
                         }
+
+                        return Ok(());
                     }
+                    BodyReader::Chunked { response, sender } => {
+                        interim_count = 0;
+                        // Still in flight until its body is streamed
+                        let mut request = inner.pop_request(connection_id, false);
+                        if let Some(request) = request.as_mut() {
+                            let result = request.task.try_set_ok(HttpTask::Response(response));
 
-                    super::body_reader::read_chunked_body(
-                        &mut read_stream,
-                        &mut tcp_buffer,
-                        sender,
-                        read_timeout,
-                        print_input_http_stream,
-                    )
-                    .await?;
-                }
-                BodyReader::WebSocketUpgrade(mut builder) => {
-                    let upgrade_response = builder.take_upgrade_response();
-                    let request = inner.pop_request(connection_id, true);
-                    if let Some(mut request) = request {
-                        let _ = request.try_set_ok(HttpTask::WebsocketUpgrade {
-                            response: upgrade_response,
-                            read_part: read_stream,
-                        });
+                            if result.is_err() {
+                                return Ok(());
+                            }
+                        }
+
+                        super::body_reader::read_chunked_body(
+                            &mut read_stream,
+                            &mut tcp_buffer,
+                            sender,
+                            read_timeout,
+                            print_input_http_stream,
+                            &limits,
+                        )
+                        .await?;
+
+                        drop(request);
                     }
+                    BodyReader::WebSocketUpgrade(mut builder) => {
+                        let upgrade_response = builder.take_upgrade_response();
+                        let request = inner.pop_request(connection_id, true);
+                        if let Some(mut request) = request {
+                            let _ = request.task.try_set_ok(HttpTask::WebsocketUpgrade {
+                                response: upgrade_response,
+                                read_part: read_stream,
+                            });
+                        }
 
-                    return Ok(());
+                        return Ok(());
+                    }
                 }
-            },
+            }
             Err(err) => match err {
                 super::HttpParseError::GetMoreData => {
                     do_read_to_buffer = true;
@@ -155,3 +292,72 @@ pub async fn read_loop<
 
     Ok(())
 }
+
+/// Reads the response of a request whose caller stopped waiting off the wire,
+/// so the responses pipelined after it can still be read. Returns `false` if the
+/// connection has to be dropped instead: the body is above
+/// `max_abandoned_body_size`, delimited by the close, or an upgrade.
+async fn discard_abandoned_response<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+>(
+    read_stream: &mut ReadHalf<TStream>,
+    tcp_buffer: &mut TcpBuffer,
+    body_reader: BodyReader,
+    read_timeout: Duration,
+    print_input_http_stream: bool,
+    limits: &MyHttpClientLimits,
+) -> Result<bool, HttpParseError> {
+    match body_reader {
+        BodyReader::LengthBased { builder, body_size } => {
+            if body_size > limits.max_abandoned_body_size {
+                return Ok(false);
+            }
+
+            super::body_reader::read_full_body(
+                read_stream,
+                tcp_buffer,
+                builder,
+                body_size,
+                read_timeout,
+                limits,
+            )
+            .await?;
+
+            Ok(true)
+        }
+        BodyReader::Chunked { response, sender } => {
+            let max_body_size = limits.max_abandoned_body_size;
+
+            // Dropping the body past the limit fails the reader, which drops the connection
+            let discard = async move {
+                let mut body = response.into_body();
+                let mut body_size = 0;
+                while let Some(Ok(frame)) = body.frame().await {
+                    if let Some(data) = frame.data_ref() {
+                        body_size += data.len();
+                        if body_size > max_body_size {
+                            return;
+                        }
+                    }
+                }
+            };
+
+            let (result, _) = futures::join!(
+                super::body_reader::read_chunked_body(
+                    read_stream,
+                    tcp_buffer,
+                    sender,
+                    read_timeout,
+                    print_input_http_stream,
+                    limits,
+                ),
+                discard
+            );
+
+            result?;
+
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
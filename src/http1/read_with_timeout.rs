@@ -132,3 +132,44 @@ pub async fn read_until_crlf<TResult, TStream: tokio::io::AsyncRead>(
         }
     }
 }
+
+/// Same as [`read_until_crlf`], but fails once the line is longer than
+/// `max_line_size` instead of reading until the buffer is exhausted.
+pub async fn read_line_with_limit<TResult, TStream: tokio::io::AsyncRead>(
+    read_stream: &mut ReadHalf<TStream>,
+    tcp_buffer: &mut TcpBuffer,
+    read_timeout: Duration,
+    max_line_size: usize,
+    conversion: impl FnOnce(&[u8]) -> Result<TResult, HttpParseError>,
+    print_input_http_stream: bool,
+) -> Result<TResult, HttpParseError> {
+    loop {
+        match tcp_buffer.read_until_crlf() {
+            Some(line) => {
+                check_line_size(line.len(), max_line_size)?;
+                return conversion(line);
+            }
+            None => {
+                check_line_size(tcp_buffer.get_buf().len(), max_line_size)?;
+                read_to_buffer(
+                    read_stream,
+                    tcp_buffer,
+                    read_timeout,
+                    print_input_http_stream,
+                )
+                .await?;
+            }
+        }
+    }
+}
+
+pub fn check_line_size(line_size: usize, max_line_size: usize) -> Result<(), HttpParseError> {
+    if line_size > max_line_size {
+        return Err(HttpParseError::invalid_payload(format!(
+            "Response line exceeds limit {}",
+            max_line_size
+        )));
+    }
+
+    Ok(())
+}
@@ -12,7 +12,9 @@ use http_body_util::BodyExt;
 use tokio::io::{AsyncWriteExt, DuplexStream, ReadHalf};
 
 use super::{
-    read_chunked_body, read_full_body, read_headers, read_until_close, BodyReader, TcpBuffer,
+    create_chunked_body_response, read_chunked_body, read_full_body, read_headers,
+    read_until_close, read_until_close_or_threshold, stream_full_body, stream_until_close,
+    BodyReader, MyHttpClientLimits, TcpBuffer, UntilCloseBody,
 };
 
 const TIMEOUT: Duration = Duration::from_secs(5);
@@ -66,9 +68,16 @@ async fn head_with_content_length_returns_empty_without_hang() {
     let (mut read_half, _held, mut buf) =
         setup(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n", false).await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::HEAD))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::HEAD),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let (builder, body_size) = match body_reader {
         BodyReader::LengthBased { builder, body_size } => (builder, body_size),
@@ -76,9 +85,16 @@ async fn head_with_content_length_returns_empty_without_hang() {
     };
     assert_eq!(body_size, 0);
 
-    let response = read_full_body(&mut read_half, &mut buf, builder, body_size, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_full_body(
+        &mut read_half,
+        &mut buf,
+        builder,
+        body_size,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 200);
     assert!(collect_body(response).await.is_empty());
 }
@@ -89,9 +105,16 @@ async fn head_with_content_length_returns_empty_without_hang() {
 async fn head_without_length_is_empty_not_until_close() {
     let (mut read_half, _held, mut buf) = setup(b"HTTP/1.1 200 OK\r\n\r\n", false).await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::HEAD))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::HEAD),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     match body_reader {
         BodyReader::LengthBased { body_size, .. } => assert_eq!(body_size, 0),
@@ -111,9 +134,16 @@ async fn head_with_chunked_encoding_returns_empty() {
     )
     .await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::HEAD))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::HEAD),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     match body_reader {
         BodyReader::LengthBased { body_size, .. } => assert_eq!(body_size, 0),
@@ -131,20 +161,36 @@ async fn close_delimited_body_is_returned_in_full() {
     )
     .await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let builder = match body_reader {
         BodyReader::UntilClose { builder } => builder,
         other => panic!("Expected UntilClose, got {:?}", other),
     };
 
-    let response = read_until_close(&mut read_half, &mut buf, builder, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_until_close(
+        &mut read_half,
+        &mut buf,
+        builder,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 200);
-    assert_eq!(collect_body(response).await, b"Hello, close-delimited world!");
+    assert_eq!(
+        collect_body(response).await,
+        b"Hello, close-delimited world!"
+    );
 }
 
 /// Bug B, exercising the actual socket read-until-EOF loop: the header block is
@@ -168,9 +214,16 @@ async fn close_delimited_body_read_from_socket_across_multiple_writes() {
         .await
         .unwrap();
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let builder = match body_reader {
         BodyReader::UntilClose { builder } => builder,
@@ -187,13 +240,22 @@ async fn close_delimited_body_read_from_socket_across_multiple_writes() {
         drop(server);
     });
 
-    let response = read_until_close(&mut read_half, &mut buf, builder, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_until_close(
+        &mut read_half,
+        &mut buf,
+        builder,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     writer.await.unwrap();
 
     assert_eq!(response.status(), 200);
-    assert_eq!(collect_body(response).await, b"part-one;part-two;part-three");
+    assert_eq!(
+        collect_body(response).await,
+        b"part-one;part-two;part-three"
+    );
 }
 
 /// An empty close-delimited body (headers, then immediate close) yields a
@@ -202,18 +264,31 @@ async fn close_delimited_body_read_from_socket_across_multiple_writes() {
 async fn close_delimited_empty_body() {
     let (mut read_half, _held, mut buf) = setup(b"HTTP/1.1 200 OK\r\n\r\n", true).await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let builder = match body_reader {
         BodyReader::UntilClose { builder } => builder,
         other => panic!("Expected UntilClose, got {:?}", other),
     };
 
-    let response = read_until_close(&mut read_half, &mut buf, builder, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_until_close(
+        &mut read_half,
+        &mut buf,
+        builder,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 200);
     assert!(collect_body(response).await.is_empty());
 }
@@ -222,12 +297,22 @@ async fn close_delimited_empty_body() {
 /// Content-Length must still return empty without hanging.
 #[tokio::test]
 async fn no_content_204_with_content_length_returns_empty() {
-    let (mut read_half, _held, mut buf) =
-        setup(b"HTTP/1.1 204 No Content\r\nContent-Length: 42\r\n\r\n", false).await;
+    let (mut read_half, _held, mut buf) = setup(
+        b"HTTP/1.1 204 No Content\r\nContent-Length: 42\r\n\r\n",
+        false,
+    )
+    .await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let (builder, body_size) = match body_reader {
         BodyReader::LengthBased { builder, body_size } => (builder, body_size),
@@ -235,9 +320,16 @@ async fn no_content_204_with_content_length_returns_empty() {
     };
     assert_eq!(body_size, 0);
 
-    let response = read_full_body(&mut read_half, &mut buf, builder, body_size, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_full_body(
+        &mut read_half,
+        &mut buf,
+        builder,
+        body_size,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 204);
     assert!(collect_body(response).await.is_empty());
 }
@@ -252,9 +344,16 @@ async fn not_modified_304_with_content_length_returns_empty() {
     )
     .await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let (builder, body_size) = match body_reader {
         BodyReader::LengthBased { builder, body_size } => (builder, body_size),
@@ -262,9 +361,16 @@ async fn not_modified_304_with_content_length_returns_empty() {
     };
     assert_eq!(body_size, 0);
 
-    let response = read_full_body(&mut read_half, &mut buf, builder, body_size, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_full_body(
+        &mut read_half,
+        &mut buf,
+        builder,
+        body_size,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 304);
     assert!(collect_body(response).await.is_empty());
 }
@@ -281,6 +387,7 @@ async fn connect_2xx_returns_empty() {
         TIMEOUT,
         false,
         Some(Method::CONNECT),
+        &MyHttpClientLimits::default(),
     )
     .await
     .unwrap();
@@ -297,9 +404,16 @@ async fn length_based_body_still_works() {
     let (mut read_half, _held, mut buf) =
         setup(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHello", false).await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let (builder, body_size) = match body_reader {
         BodyReader::LengthBased { builder, body_size } => (builder, body_size),
@@ -307,9 +421,16 @@ async fn length_based_body_still_works() {
     };
     assert_eq!(body_size, 5);
 
-    let response = read_full_body(&mut read_half, &mut buf, builder, body_size, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_full_body(
+        &mut read_half,
+        &mut buf,
+        builder,
+        body_size,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 200);
     assert_eq!(collect_body(response).await, b"Hello");
 }
@@ -326,9 +447,16 @@ async fn chunked_body_still_works() {
     let mut read_half = read_half;
     let mut buf = buf;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     let (response, sender) = match body_reader {
         BodyReader::Chunked { response, sender } => (response, sender),
@@ -336,9 +464,16 @@ async fn chunked_body_still_works() {
     };
 
     let reader = tokio::spawn(async move {
-        read_chunked_body(&mut read_half, &mut buf, sender, TIMEOUT, false)
-            .await
-            .unwrap();
+        read_chunked_body(
+            &mut read_half,
+            &mut buf,
+            sender,
+            TIMEOUT,
+            false,
+            &MyHttpClientLimits::default(),
+        )
+        .await
+        .unwrap();
     });
 
     assert_eq!(response.status(), 200);
@@ -348,6 +483,212 @@ async fn chunked_body_still_works() {
     assert_eq!(body, b"Hello World");
 }
 
+/// Trailer fields after the last chunk are delivered as a trailers frame instead
+/// of breaking the parsing of the end of the body.
+#[tokio::test]
+async fn chunked_body_trailers_are_delivered() {
+    let (mut read_half, _held, mut buf) = setup(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\ngrpc-status: 0\r\nServer-Timing: db;dur=53\r\n\r\n",
+        false,
+    )
+    .await;
+
+    let limits = MyHttpClientLimits::default();
+
+    let (response, sender) = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &limits,
+    )
+    .await
+    .unwrap()
+    {
+        BodyReader::Chunked { response, sender } => (response, sender),
+        other => panic!("Expected Chunked, got {:?}", other),
+    };
+
+    let reader = tokio::spawn(async move {
+        read_chunked_body(&mut read_half, &mut buf, sender, TIMEOUT, false, &limits)
+            .await
+            .unwrap();
+        buf
+    });
+
+    let collected = response.into_body().collect().await.unwrap();
+    let buf = reader.await.unwrap();
+
+    let trailers = collected.trailers().unwrap().clone();
+    assert_eq!(collected.to_bytes().as_ref(), b"Hello");
+    assert_eq!(trailers.get("grpc-status").unwrap(), "0");
+    assert_eq!(trailers.get("server-timing").unwrap(), "db;dur=53");
+    assert!(buf.get_buf().is_empty());
+}
+
+#[tokio::test]
+async fn chunked_body_trailers_over_limit_are_rejected() {
+    let limits = MyHttpClientLimits {
+        max_trailers_count: 1,
+        ..Default::default()
+    };
+
+    let (mut read_half, _held, mut buf) = setup(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n0\r\na: 1\r\nb: 2\r\n\r\n",
+        false,
+    )
+    .await;
+
+    let sender = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &limits,
+    )
+    .await
+    .unwrap()
+    {
+        BodyReader::Chunked { sender, .. } => sender,
+        other => panic!("Expected Chunked, got {:?}", other),
+    };
+
+    let err = read_chunked_body(&mut read_half, &mut buf, sender, TIMEOUT, false, &limits)
+        .await
+        .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
+}
+
+/// A length-based body streamed above the threshold is delivered piece by piece:
+/// the first part can be read by the caller while the rest is still in flight.
+#[tokio::test]
+async fn length_based_body_is_streamed_before_it_is_complete() {
+    let (mut read_half, held, mut buf) =
+        setup(b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nHello", false).await;
+    let mut server = held.unwrap();
+
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
+
+    let (builder, body_size) = match body_reader {
+        BodyReader::LengthBased { builder, body_size } => (builder, body_size),
+        other => panic!("Expected LengthBased, got {:?}", other),
+    };
+
+    let (sender, response) = create_chunked_body_response(builder);
+
+    let reader = tokio::spawn(async move {
+        stream_full_body(&mut read_half, &mut buf, sender, body_size, TIMEOUT)
+            .await
+            .unwrap();
+    });
+
+    let mut body = response.into_body();
+    let first = body.frame().await.unwrap().unwrap().into_data().unwrap();
+    assert_eq!(first.as_ref(), b"Hello");
+
+    server.write_all(b"World").await.unwrap();
+
+    let rest = body.collect().await.unwrap().to_bytes();
+    reader.await.unwrap();
+
+    assert_eq!(rest.as_ref(), b"World");
+}
+
+/// A close-delimited body below the threshold is still returned in full; above
+/// it, the part read so far is handed back and the rest is streamed until EOF.
+#[tokio::test]
+async fn close_delimited_body_over_threshold_is_streamed() {
+    let (mut read_half, _held, mut buf) = setup(b"HTTP/1.1 200 OK\r\n\r\nsmall", true).await;
+
+    let builder = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap()
+    {
+        BodyReader::UntilClose { builder } => builder,
+        other => panic!("Expected UntilClose, got {:?}", other),
+    };
+
+    match read_until_close_or_threshold(&mut read_half, &mut buf, builder, 16, TIMEOUT)
+        .await
+        .unwrap()
+    {
+        UntilCloseBody::Complete(response) => assert_eq!(collect_body(response).await, b"small"),
+        UntilCloseBody::ExceedsThreshold { .. } => panic!("Expected Complete"),
+    }
+
+    let (client, mut server) = tokio::io::duplex(1024);
+    let (mut read_half, _write_half) = tokio::io::split(client);
+    server
+        .write_all(b"HTTP/1.1 200 OK\r\n\r\n0123456789")
+        .await
+        .unwrap();
+
+    let mut buf = TcpBuffer::new();
+    super::read_to_buffer(&mut read_half, &mut buf, TIMEOUT, false)
+        .await
+        .unwrap();
+
+    let builder = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap()
+    {
+        BodyReader::UntilClose { builder } => builder,
+        other => panic!("Expected UntilClose, got {:?}", other),
+    };
+
+    let (builder, head) =
+        match read_until_close_or_threshold(&mut read_half, &mut buf, builder, 4, TIMEOUT)
+            .await
+            .unwrap()
+        {
+            UntilCloseBody::ExceedsThreshold { builder, head } => (builder, head),
+            UntilCloseBody::Complete(_) => panic!("Expected ExceedsThreshold"),
+        };
+    assert_eq!(head, b"0123456789");
+
+    let (sender, response) = create_chunked_body_response(builder);
+
+    let writer = tokio::spawn(async move {
+        server.write_all(b"-tail").await.unwrap();
+        drop(server);
+    });
+
+    let reader = tokio::spawn(async move {
+        stream_until_close(&mut read_half, sender, TIMEOUT)
+            .await
+            .unwrap();
+    });
+
+    assert_eq!(collect_body(response).await, b"-tail");
+    writer.await.unwrap();
+    reader.await.unwrap();
+}
+
 /// A non-websocket interim 1xx response (100 Continue) must be signalled as
 /// `Interim` (to be skipped), and the following real response parsed from the
 /// same stream — not delivered as the final response.
@@ -359,28 +700,49 @@ async fn interim_100_continue_is_skipped_then_final_response_read() {
     )
     .await;
 
-    let first = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let first = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert!(
-        matches!(first, BodyReader::Interim),
+        matches!(&first, BodyReader::Interim(response) if response.status() == 100),
         "Expected Interim for 100 Continue, got {:?}",
         first
     );
 
     // The real 200 is parsed next from the same stream.
-    let second = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let second = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     let (builder, body_size) = match second {
         BodyReader::LengthBased { builder, body_size } => (builder, body_size),
         other => panic!("Expected LengthBased, got {:?}", other),
     };
     assert_eq!(body_size, 2);
 
-    let response = read_full_body(&mut read_half, &mut buf, builder, body_size, TIMEOUT)
-        .await
-        .unwrap();
+    let response = read_full_body(
+        &mut read_half,
+        &mut buf,
+        builder,
+        body_size,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(response.status(), 200);
     assert_eq!(collect_body(response).await, b"hi");
 }
@@ -394,12 +756,19 @@ async fn early_hints_103_is_interim() {
     )
     .await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     assert!(
-        matches!(body_reader, BodyReader::Interim),
+        matches!(&body_reader, BodyReader::Interim(response) if response.headers().contains_key("link")),
         "Expected Interim for 103 Early Hints, got {:?}",
         body_reader
     );
@@ -416,9 +785,16 @@ async fn websocket_upgrade_still_detected() {
     )
     .await;
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, Some(Method::GET))
-        .await
-        .unwrap();
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
 
     assert!(
         matches!(body_reader, BodyReader::WebSocketUpgrade(_)),
@@ -432,22 +808,168 @@ async fn websocket_upgrade_still_detected() {
 /// responses.
 #[tokio::test]
 async fn unknown_method_defaults_to_until_close() {
+    let (mut read_half, _held, mut buf) = setup(b"HTTP/1.1 200 OK\r\n\r\npayload", true).await;
+
+    let body_reader = read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        None,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
+
+    let builder = match body_reader {
+        BodyReader::UntilClose { builder } => builder,
+        other => panic!("Expected UntilClose, got {:?}", other),
+    };
+
+    let response = read_until_close(
+        &mut read_half,
+        &mut buf,
+        builder,
+        TIMEOUT,
+        &MyHttpClientLimits::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(collect_body(response).await, b"payload");
+}
+
+/// A header line longer than `max_header_line_size` is rejected, both when it
+/// arrived complete and while it is still waiting for its CRLF.
+#[tokio::test]
+async fn header_line_over_limit_is_rejected() {
+    let limits = MyHttpClientLimits {
+        max_header_line_size: 16,
+        ..Default::default()
+    };
+
+    let (mut read_half, _held, mut buf) = setup(
+        b"HTTP/1.1 200 OK\r\nX-Long: 0123456789abcdef\r\n\r\n",
+        false,
+    )
+    .await;
+    let err = read_headers(&mut read_half, &mut buf, TIMEOUT, false, None, &limits)
+        .await
+        .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
+
     let (mut read_half, _held, mut buf) =
-        setup(b"HTTP/1.1 200 OK\r\n\r\npayload", true).await;
+        setup(b"HTTP/1.1 200 OK\r\nX-Long: 0123456789abcdef", false).await;
+    let err = read_headers(&mut read_half, &mut buf, TIMEOUT, false, None, &limits)
+        .await
+        .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
+}
+
+/// The whole header block, status line included, is bounded by
+/// `max_headers_total_size` even if every single line is small.
+#[tokio::test]
+async fn headers_over_total_limit_are_rejected() {
+    let limits = MyHttpClientLimits {
+        max_headers_total_size: 40,
+        ..Default::default()
+    };
+
+    let (mut read_half, _held, mut buf) = setup(
+        b"HTTP/1.1 200 OK\r\nX-A: 1\r\nX-B: 2\r\nX-C: 3\r\n\r\n",
+        false,
+    )
+    .await;
+    let err = read_headers(&mut read_half, &mut buf, TIMEOUT, false, None, &limits)
+        .await
+        .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
 
-    let body_reader = read_headers(&mut read_half, &mut buf, TIMEOUT, false, None)
+    let (mut read_half, _held, mut buf) = setup(b"HTTP/1.1 200 OK\r\nX-A: 1\r\n\r\n", true).await;
+    read_headers(&mut read_half, &mut buf, TIMEOUT, false, None, &limits)
         .await
         .unwrap();
+}
 
-    let builder = match body_reader {
+/// Body size limits come from the client limits rather than the crate consts.
+#[tokio::test]
+async fn body_limits_are_taken_from_client_limits() {
+    let limits = MyHttpClientLimits {
+        max_response_body_size: 4,
+        max_chunk_size: 4,
+        ..Default::default()
+    };
+
+    let (mut read_half, _held, mut buf) =
+        setup(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nHello", false).await;
+    let (builder, body_size) = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &limits,
+    )
+    .await
+    .unwrap()
+    {
+        BodyReader::LengthBased { builder, body_size } => (builder, body_size),
+        other => panic!("Expected LengthBased, got {:?}", other),
+    };
+    let err = read_full_body(
+        &mut read_half,
+        &mut buf,
+        builder,
+        body_size,
+        TIMEOUT,
+        &limits,
+    )
+    .await
+    .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
+
+    let (mut read_half, _held, mut buf) = setup(b"HTTP/1.1 200 OK\r\n\r\nHello", true).await;
+    let builder = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &limits,
+    )
+    .await
+    .unwrap()
+    {
         BodyReader::UntilClose { builder } => builder,
         other => panic!("Expected UntilClose, got {:?}", other),
     };
+    let err = read_until_close(&mut read_half, &mut buf, builder, TIMEOUT, &limits)
+        .await
+        .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
 
-    let response = read_until_close(&mut read_half, &mut buf, builder, TIMEOUT)
+    let (mut read_half, _held, mut buf) = setup(
+        b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nHello\r\n0\r\n\r\n",
+        false,
+    )
+    .await;
+    let sender = match read_headers(
+        &mut read_half,
+        &mut buf,
+        TIMEOUT,
+        false,
+        Some(Method::GET),
+        &limits,
+    )
+    .await
+    .unwrap()
+    {
+        BodyReader::Chunked { sender, .. } => sender,
+        other => panic!("Expected Chunked, got {:?}", other),
+    };
+    let err = read_chunked_body(&mut read_half, &mut buf, sender, TIMEOUT, false, &limits)
         .await
-        .unwrap();
-    assert_eq!(collect_body(response).await, b"payload");
+        .unwrap_err();
+    assert!(err.as_invalid_payload().is_some(), "{:?}", err);
 }
 
 /// The method the framing logic relies on is derived by `MyHttpRequest::get_method`
@@ -468,7 +990,13 @@ fn get_method_parses_serialized_request_line() {
         Method::POST,
         Method::DELETE,
     ] {
-        let req = MyHttpRequest::new(method.clone(), "/path?x=1", Version::HTTP_11, &headers, vec![]);
+        let req = MyHttpRequest::new(
+            method.clone(),
+            "/path?x=1",
+            Version::HTTP_11,
+            &headers,
+            vec![],
+        );
         assert_eq!(req.get_method(), method, "MyHttpRequest::new path");
 
         let built = MyHttpRequestBuilder::new(method.clone(), "/path?x=1").build();
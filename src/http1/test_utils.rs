@@ -0,0 +1,58 @@
+//! Helpers for tests that drive a real client against a server on a local
+//! socket.
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::{
+    io::{AsyncReadExt, ReadHalf, WriteHalf},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+pub struct TestConnector {
+    pub host_port: String,
+}
+
+#[async_trait::async_trait]
+impl MyHttpClientConnector<TcpStream> for TestConnector {
+    async fn connect(&self) -> Result<TcpStream, MyHttpClientError> {
+        TcpStream::connect(self.host_port.as_str())
+            .await
+            .map_err(|err| MyHttpClientError::CanNotConnectToRemoteHost(err.to_string()))
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        RemoteEndpoint::try_parse(self.host_port.as_str()).unwrap()
+    }
+
+    fn is_debug(&self) -> bool {
+        false
+    }
+
+    fn reunite(read: ReadHalf<TcpStream>, write: WriteHalf<TcpStream>) -> TcpStream {
+        read.unsplit(write)
+    }
+}
+
+pub async fn bind_local() -> (TcpListener, TestConnector) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let host_port = listener.local_addr().unwrap().to_string();
+    (listener, TestConnector { host_port })
+}
+
+/// Reads from `socket` into `received` until it ends with `terminator`.
+/// Returns `false` if the peer closed the socket first.
+pub async fn read_until_ends_with(
+    socket: &mut TcpStream,
+    received: &mut Vec<u8>,
+    terminator: &[u8],
+) -> bool {
+    let mut buf = vec![0u8; 4096];
+    while !received.ends_with(terminator) {
+        match socket.read(&mut buf).await {
+            Ok(0) | Err(_) => return false,
+            Ok(read) => received.extend_from_slice(&buf[..read]),
+        }
+    }
+    true
+}
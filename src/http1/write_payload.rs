@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::{MyHttpRequestBodyStreamBoxed, MyHttpRequestTrailers};
+
+const WRITE_CHUNK_SIZE: usize = 1024 * 1024;
+
+pub async fn write_bytes<TWrite: AsyncWrite + Unpin>(
+    write_stream: &mut TWrite,
+    payload: &[u8],
+    send_to_socket_timeout: Duration,
+) -> Result<(), std::io::Error> {
+    for chunk in payload.chunks(WRITE_CHUNK_SIZE) {
+        let future = write_stream.write_all(chunk);
+
+        match tokio::time::timeout(send_to_socket_timeout, future).await {
+            Ok(result) => result?,
+            Err(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!("Send to socket timeout {:?}", send_to_socket_timeout),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a streamed request body. Without a `content_length` every piece is
+/// framed as an HTTP/1.1 chunk and the body is closed with the last chunk,
+/// followed by `trailers` if there are any.
+/// `send_to_socket_timeout` also bounds the wait for the next piece, so a stalled
+/// producer can not hold the connection forever.
+pub async fn write_body_stream<TWrite: AsyncWrite + Unpin>(
+    write_stream: &mut TWrite,
+    mut body: MyHttpRequestBodyStreamBoxed,
+    content_length: Option<u64>,
+    trailers: Option<MyHttpRequestTrailers>,
+    send_to_socket_timeout: Duration,
+) -> Result<(), std::io::Error> {
+    let mut sent: u64 = 0;
+
+    loop {
+        let next = match tokio::time::timeout(send_to_socket_timeout, body.next()).await {
+            Ok(next) => next,
+            Err(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!(
+                        "Request body stream produced nothing for {:?}",
+                        send_to_socket_timeout
+                    ),
+                ));
+            }
+        };
+
+        let chunk: Bytes = match next {
+            Some(chunk) => chunk?,
+            None => break,
+        };
+
+        // An empty chunk would be read by the server as the end of a chunked body
+        if chunk.is_empty() {
+            continue;
+        }
+
+        sent += chunk.len() as u64;
+
+        match content_length {
+            Some(content_length) => {
+                if sent > content_length {
+                    return Err(body_size_mismatch(content_length, sent));
+                }
+
+                write_bytes(write_stream, &chunk, send_to_socket_timeout).await?;
+            }
+            None => {
+                let size_line = format!("{:x}\r\n", chunk.len());
+                write_bytes(write_stream, size_line.as_bytes(), send_to_socket_timeout).await?;
+                write_bytes(write_stream, &chunk, send_to_socket_timeout).await?;
+                write_bytes(write_stream, crate::CL_CR, send_to_socket_timeout).await?;
+            }
+        }
+    }
+
+    match content_length {
+        Some(content_length) => {
+            if sent != content_length {
+                return Err(body_size_mismatch(content_length, sent));
+            }
+        }
+        None => {
+            let mut last_chunk = b"0\r\n".to_vec();
+            if let Some(trailers) = trailers {
+                trailers.write_to(&mut last_chunk);
+            }
+            last_chunk.extend_from_slice(crate::CL_CR);
+
+            write_bytes(write_stream, &last_chunk, send_to_socket_timeout).await?;
+        }
+    }
+
+    write_stream.flush().await
+}
+
+fn body_size_mismatch(content_length: u64, sent: u64) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!(
+            "Request body stream size does not match Content-Length {}. Produced: {}",
+            content_length, sent
+        ),
+    )
+}
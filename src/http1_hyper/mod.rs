@@ -1,5 +1,7 @@
 mod my_http_hyper_client;
 pub use my_http_hyper_client::*;
+mod my_http_hyper_client_redirect;
+mod my_http_hyper_client_retry;
 mod my_http_hyper_client_inner;
 pub use my_http_hyper_client_inner::*;
 mod hyper_http_result;
@@ -9,7 +9,12 @@ use http::StatusCode;
 use http_body_util::{combinators::BoxBody, Full};
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
-use crate::{MyHttpClientConnector, MyHttpClientDisconnect, MyHttpClientError};
+use crate::{
+    circuit_breaker::CircuitBreaker,
+    redirect::{RedirectConnectorFactory, RedirectPolicy},
+    retry::RetryPolicy,
+    MyHttpClientConnector, MyHttpClientDisconnect, MyHttpClientError,
+};
 
 use super::*;
 use crate::hyper::*;
@@ -18,14 +23,20 @@ pub struct MyHttpHyperClient<
     TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
     TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
 > {
-    connector: TConnector,
+    pub(super) connector: TConnector,
     stream: PhantomData<TStream>,
-    inner: Arc<MyHttpHyperClientInner>,
+    pub(crate) inner: Arc<MyHttpHyperClientInner>,
     connect_timeout: Duration,
     connection_id: AtomicU64,
     // tokio::sync::Mutex by design: held across the dial (TCP connect + http
     // handshake) to serialize concurrent dialers, so parking_lot does not fit
     connect_lock: tokio::sync::Mutex<()>,
+    decompress_responses: bool,
+    pub(super) redirect_policy: Option<RedirectPolicy>,
+    pub(super) redirect_connector_factory:
+        Option<Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>>,
+    pub(super) retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync + 'static>>,
+    pub(super) circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl<
@@ -49,6 +60,11 @@ impl<
             connect_timeout: Duration::from_secs(5),
             connection_id: AtomicU64::new(0),
             connect_lock: tokio::sync::Mutex::new(()),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
         }
     }
 
@@ -71,6 +87,11 @@ impl<
             connect_timeout: Duration::from_secs(5),
             connection_id: AtomicU64::new(0),
             connect_lock: tokio::sync::Mutex::new(()),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
         }
     }
 
@@ -78,6 +99,54 @@ impl<
         self.connect_timeout = connection_timeout;
     }
 
+    /// Sends `Accept-Encoding` with the codings enabled by cargo features (unless
+    /// the request has its own) and decodes responses with a supported
+    /// `Content-Encoding`, removing that header and `Content-Length`. Decoded
+    /// bodies over [`crate::compression::MAX_DECODED_BODY_SIZE`] end with an error.
+    pub fn set_decompress_responses(&mut self, decompress_responses: bool) {
+        self.decompress_responses = decompress_responses;
+    }
+
+    /// Makes `do_request` follow redirects. See [`RedirectPolicy`].
+    pub fn set_redirect_policy(&mut self, redirect_policy: RedirectPolicy) {
+        self.redirect_policy = Some(redirect_policy);
+    }
+
+    /// Connectors for cross-origin redirects. Without a factory such redirects
+    /// are returned to the caller even if the policy allows them.
+    pub fn set_redirect_connector_factory(
+        &mut self,
+        factory: Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>,
+    ) {
+        self.redirect_connector_factory = Some(factory);
+    }
+
+    /// Makes `do_request` repeat failed attempts. See [`RetryPolicy`].
+    pub fn set_retry_policy(&mut self, retry_policy: Arc<dyn RetryPolicy + Send + Sync + 'static>) {
+        self.retry_policy = Some(retry_policy);
+    }
+
+    /// Fails requests fast while the endpoint is down. One breaker can be shared
+    /// by many clients. See [`CircuitBreaker`].
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    /// A client for another endpoint with the same settings, used for a
+    /// cross-origin redirect hop.
+    pub(super) fn create_for_connector(&self, connector: TConnector) -> Self {
+        let mut result = match self.inner.metrics.clone() {
+            Some(metrics) => Self::new_with_metrics(connector, metrics),
+            None => Self::new(connector),
+        };
+
+        result.connect_timeout = self.connect_timeout;
+        result.decompress_responses = self.decompress_responses;
+        result.retry_policy = self.retry_policy.clone();
+        result.circuit_breaker = self.circuit_breaker.clone();
+        result
+    }
+
     async fn get_response(
         &self,
         req: hyper::Request<Full<Bytes>>,
@@ -94,6 +163,15 @@ impl<
             return Ok(result);
         }
 
+        let response = if self.decompress_responses {
+            crate::compression::decompress_response(
+                response,
+                crate::compression::MAX_DECODED_BODY_SIZE,
+            )
+        } else {
+            response
+        };
+
         Ok(HyperHttpResponse::Response(response))
     }
 
@@ -102,6 +180,24 @@ impl<
         req: hyper::Request<Full<Bytes>>,
         request_timeout: Duration,
     ) -> Result<HyperHttpResponse, MyHttpClientError> {
+        match self.redirect_policy {
+            Some(redirect_policy) => {
+                self.do_request_with_redirects(req, redirect_policy, request_timeout)
+                    .await
+            }
+            None => self.do_single_request(req, request_timeout).await,
+        }
+    }
+
+    pub(super) async fn send_single_request(
+        &self,
+        mut req: hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<HyperHttpResponse, MyHttpClientError> {
+        if self.decompress_responses {
+            crate::compression::add_accept_encoding(req.headers_mut());
+        }
+
         let request_is_idempotent = req.method().is_idempotent();
         let mut retry_no = 0;
         loop {
@@ -115,7 +211,7 @@ impl<
                     // The request never reached the wire, so reconnecting and retrying
                     // is safe for any method
                     if retry_no > 3 {
-                        return Err(MyHttpClientError::Disconnected);
+                        return Err(MyHttpClientError::RequestNotSent);
                     }
                     retry_no += 1;
                     self.connect().await?;
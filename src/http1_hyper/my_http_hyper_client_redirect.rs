@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::Full;
+
+use crate::{
+    redirect::{RedirectChain, RedirectPolicy},
+    MyHttpClientConnector, MyHttpClientError,
+};
+
+use super::{HyperHttpResponse, MyHttpHyperClient};
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > MyHttpHyperClient<TStream, TConnector>
+{
+    pub(super) async fn do_request_with_redirects(
+        &self,
+        mut req: hyper::Request<Full<Bytes>>,
+        redirect_policy: RedirectPolicy,
+        request_timeout: Duration,
+    ) -> Result<HyperHttpResponse, MyHttpClientError> {
+        let host_port = self
+            .connector
+            .get_remote_endpoint()
+            .get_host_port()
+            .to_string();
+        let mut chain = RedirectChain::new(
+            redirect_policy,
+            crate::redirect::get_hyper_request_origin(&req, host_port.as_str()),
+        );
+
+        // Client of the origin the chain moved to; `None` while on our own origin
+        let mut cross_origin_client: Option<Self> = None;
+
+        loop {
+            let response = match cross_origin_client.as_ref() {
+                Some(client) => {
+                    client
+                        .do_single_request(req.clone(), request_timeout)
+                        .await?
+                }
+                None => self.do_single_request(req.clone(), request_timeout).await?,
+            };
+
+            let response = match response {
+                HyperHttpResponse::Response(response) => response,
+                HyperHttpResponse::WebSocketUpgrade { .. } => return Ok(response),
+            };
+
+            let path_and_query = req
+                .uri()
+                .path_and_query()
+                .map(|itm| itm.as_str())
+                .unwrap_or("/");
+
+            let hop = chain.get_next_hop(
+                &response,
+                req.method(),
+                path_and_query,
+                true,
+                self.redirect_connector_factory.as_ref(),
+            )?;
+
+            let hop = match hop {
+                Some(hop) => hop,
+                None => return Ok(into_response(response, cross_origin_client)),
+            };
+
+            crate::utils::drain_body(response, request_timeout).await;
+
+            req = crate::redirect::create_redirect_hyper_request(
+                &req,
+                &hop.step,
+                hop.target_origin.as_ref(),
+            );
+
+            if let Some(connector) = hop.connector {
+                cross_origin_client = Some(self.create_for_connector(connector));
+            }
+
+            chain.follow(hop.target_origin);
+        }
+    }
+}
+
+fn into_response<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+>(
+    response: crate::HyperResponse,
+    cross_origin_client: Option<MyHttpHyperClient<TStream, TConnector>>,
+) -> HyperHttpResponse {
+    match cross_origin_client {
+        Some(client) => HyperHttpResponse::Response(crate::body_guard::hold_until_body_ends(
+            response,
+            Box::new(client),
+        )),
+        None => HyperHttpResponse::Response(response),
+    }
+}
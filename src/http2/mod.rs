@@ -1,5 +1,7 @@
 mod my_http2_client;
 pub use my_http2_client::*;
+mod my_http2_client_redirect;
+mod my_http2_client_retry;
 mod my_http2_client_inner;
 pub use my_http2_client_inner::*;
 mod wrap_http2_endpoint;
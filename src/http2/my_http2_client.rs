@@ -8,7 +8,12 @@ use bytes::Bytes;
 use http_body_util::{combinators::BoxBody, Full};
 use rust_extensions::date_time::DateTimeAsMicroseconds;
 
-use crate::{MyHttpClientConnector, MyHttpClientError};
+use crate::{
+    circuit_breaker::CircuitBreaker,
+    redirect::{RedirectConnectorFactory, RedirectPolicy},
+    retry::RetryPolicy,
+    MyHttpClientConnector, MyHttpClientError,
+};
 
 use super::{MyHttp2ClientInner, MyHttp2ConnectionState};
 use crate::hyper::*;
@@ -17,15 +22,21 @@ pub struct MyHttp2Client<
     TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
     TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
 > {
-    connector: TConnector,
+    pub(super) connector: TConnector,
     stream: PhantomData<TStream>,
-    inner: Arc<MyHttp2ClientInner>,
+    pub(crate) inner: Arc<MyHttp2ClientInner>,
     connect_timeout: Duration,
     connection_id: AtomicU64,
     keep_alive: Option<(Duration, Duration)>,
     // tokio::sync::Mutex by design: held across the dial (TCP connect + h2
     // handshake) to serialize concurrent dialers, so parking_lot does not fit
     connect_lock: tokio::sync::Mutex<()>,
+    decompress_responses: bool,
+    pub(super) redirect_policy: Option<RedirectPolicy>,
+    pub(super) redirect_connector_factory:
+        Option<Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>>,
+    pub(super) retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync + 'static>>,
+    pub(super) circuit_breaker: Option<Arc<CircuitBreaker>>,
 }
 
 impl<
@@ -46,6 +57,11 @@ impl<
             connection_id: AtomicU64::new(0),
             keep_alive: None,
             connect_lock: tokio::sync::Mutex::new(()),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
         }
     }
 
@@ -65,6 +81,11 @@ impl<
             connection_id: AtomicU64::new(0),
             keep_alive: None,
             connect_lock: tokio::sync::Mutex::new(()),
+            decompress_responses: false,
+            redirect_policy: None,
+            redirect_connector_factory: None,
+            retry_policy: None,
+            circuit_breaker: None,
         }
     }
 
@@ -79,6 +100,55 @@ impl<
         self.keep_alive = Some((interval, timeout));
     }
 
+    /// Sends `Accept-Encoding` with the codings enabled by cargo features (unless
+    /// the request has its own) and decodes responses with a supported
+    /// `Content-Encoding`, removing that header and `Content-Length`. Decoded
+    /// bodies over [`crate::compression::MAX_DECODED_BODY_SIZE`] end with an error.
+    pub fn set_decompress_responses(&mut self, decompress_responses: bool) {
+        self.decompress_responses = decompress_responses;
+    }
+
+    /// Makes `do_request` follow redirects. See [`RedirectPolicy`].
+    pub fn set_redirect_policy(&mut self, redirect_policy: RedirectPolicy) {
+        self.redirect_policy = Some(redirect_policy);
+    }
+
+    /// Connectors for cross-origin redirects. Without a factory such redirects
+    /// are returned to the caller even if the policy allows them.
+    pub fn set_redirect_connector_factory(
+        &mut self,
+        factory: Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>,
+    ) {
+        self.redirect_connector_factory = Some(factory);
+    }
+
+    /// Makes `do_request` repeat failed attempts. See [`RetryPolicy`].
+    pub fn set_retry_policy(&mut self, retry_policy: Arc<dyn RetryPolicy + Send + Sync + 'static>) {
+        self.retry_policy = Some(retry_policy);
+    }
+
+    /// Fails requests fast while the endpoint is down. One breaker can be shared
+    /// by many clients. See [`CircuitBreaker`].
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    /// A client for another endpoint with the same settings, used for a
+    /// cross-origin redirect hop.
+    pub(super) fn create_for_connector(&self, connector: TConnector) -> Self {
+        let mut result = match self.inner.metrics.clone() {
+            Some(metrics) => Self::new_with_metrics(connector, metrics),
+            None => Self::new(connector),
+        };
+
+        result.connect_timeout = self.connect_timeout;
+        result.keep_alive = self.keep_alive;
+        result.decompress_responses = self.decompress_responses;
+        result.retry_policy = self.retry_policy.clone();
+        result.circuit_breaker = self.circuit_breaker.clone();
+        result
+    }
+
     /// Lock-free check whether the client holds an established connection right now.
     /// `false` does not mean the client is unusable: it connects lazily, so this is
     /// `false` before the first request and becomes `true` again after a reconnect.
@@ -93,11 +163,46 @@ impl<
         req: &hyper::Request<Full<Bytes>>,
         request_timeout: Duration,
     ) -> Result<hyper::Response<BoxBody<Bytes, String>>, MyHttpClientError> {
+        match self.redirect_policy {
+            Some(redirect_policy) => {
+                self.do_request_with_redirects(req, redirect_policy, request_timeout)
+                    .await
+            }
+            None => self.do_single_request(req, request_timeout).await,
+        }
+    }
+
+    pub(super) async fn send_single_request(
+        &self,
+        req: &hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<hyper::Response<BoxBody<Bytes, String>>, MyHttpClientError> {
+        let with_accept_encoding = if self.decompress_responses
+            && !req.headers().contains_key(http::header::ACCEPT_ENCODING)
+        {
+            let mut req = req.clone();
+            crate::compression::add_accept_encoding(req.headers_mut());
+            Some(req)
+        } else {
+            None
+        };
+
+        let req = with_accept_encoding.as_ref().unwrap_or(req);
+
         let request_is_idempotent = req.method().is_idempotent();
         let mut retry_no = 0;
         loop {
             let err = match self.inner.send_payload(req, request_timeout).await {
                 Ok(response) => {
+                    let response = if self.decompress_responses {
+                        crate::compression::decompress_response(
+                            response,
+                            crate::compression::MAX_DECODED_BODY_SIZE,
+                        )
+                    } else {
+                        response
+                    };
+
                     return Ok(response);
                 }
                 Err(err) => err,
@@ -108,7 +213,7 @@ impl<
                     // The request never reached the wire, so reconnecting and retrying
                     // is safe for any method
                     if retry_no > 3 {
-                        return Err(MyHttpClientError::Disconnected);
+                        return Err(MyHttpClientError::RequestNotSent);
                     }
                     retry_no += 1;
                     self.connect().await?;
@@ -221,7 +326,7 @@ impl<
             let mut state = self.inner.state.lock().await;
             match &mut *state {
                 MyHttp2ConnectionState::Disconnected => {
-                    return Err(MyHttpClientError::Disconnected);
+                    return Err(MyHttpClientError::RequestNotSent);
                 }
                 MyHttp2ConnectionState::Connected {
                     send_request,
@@ -278,6 +383,27 @@ impl<
     }
 
     pub async fn connect(&self) -> Result<(), MyHttpClientError> {
+        self.establish_connection(self.connector.connect()).await
+    }
+
+    /// Makes `stream`, already connected to the endpoint, the connection of the
+    /// client instead of dialing one. Used to continue a connection upgraded to
+    /// h2c; reconnects dial through the connector as usual.
+    pub(crate) async fn connect_with_stream<
+        TUpgradedStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    >(
+        &self,
+        stream: TUpgradedStream,
+    ) -> Result<(), MyHttpClientError> {
+        self.establish_connection(async { Ok(stream) }).await
+    }
+
+    async fn establish_connection<
+        TConnectedStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    >(
+        &self,
+        stream: impl std::future::Future<Output = Result<TConnectedStream, MyHttpClientError>>,
+    ) -> Result<(), MyHttpClientError> {
         // Serializes dialers: a burst of failing requests produces one dial, not a
         // thundering herd. The state lock is NOT held across the dial, so concurrent
         // send_payload calls keep failing fast with Disconnected (and requests on a
@@ -308,7 +434,7 @@ impl<
         // The timeout covers the whole dial including the h2 handshake: an upstream
         // that accepts TCP but never answers SETTINGS must not hang connect() forever
         let dial = async {
-            let stream = self.connector.connect().await?;
+            let stream = stream.await?;
 
             super::wrap_http2_endpoint::wrap_http2_endpoint(
                 stream,
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_body_util::{combinators::BoxBody, Full};
+
+use crate::{retry::RetryReason, MyHttpClientConnector, MyHttpClientError};
+
+use super::MyHttp2Client;
+
+impl<
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    > MyHttp2Client<TStream, TConnector>
+{
+    /// One request-response exchange, repeated according to the retry policy.
+    pub(super) async fn do_single_request(
+        &self,
+        req: &hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<hyper::Response<BoxBody<Bytes, String>>, MyHttpClientError> {
+        let retry_policy = match self.retry_policy.as_ref() {
+            Some(retry_policy) => retry_policy,
+            None => return self.send_attempt(req, request_timeout).await,
+        };
+
+        let mut attempt = 1;
+
+        loop {
+            let delay = match self.send_attempt(req, request_timeout).await {
+                Ok(response) => {
+                    let delay = crate::retry::get_response_retry_delay(
+                        retry_policy.as_ref(),
+                        req.method(),
+                        attempt,
+                        response.status(),
+                        response.headers(),
+                    );
+
+                    match delay {
+                        Some(delay) => {
+                            crate::utils::drain_body(response, request_timeout).await;
+                            delay
+                        }
+                        None => return Ok(response),
+                    }
+                }
+                Err(err) => {
+                    let reason = RetryReason::Error(&err);
+                    match retry_policy.get_retry_delay(req.method(), attempt, &reason) {
+                        Some(delay) => delay,
+                        None => return Err(err),
+                    }
+                }
+            };
+
+            if let Some(metrics) = self.inner.metrics.as_ref() {
+                metrics.request_retried(self.inner.name.as_str(), attempt, delay);
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// One attempt, let through or failed fast by the circuit breaker.
+    async fn send_attempt(
+        &self,
+        req: &hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<hyper::Response<BoxBody<Bytes, String>>, MyHttpClientError> {
+        let circuit_breaker = match self.circuit_breaker.as_ref() {
+            Some(circuit_breaker) => circuit_breaker,
+            None => return self.send_single_request(req, request_timeout).await,
+        };
+
+        let state_changed = |state| {
+            if let Some(metrics) = self.inner.metrics.as_ref() {
+                metrics.circuit_breaker_state_changed(self.inner.name.as_str(), state);
+            }
+        };
+
+        circuit_breaker
+            .execute(
+                self.inner.name.as_str(),
+                self.send_single_request(req, request_timeout),
+                state_changed,
+            )
+            .await
+    }
+}
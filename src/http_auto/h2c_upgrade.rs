@@ -0,0 +1,91 @@
+use bytes::Bytes;
+use http::{header, HeaderValue};
+use http_body_util::Full;
+use hyper_util::rt::TokioIo;
+
+use super::h2c_upgraded_stream::H2cUpgradedStream;
+use crate::{hyper::SendHyperPayloadError, MyHttpClientError};
+
+// base64url of a SETTINGS payload with SETTINGS_ENABLE_PUSH = 0
+const HTTP2_SETTINGS: &str = "AAIAAAAA";
+
+pub(super) enum H2cUpgrade<TStream> {
+    /// `101 Switching Protocols`: the connection speaks HTTP/2 from now on and
+    /// the response to the request comes on stream 1.
+    Accepted(H2cUpgradedStream<TStream>),
+    /// Any other status is the HTTP/1.1 response to the request itself.
+    Declined(crate::HyperResponse),
+}
+
+/// Adds the headers asking the server to switch the connection to h2c
+/// (RFC 7540 §3.2) while it answers `req`.
+pub(super) fn add_h2c_upgrade_headers(req: &mut hyper::Request<Full<Bytes>>) {
+    let headers = req.headers_mut();
+    headers.insert(
+        header::CONNECTION,
+        HeaderValue::from_static("Upgrade, HTTP2-Settings"),
+    );
+    headers.insert(header::UPGRADE, HeaderValue::from_static("h2c"));
+    headers.insert("HTTP2-Settings", HeaderValue::from_static(HTTP2_SETTINGS));
+}
+
+/// Reads the answer to a request sent with [`add_h2c_upgrade_headers`]. On
+/// `101` the connection is taken back from hyper together with the bytes it read
+/// past the response head.
+pub(super) async fn get_h2c_upgrade_result<TStream>(
+    response: crate::HyperResponse,
+) -> Result<H2cUpgrade<TStream>, MyHttpClientError>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+{
+    if response.status() != http::StatusCode::SWITCHING_PROTOCOLS {
+        return Ok(H2cUpgrade::Declined(response));
+    }
+
+    let upgraded = hyper::upgrade::on(response).await.map_err(|err| {
+        MyHttpClientError::CanNotExecuteRequest(format!("h2c upgrade failed: {}", err))
+    })?;
+
+    match upgraded.downcast::<TokioIo<TStream>>() {
+        Ok(parts) => Ok(H2cUpgrade::Accepted(H2cUpgradedStream::new(
+            parts.io.into_inner(),
+            parts.read_buf,
+        ))),
+        Err(_) => Err(MyHttpClientError::CanNotExecuteRequest(
+            "h2c upgrade failed: the upgraded connection is not the dialed one".to_string(),
+        )),
+    }
+}
+
+/// Errors of the request that carried the upgrade. It is not retried: on the
+/// connection it went out on the protocol is not known yet.
+pub(super) fn into_client_error(err: SendHyperPayloadError) -> MyHttpClientError {
+    match err {
+        SendHyperPayloadError::Disconnected => MyHttpClientError::RequestNotSent,
+        SendHyperPayloadError::Disposed => MyHttpClientError::Disposed,
+        SendHyperPayloadError::UpgradedToWebsocket => MyHttpClientError::UpgradedToWebSocket,
+        SendHyperPayloadError::RequestTimeout(duration) => {
+            MyHttpClientError::RequestTimeout(duration)
+        }
+        SendHyperPayloadError::HyperError { err, .. } => {
+            if err.is_canceled() {
+                MyHttpClientError::RequestNotSent
+            } else {
+                MyHttpClientError::CanNotExecuteRequest(err.to_string())
+            }
+        }
+    }
+}
+
+/// Copy of the upgrade request for hyper's HTTP/2 client to open stream 1 with.
+/// Its frames never reach the server, so the body is left out.
+pub(super) fn create_stream_1_request(
+    req: &hyper::Request<Full<Bytes>>,
+) -> hyper::Request<Full<Bytes>> {
+    let mut result = hyper::Request::new(Full::new(Bytes::new()));
+    *result.method_mut() = req.method().clone();
+    *result.uri_mut() = req.uri().clone();
+    *result.headers_mut() = req.headers().clone();
+    result.headers_mut().remove(header::CONTENT_LENGTH);
+    result
+}
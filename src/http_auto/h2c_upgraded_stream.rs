@@ -0,0 +1,445 @@
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll, Waker},
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const CLIENT_PREFACE_SIZE: usize = 24;
+const FRAME_HEADER_SIZE: usize = 9;
+
+const FRAME_TYPE_DATA: u8 = 0x0;
+const FRAME_TYPE_HEADERS: u8 = 0x1;
+const FRAME_TYPE_SETTINGS: u8 = 0x4;
+const FRAME_TYPE_CONTINUATION: u8 = 0x9;
+
+const FLAG_ACK: u8 = 0x1;
+
+const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+
+/// Connection the server switched to h2c (RFC 7540 §3.2), handed to hyper's
+/// HTTP/2 client.
+///
+/// The server answers the upgrade request on stream 1, and hyper only reads a
+/// response on a stream it opened itself. So the request is sent through hyper
+/// once more and its frames on stream 1 are kept off the wire: the server has
+/// the request already. Dropping a header block would leave the server's HPACK
+/// table behind hyper's, so hyper is told the server's table size is 0 and
+/// does not index headers at all. Server frames on stream 1 wait until hyper
+/// has opened the stream.
+pub(super) struct H2cUpgradedStream<TStream> {
+    stream: TStream,
+    /// Server bytes not processed yet, starting with the ones read past the
+    /// `101` response head.
+    read_in: BytesMut,
+    incoming: IncomingFrames,
+    /// Reader waiting for hyper to open stream 1.
+    read_waker: Option<Waker>,
+    /// Server bytes rewritten and not taken by hyper yet.
+    read_out: BytesMut,
+    outgoing: OutgoingFrames,
+    /// Client bytes filtered and not written to `stream` yet.
+    write_out: BytesMut,
+}
+
+impl<TStream> H2cUpgradedStream<TStream> {
+    pub fn new(stream: TStream, read_ahead: Bytes) -> Self {
+        Self {
+            stream,
+            read_in: BytesMut::from(&read_ahead[..]),
+            incoming: IncomingFrames::default(),
+            read_waker: None,
+            read_out: BytesMut::new(),
+            outgoing: OutgoingFrames::default(),
+            write_out: BytesMut::new(),
+        }
+    }
+}
+
+struct FrameHeader {
+    length: usize,
+    frame_type: u8,
+    flags: u8,
+    stream_id: u32,
+}
+
+impl FrameHeader {
+    fn parse(src: &[u8]) -> Self {
+        Self {
+            length: u32::from_be_bytes([0, src[0], src[1], src[2]]) as usize,
+            frame_type: src[3],
+            flags: src[4],
+            stream_id: u32::from_be_bytes([src[5], src[6], src[7], src[8]]) & 0x7fff_ffff,
+        }
+    }
+
+    fn write(&self, dest: &mut BytesMut) {
+        dest.extend_from_slice(&(self.length as u32).to_be_bytes()[1..]);
+        dest.extend_from_slice(&[self.frame_type, self.flags]);
+        dest.extend_from_slice(&self.stream_id.to_be_bytes());
+    }
+}
+
+/// Sets `SETTINGS_HEADER_TABLE_SIZE` to 0 in every SETTINGS frame of the server
+/// and passes the other frames through.
+#[derive(Default)]
+struct IncomingFrames {
+    header: Vec<u8>,
+    /// Payload bytes of the current frame still to pass through.
+    payload_left: usize,
+    /// Header of the current SETTINGS frame and its payload read so far.
+    settings: Option<(FrameHeader, Vec<u8>)>,
+}
+
+impl IncomingFrames {
+    fn is_waiting_for_stream_1(&self, stream_1_opened: bool) -> bool {
+        !stream_1_opened
+            && self.header.len() == FRAME_HEADER_SIZE
+            && FrameHeader::parse(&self.header).stream_id == 1
+    }
+
+    /// Returns how much of `data` is taken. Stops at a frame on stream 1 while
+    /// hyper has not opened it.
+    fn process(&mut self, mut data: &[u8], stream_1_opened: bool, dest: &mut BytesMut) -> usize {
+        let data_len = data.len();
+
+        loop {
+            if self.payload_left > 0 {
+                if data.is_empty() {
+                    break;
+                }
+
+                let size = self.payload_left.min(data.len());
+                dest.extend_from_slice(&data[..size]);
+                self.payload_left -= size;
+                data = &data[size..];
+                continue;
+            }
+
+            if let Some((header, mut payload)) = self.settings.take() {
+                if data.is_empty() {
+                    self.settings = Some((header, payload));
+                    break;
+                }
+
+                let size = (header.length - payload.len()).min(data.len());
+                payload.extend_from_slice(&data[..size]);
+                data = &data[size..];
+
+                if payload.len() < header.length {
+                    self.settings = Some((header, payload));
+                } else {
+                    write_settings(header, payload, dest);
+                }
+                continue;
+            }
+
+            if self.header.len() < FRAME_HEADER_SIZE {
+                if data.is_empty() {
+                    break;
+                }
+
+                let size = (FRAME_HEADER_SIZE - self.header.len()).min(data.len());
+                self.header.extend_from_slice(&data[..size]);
+                data = &data[size..];
+                continue;
+            }
+
+            if self.is_waiting_for_stream_1(stream_1_opened) {
+                break;
+            }
+
+            let header = FrameHeader::parse(&self.header);
+            self.header.clear();
+
+            if header.frame_type == FRAME_TYPE_SETTINGS
+                && header.flags & FLAG_ACK == 0
+                && header.length.is_multiple_of(6)
+            {
+                if header.length == 0 {
+                    write_settings(header, Vec::new(), dest);
+                } else {
+                    let payload = Vec::with_capacity(header.length);
+                    self.settings = Some((header, payload));
+                }
+                continue;
+            }
+
+            header.write(dest);
+            self.payload_left = header.length;
+        }
+
+        data_len - data.len()
+    }
+}
+
+fn write_settings(mut header: FrameHeader, mut payload: Vec<u8>, dest: &mut BytesMut) {
+    let mut table_size_found = false;
+
+    for setting in payload.chunks_mut(6) {
+        if u16::from_be_bytes([setting[0], setting[1]]) == SETTINGS_HEADER_TABLE_SIZE {
+            setting[2..].copy_from_slice(&0u32.to_be_bytes());
+            table_size_found = true;
+        }
+    }
+
+    if !table_size_found {
+        payload.extend_from_slice(&SETTINGS_HEADER_TABLE_SIZE.to_be_bytes());
+        payload.extend_from_slice(&0u32.to_be_bytes());
+    }
+
+    header.length = payload.len();
+    header.write(dest);
+    dest.extend_from_slice(&payload);
+}
+
+/// Drops the HEADERS, CONTINUATION and DATA frames hyper sends on stream 1 and
+/// passes the client preface and every other frame through.
+#[derive(Default)]
+struct OutgoingFrames {
+    preface_written: usize,
+    header: Vec<u8>,
+    /// Payload bytes of the current frame still to pass through or drop.
+    payload_left: usize,
+    dropping: bool,
+    stream_1_opened: bool,
+}
+
+impl OutgoingFrames {
+    fn process(&mut self, mut data: &[u8], dest: &mut BytesMut) {
+        if self.preface_written < CLIENT_PREFACE_SIZE {
+            let size = (CLIENT_PREFACE_SIZE - self.preface_written).min(data.len());
+            dest.extend_from_slice(&data[..size]);
+            self.preface_written += size;
+            data = &data[size..];
+        }
+
+        while !data.is_empty() {
+            if self.payload_left > 0 {
+                let size = self.payload_left.min(data.len());
+                if !self.dropping {
+                    dest.extend_from_slice(&data[..size]);
+                }
+                self.payload_left -= size;
+                data = &data[size..];
+                continue;
+            }
+
+            let size = (FRAME_HEADER_SIZE - self.header.len()).min(data.len());
+            self.header.extend_from_slice(&data[..size]);
+            data = &data[size..];
+
+            if self.header.len() < FRAME_HEADER_SIZE {
+                continue;
+            }
+
+            let header = FrameHeader::parse(&self.header);
+            self.header.clear();
+
+            self.dropping = header.stream_id == 1
+                && matches!(
+                    header.frame_type,
+                    FRAME_TYPE_HEADERS | FRAME_TYPE_CONTINUATION | FRAME_TYPE_DATA
+                );
+
+            if header.stream_id == 1 && header.frame_type == FRAME_TYPE_HEADERS {
+                self.stream_1_opened = true;
+            }
+
+            if !self.dropping {
+                header.write(dest);
+            }
+            self.payload_left = header.length;
+        }
+    }
+}
+
+impl<TStream: AsyncWrite + Unpin> H2cUpgradedStream<TStream> {
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while !self.write_out.is_empty() {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.write_out))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.write_out.advance(written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<TStream: AsyncRead + Unpin> AsyncRead for H2cUpgradedStream<TStream> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        loop {
+            if !this.read_out.is_empty() {
+                let size = this.read_out.len().min(buf.remaining());
+                buf.put_slice(&this.read_out.split_to(size));
+                return Poll::Ready(Ok(()));
+            }
+
+            let stream_1_opened = this.outgoing.stream_1_opened;
+
+            if this.incoming.is_waiting_for_stream_1(stream_1_opened) {
+                this.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+
+            if !this.read_in.is_empty() {
+                let taken =
+                    this.incoming
+                        .process(&this.read_in, stream_1_opened, &mut this.read_out);
+                this.read_in.advance(taken);
+                continue;
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.stream).poll_read(cx, &mut chunk))?;
+
+            if chunk.filled().is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+
+            this.read_in.extend_from_slice(chunk.filled());
+        }
+    }
+}
+
+impl<TStream: AsyncWrite + Unpin> AsyncWrite for H2cUpgradedStream<TStream> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        this.outgoing.process(buf, &mut this.write_out);
+
+        if this.outgoing.stream_1_opened {
+            if let Some(waker) = this.read_waker.take() {
+                waker.wake();
+            }
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.stream).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{FrameHeader, H2cUpgradedStream};
+
+    fn frame(frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut result = BytesMut::new();
+        FrameHeader {
+            length: payload.len(),
+            frame_type,
+            flags,
+            stream_id,
+        }
+        .write(&mut result);
+        result.extend_from_slice(payload);
+        result.to_vec()
+    }
+
+    #[tokio::test]
+    async fn replayed_request_is_kept_off_the_wire() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let mut stream = H2cUpgradedStream::new(client, Bytes::new());
+
+        let preface = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n".to_vec();
+        let settings = frame(0x4, 0, 0, &[0, 2, 0, 0, 0, 0]);
+        let stream_1_headers = frame(0x1, 0x5, 1, &[0x82, 0x84]);
+        let stream_3_headers = frame(0x1, 0x5, 3, &[0x82, 0x84]);
+
+        let mut sent = Vec::new();
+        sent.extend_from_slice(&preface);
+        sent.extend_from_slice(&settings);
+        sent.extend_from_slice(&stream_1_headers);
+        sent.extend_from_slice(&stream_3_headers);
+
+        for byte in sent {
+            stream.write_all(&[byte]).await.unwrap();
+        }
+        stream.flush().await.unwrap();
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&preface);
+        expected.extend_from_slice(&settings);
+        expected.extend_from_slice(&stream_3_headers);
+
+        let mut received = vec![0u8; expected.len()];
+        server.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+    }
+
+    #[tokio::test]
+    async fn server_frames_are_prepared_for_hyper() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+
+        let with_table_size = frame(0x4, 0, 0, &[0, 3, 0, 0, 0, 100, 0, 1, 0, 0, 16, 0]);
+        let empty = frame(0x4, 0, 0, &[]);
+        let ack = frame(0x4, 0x1, 0, &[]);
+        let data = frame(0x0, 0, 1, b"body");
+
+        let mut read_ahead = with_table_size.clone();
+        read_ahead.extend_from_slice(&empty[..4]);
+        let mut stream = H2cUpgradedStream::new(client, read_ahead.into());
+
+        server.write_all(&empty[4..]).await.unwrap();
+        server.write_all(&ack).await.unwrap();
+        server.write_all(&data).await.unwrap();
+
+        // SETTINGS_HEADER_TABLE_SIZE is set to 0, or added
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&frame(0x4, 0, 0, &[0, 3, 0, 0, 0, 100, 0, 1, 0, 0, 0, 0]));
+        expected.extend_from_slice(&frame(0x4, 0, 0, &[0, 1, 0, 0, 0, 0]));
+        expected.extend_from_slice(&ack);
+
+        let mut received = vec![0u8; expected.len()];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, expected);
+
+        // Stream 1 is held back until hyper has opened it
+        let mut buf = [0u8; 16];
+        let read =
+            tokio::time::timeout(std::time::Duration::from_millis(100), stream.read(&mut buf))
+                .await;
+        assert!(read.is_err());
+
+        stream
+            .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+            .await
+            .unwrap();
+        stream
+            .write_all(&frame(0x1, 0x5, 1, &[0x82, 0x84]))
+            .await
+            .unwrap();
+
+        let mut received = vec![0u8; data.len()];
+        stream.read_exact(&mut received).await.unwrap();
+        assert_eq!(received, data);
+    }
+}
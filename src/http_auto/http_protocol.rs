@@ -0,0 +1,6 @@
+/// The protocol [`super::MyHttpAutoClient`] speaks to its endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpProtocol {
+    Http1,
+    Http2,
+}
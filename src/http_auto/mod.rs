@@ -0,0 +1,7 @@
+mod http_protocol;
+pub use http_protocol::*;
+mod my_http_auto_client;
+pub use my_http_auto_client::*;
+mod h2c_upgrade;
+mod h2c_upgraded_stream;
+mod pre_dialed_connector;
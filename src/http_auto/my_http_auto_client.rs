@@ -0,0 +1,576 @@
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use http_body_util::Full;
+
+use super::{h2c_upgrade::H2cUpgrade, pre_dialed_connector::PreDialedConnector, HttpProtocol};
+use crate::{
+    circuit_breaker::CircuitBreaker, connectors::AlpnStream, http1_hyper::*, http2::*,
+    hyper::MyHttpHyperClientMetrics, retry::RetryPolicy, MyHttpClientConnector, MyHttpClientError,
+};
+
+const ALPN_H2: &[u8] = b"h2";
+
+enum MyHttpAutoClientInner<TStream, TConnector>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    Http1(MyHttpHyperClient<TStream, PreDialedConnector<TStream, TConnector>>),
+    Http2(MyHttp2Client<TStream, PreDialedConnector<TStream, TConnector>>),
+}
+
+/// Speaks HTTP/2 to the endpoint if the first connection negotiated `h2` with
+/// ALPN and HTTP/1.1 otherwise, through [`MyHttp2Client`] or [`MyHttpHyperClient`].
+/// [`Self::set_prior_knowledge`] skips the negotiation, e.g. for h2c, and
+/// [`Self::set_h2c_upgrade`] asks a cleartext server to upgrade instead.
+///
+/// The choice is made once, on the first request. Redirects are not followed.
+pub struct MyHttpAutoClient<TStream, TConnector>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    connector: Arc<TConnector>,
+    inner: tokio::sync::OnceCell<MyHttpAutoClientInner<TStream, TConnector>>,
+    prior_knowledge: Option<HttpProtocol>,
+    h2c_upgrade: bool,
+    connect_timeout: Duration,
+    decompress_responses: bool,
+    retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync + 'static>>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    metrics: Option<Arc<dyn MyHttpHyperClientMetrics + Send + Sync + 'static>>,
+}
+
+impl<TStream, TConnector> MyHttpAutoClient<TStream, TConnector>
+where
+    TStream:
+        AlpnStream + tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    pub fn new(connector: TConnector) -> Self {
+        Self {
+            connector: Arc::new(connector),
+            inner: tokio::sync::OnceCell::new(),
+            prior_knowledge: None,
+            h2c_upgrade: false,
+            connect_timeout: Duration::from_secs(5),
+            decompress_responses: false,
+            retry_policy: None,
+            circuit_breaker: None,
+            metrics: None,
+        }
+    }
+
+    pub fn new_with_metrics(
+        connector: TConnector,
+        metrics: Arc<dyn MyHttpHyperClientMetrics + Send + Sync + 'static>,
+    ) -> Self {
+        let mut result = Self::new(connector);
+        result.metrics = Some(metrics);
+        result
+    }
+
+    /// Uses `protocol` without looking at ALPN.
+    pub fn set_prior_knowledge(&mut self, protocol: HttpProtocol) {
+        self.prior_knowledge = Some(protocol);
+    }
+
+    /// When the connection negotiated no protocol with ALPN, sends the first
+    /// request over HTTP/1.1 with `Upgrade: h2c` (RFC 7540 §3.2). If the server
+    /// switches, the connection goes on as HTTP/2 and the response to that request
+    /// is read from stream 1; otherwise the server has answered it over HTTP/1.1
+    /// and the connection stays HTTP/1.1.
+    ///
+    /// The first request is not retried. Connections dialed later use the
+    /// chosen protocol directly, HTTP/2 with prior knowledge.
+    pub fn set_h2c_upgrade(&mut self, h2c_upgrade: bool) {
+        self.h2c_upgrade = h2c_upgrade;
+    }
+
+    pub fn set_connect_timeout(&mut self, connect_timeout: Duration) {
+        self.connect_timeout = connect_timeout;
+    }
+
+    /// See [`MyHttpHyperClient::set_decompress_responses`].
+    pub fn set_decompress_responses(&mut self, decompress_responses: bool) {
+        self.decompress_responses = decompress_responses;
+    }
+
+    /// Makes `do_request` repeat failed attempts. See [`RetryPolicy`].
+    pub fn set_retry_policy(&mut self, retry_policy: Arc<dyn RetryPolicy + Send + Sync + 'static>) {
+        self.retry_policy = Some(retry_policy);
+    }
+
+    /// Fails requests fast while the endpoint is down. See [`CircuitBreaker`].
+    pub fn set_circuit_breaker(&mut self, circuit_breaker: Arc<CircuitBreaker>) {
+        self.circuit_breaker = Some(circuit_breaker);
+    }
+
+    /// The protocol in use, `None` before the first request chose it.
+    pub fn get_protocol(&self) -> Option<HttpProtocol> {
+        match self.inner.get()? {
+            MyHttpAutoClientInner::Http1(_) => Some(HttpProtocol::Http1),
+            MyHttpAutoClientInner::Http2(_) => Some(HttpProtocol::Http2),
+        }
+    }
+
+    pub async fn do_request(
+        &self,
+        req: hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<HyperHttpResponse, MyHttpClientError> {
+        let mut upgrade_response = None;
+
+        let inner = self
+            .inner
+            .get_or_try_init(|| async {
+                let (inner, response) = self.create_inner(&req, request_timeout).await?;
+                upgrade_response = response;
+                Ok::<_, MyHttpClientError>(inner)
+            })
+            .await?;
+
+        if let Some(response) = upgrade_response {
+            return Ok(HyperHttpResponse::Response(response));
+        }
+
+        match inner {
+            MyHttpAutoClientInner::Http1(client) => client.do_request(req, request_timeout).await,
+            MyHttpAutoClientInner::Http2(client) => {
+                let response = client.do_request(&req, request_timeout).await?;
+                Ok(HyperHttpResponse::Response(response))
+            }
+        }
+    }
+
+    /// Chooses the protocol. With an h2c upgrade `req` is sent on the way, and
+    /// its response is returned along with the client.
+    async fn create_inner(
+        &self,
+        req: &hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<
+        (
+            MyHttpAutoClientInner<TStream, TConnector>,
+            Option<crate::HyperResponse>,
+        ),
+        MyHttpClientError,
+    > {
+        let (protocol, stream) = match self.prior_knowledge {
+            Some(protocol) => (protocol, None),
+            None => {
+                let stream = self.dial().await?;
+
+                match stream.get_alpn_protocol() {
+                    Some(protocol) if protocol == ALPN_H2 => (HttpProtocol::Http2, Some(stream)),
+                    None if self.h2c_upgrade => {
+                        return self
+                            .create_inner_with_h2c_upgrade(stream, req, request_timeout)
+                            .await;
+                    }
+                    _ => (HttpProtocol::Http1, Some(stream)),
+                }
+            }
+        };
+
+        let result = match protocol {
+            HttpProtocol::Http1 => MyHttpAutoClientInner::Http1(self.create_http1_client(stream)),
+            HttpProtocol::Http2 => MyHttpAutoClientInner::Http2(self.create_http2_client(stream)),
+        };
+
+        Ok((result, None))
+    }
+
+    async fn create_inner_with_h2c_upgrade(
+        &self,
+        stream: TStream,
+        req: &hyper::Request<Full<Bytes>>,
+        request_timeout: Duration,
+    ) -> Result<
+        (
+            MyHttpAutoClientInner<TStream, TConnector>,
+            Option<crate::HyperResponse>,
+        ),
+        MyHttpClientError,
+    > {
+        let http1_client = self.create_http1_client(Some(stream));
+        http1_client.connect().await?;
+
+        let mut upgrade_req = req.clone();
+        if self.decompress_responses {
+            crate::compression::add_accept_encoding(upgrade_req.headers_mut());
+        }
+        super::h2c_upgrade::add_h2c_upgrade_headers(&mut upgrade_req);
+
+        let response = http1_client
+            .inner
+            .send_payload(&upgrade_req, request_timeout)
+            .await
+            .map_err(super::h2c_upgrade::into_client_error)?;
+
+        let upgraded = match super::h2c_upgrade::get_h2c_upgrade_result::<TStream>(response).await?
+        {
+            H2cUpgrade::Accepted(upgraded) => upgraded,
+            H2cUpgrade::Declined(response) => {
+                let response = self.decompress_first_response(response);
+                return Ok((MyHttpAutoClientInner::Http1(http1_client), Some(response)));
+            }
+        };
+
+        let http2_client = self.create_http2_client(None);
+        http2_client.connect_with_stream(upgraded).await?;
+
+        let mut stream_1_req = super::h2c_upgrade::create_stream_1_request(req);
+        if self.decompress_responses {
+            crate::compression::add_accept_encoding(stream_1_req.headers_mut());
+        }
+
+        let response = http2_client
+            .inner
+            .send_payload(&stream_1_req, request_timeout)
+            .await
+            .map_err(super::h2c_upgrade::into_client_error)?;
+
+        let response = self.decompress_first_response(response);
+        Ok((MyHttpAutoClientInner::Http2(http2_client), Some(response)))
+    }
+
+    fn decompress_first_response(&self, response: crate::HyperResponse) -> crate::HyperResponse {
+        if !self.decompress_responses {
+            return response;
+        }
+
+        crate::compression::decompress_response(response, crate::compression::MAX_DECODED_BODY_SIZE)
+    }
+
+    fn create_http1_client(
+        &self,
+        stream: Option<TStream>,
+    ) -> MyHttpHyperClient<TStream, PreDialedConnector<TStream, TConnector>> {
+        let connector = PreDialedConnector::new(self.connector.clone(), stream);
+
+        let mut client = match self.metrics.clone() {
+            Some(metrics) => MyHttpHyperClient::new_with_metrics(connector, metrics),
+            None => MyHttpHyperClient::new(connector),
+        };
+
+        client.set_connect_timeout(self.connect_timeout);
+        client.set_decompress_responses(self.decompress_responses);
+
+        if let Some(retry_policy) = self.retry_policy.clone() {
+            client.set_retry_policy(retry_policy);
+        }
+
+        if let Some(circuit_breaker) = self.circuit_breaker.clone() {
+            client.set_circuit_breaker(circuit_breaker);
+        }
+
+        client
+    }
+
+    fn create_http2_client(
+        &self,
+        stream: Option<TStream>,
+    ) -> MyHttp2Client<TStream, PreDialedConnector<TStream, TConnector>> {
+        let connector = PreDialedConnector::new(self.connector.clone(), stream);
+
+        let mut client = match self.metrics.clone() {
+            Some(metrics) => MyHttp2Client::new_with_metrics(connector, metrics),
+            None => MyHttp2Client::new(connector),
+        };
+
+        client.set_connect_timeout(self.connect_timeout);
+        client.set_decompress_responses(self.decompress_responses);
+
+        if let Some(retry_policy) = self.retry_policy.clone() {
+            client.set_retry_policy(retry_policy);
+        }
+
+        if let Some(circuit_breaker) = self.circuit_breaker.clone() {
+            client.set_circuit_breaker(circuit_breaker);
+        }
+
+        client
+    }
+
+    async fn dial(&self) -> Result<TStream, MyHttpClientError> {
+        match tokio::time::timeout(self.connect_timeout, self.connector.connect()).await {
+            Ok(result) => result,
+            Err(_) => Err(MyHttpClientError::CanNotConnectToRemoteHost(format!(
+                "Can not connect to remote endpoint: '{}' Timeout: {:?}",
+                self.connector.get_remote_endpoint().get_host_port(),
+                self.connect_timeout
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use tokio::{
+        io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::MyHttpAutoClient;
+    use crate::{
+        connectors::TcpConnector, http1_hyper::HyperHttpResponse, http_auto::HttpProtocol,
+    };
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn get_request() -> hyper::Request<Full<Bytes>> {
+        hyper::Request::builder()
+            .uri("http://localhost/")
+            .body(Full::new(Bytes::new()))
+            .unwrap()
+    }
+
+    fn unwrap_status(response: HyperHttpResponse) -> http::StatusCode {
+        match response {
+            HyperHttpResponse::Response(response) => response.status(),
+            HyperHttpResponse::WebSocketUpgrade { .. } => panic!("Unexpected upgrade"),
+        }
+    }
+
+    async fn serve_http2(stream: impl AsyncRead + AsyncWrite + Unpin + Send + 'static) {
+        let service = hyper::service::service_fn(|_req| async {
+            Ok::<_, std::convert::Infallible>(hyper::Response::new(Full::new(Bytes::new())))
+        });
+
+        let _ = hyper::server::conn::http2::Builder::new(hyper_util::rt::TokioExecutor::new())
+            .serve_connection(hyper_util::rt::TokioIo::new(stream), service)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn http1_is_used_without_alpn() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !received.ends_with(b"\r\n\r\n") {
+                let read = socket.read(&mut buf).await.unwrap();
+                assert!(read > 0);
+                received.extend_from_slice(&buf[..read]);
+            }
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = socket.read(&mut buf).await;
+        });
+
+        let client = MyHttpAutoClient::new(TcpConnector::new(remote_endpoint));
+        assert_eq!(client.get_protocol(), None);
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 200);
+        assert_eq!(client.get_protocol(), Some(HttpProtocol::Http1));
+    }
+
+    #[tokio::test]
+    async fn http2_with_prior_knowledge() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            serve_http2(socket).await;
+        });
+
+        let mut client = MyHttpAutoClient::new(TcpConnector::new(remote_endpoint));
+        client.set_prior_knowledge(HttpProtocol::Http2);
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 200);
+        assert_eq!(client.get_protocol(), Some(HttpProtocol::Http2));
+    }
+
+    /// Reads the first request on `socket` and checks that it is the request made
+    /// by the test, asking to switch to h2c.
+    async fn read_h2c_upgrade_request(socket: &mut tokio::net::TcpStream) {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !received.ends_with(b"\r\n\r\n") {
+            let read = socket.read(&mut buf).await.unwrap();
+            assert!(read > 0);
+            received.extend_from_slice(&buf[..read]);
+        }
+
+        let received = String::from_utf8(received).unwrap().to_lowercase();
+        assert!(received.starts_with("get http://localhost/ http/1.1\r\n"));
+        assert!(received.contains("\r\nupgrade: h2c\r\n"));
+        assert!(received.contains("\r\nhttp2-settings: "));
+    }
+
+    fn h2_frame(frame_type: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut result = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        result.extend_from_slice(&[frame_type, flags]);
+        result.extend_from_slice(&stream_id.to_be_bytes());
+        result.extend_from_slice(payload);
+        result
+    }
+
+    #[tokio::test]
+    async fn http2_after_accepted_h2c_upgrade() {
+        const SETTINGS: u8 = 0x4;
+        const HEADERS: u8 = 0x1;
+        const END_STREAM_AND_HEADERS: u8 = 0x5;
+        // HPACK static table entries
+        const STATUS_200: u8 = 0x88;
+        const STATUS_204: u8 = 0x89;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        let server = tokio::spawn(async move {
+            // Only one connection: a second dial would fail
+            let (mut socket, _) = listener.accept().await.unwrap();
+            drop(listener);
+
+            read_h2c_upgrade_request(&mut socket).await;
+
+            // The server preface and the response to the upgrade request on stream 1
+            // come right after the 101 head, in the same write
+            let mut response =
+                b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n"
+                    .to_vec();
+            response.extend_from_slice(&h2_frame(SETTINGS, 0, 0, &[]));
+            response.extend_from_slice(&h2_frame(
+                HEADERS,
+                END_STREAM_AND_HEADERS,
+                1,
+                &[STATUS_200],
+            ));
+            socket.write_all(&response).await.unwrap();
+
+            let mut preface = [0u8; 24];
+            socket.read_exact(&mut preface).await.unwrap();
+            assert_eq!(&preface, b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+
+            let mut streams = Vec::new();
+            loop {
+                let mut header = [0u8; 9];
+                if socket.read_exact(&mut header).await.is_err() {
+                    return streams;
+                }
+
+                let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+                let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+                let mut payload = vec![0u8; length];
+                socket.read_exact(&mut payload).await.unwrap();
+
+                match header[3] {
+                    SETTINGS if header[4] == 0 => {
+                        socket
+                            .write_all(&h2_frame(SETTINGS, 0x1, 0, &[]))
+                            .await
+                            .unwrap();
+                    }
+                    HEADERS => {
+                        streams.push(stream_id);
+                        let frame =
+                            h2_frame(HEADERS, END_STREAM_AND_HEADERS, stream_id, &[STATUS_204]);
+                        socket.write_all(&frame).await.unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let mut client = MyHttpAutoClient::new(TcpConnector::new(remote_endpoint));
+        client.set_h2c_upgrade(true);
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 200);
+        assert_eq!(client.get_protocol(), Some(HttpProtocol::Http2));
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 204);
+
+        drop(client);
+        // The copy of the upgrade request hyper sends on stream 1 stays off the wire
+        assert_eq!(server.await.unwrap(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn http1_after_declined_h2c_upgrade() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            // Only one connection: a second dial would fail
+            let (mut socket, _) = listener.accept().await.unwrap();
+            drop(listener);
+
+            read_h2c_upgrade_request(&mut socket).await;
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+
+            let mut received = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !received.ends_with(b"\r\n\r\n") {
+                let read = socket.read(&mut buf).await.unwrap();
+                assert!(read > 0);
+                received.extend_from_slice(&buf[..read]);
+            }
+            let received = String::from_utf8(received).unwrap().to_lowercase();
+            assert!(!received.contains("\r\nupgrade: "));
+
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = socket.read(&mut buf).await;
+        });
+
+        let mut client = MyHttpAutoClient::new(TcpConnector::new(remote_endpoint));
+        client.set_h2c_upgrade(true);
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 200);
+        assert_eq!(client.get_protocol(), Some(HttpProtocol::Http1));
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 204);
+    }
+
+    #[cfg(feature = "rustls")]
+    #[tokio::test]
+    async fn http2_is_used_when_negotiated_with_alpn() {
+        use crate::connectors::{test_certificates::TestCertificates, TlsConnector};
+
+        let certificates = TestCertificates::generate();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote_endpoint = listener.local_addr().unwrap().to_string();
+        let acceptor = tokio_rustls::TlsAcceptor::from(certificates.get_server_config(false));
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let tls = acceptor.accept(socket).await.unwrap();
+            serve_http2(tls).await;
+        });
+
+        let mut connector = TlsConnector::new(remote_endpoint, certificates.get_root_store());
+        connector.set_server_name("localhost").unwrap();
+        connector.set_alpn_protocols(&[b"h2", b"http/1.1"]);
+
+        let client = MyHttpAutoClient::new(connector);
+
+        let response = client.do_request(get_request(), TIMEOUT).await.unwrap();
+        assert_eq!(unwrap_status(response), 200);
+        assert_eq!(client.get_protocol(), Some(HttpProtocol::Http2));
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use rust_extensions::remote_endpoint::RemoteEndpoint;
+use tokio::io::{ReadHalf, WriteHalf};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+/// Hands the connection dialed to read ALPN to the client chosen by it, and
+/// dials through the wrapped connector from then on.
+pub(super) struct PreDialedConnector<TStream, TConnector> {
+    connector: Arc<TConnector>,
+    stream: parking_lot::Mutex<Option<TStream>>,
+}
+
+impl<TStream, TConnector> PreDialedConnector<TStream, TConnector> {
+    pub fn new(connector: Arc<TConnector>, stream: Option<TStream>) -> Self {
+        Self {
+            connector,
+            stream: parking_lot::Mutex::new(stream),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<TStream, TConnector> MyHttpClientConnector<TStream> for PreDialedConnector<TStream, TConnector>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    async fn connect(&self) -> Result<TStream, MyHttpClientError> {
+        let stream = self.stream.lock().take();
+
+        match stream {
+            Some(stream) => Ok(stream),
+            None => self.connector.connect().await,
+        }
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        self.connector.get_remote_endpoint()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.connector.is_debug()
+    }
+
+    fn reunite(read: ReadHalf<TStream>, write: WriteHalf<TStream>) -> TStream {
+        TConnector::reunite(read, write)
+    }
+}
@@ -21,4 +21,16 @@ pub trait MyHttpHyperClientMetrics {
     fn instance_disposed(&self, name: &str);
     fn connected(&self, name: &str);
     fn disconnected(&self, name: &str);
+
+    /// A failed attempt of a request is repeated after `delay` according to the
+    /// client's [`crate::retry::RetryPolicy`]. `attempt` is the number of the failed one.
+    fn request_retried(&self, _name: &str, _attempt: usize, _delay: Duration) {}
+
+    /// The circuit of the endpoint this client talks to changed its state.
+    fn circuit_breaker_state_changed(
+        &self,
+        _name: &str,
+        _state: crate::circuit_breaker::CircuitBreakerState,
+    ) {
+    }
 }
@@ -1,9 +1,13 @@
+pub mod circuit_breaker;
+pub mod compression;
+pub mod connectors;
 pub mod http1;
 
 mod error;
 pub use error::*;
 
 pub mod http2;
+pub mod http_auto;
 mod my_http_client_connector;
 pub mod utils;
 pub use my_http_client_connector::*;
@@ -12,10 +16,14 @@ pub use my_http_client_disconnect::*;
 
 pub mod http1_hyper;
 pub mod hyper;
+pub mod redirect;
+pub mod retry;
 
 pub type HyperResponse = http::Response<http_body_util::combinators::BoxBody<bytes::Bytes, String>>;
 mod headers;
 pub use headers::*;
+mod typed_headers;
+mod body_guard;
 
 const CL_CR: &[u8] = b"\r\n";
 pub extern crate http;
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use rust_extensions::remote_endpoint::RemoteEndpoint;
 use tokio::io::{ReadHalf, WriteHalf};
 
@@ -11,3 +13,28 @@ pub trait MyHttpClientConnector<TStream: tokio::io::AsyncRead + tokio::io::Async
 
     fn reunite(read: ReadHalf<TStream>, write: WriteHalf<TStream>) -> TStream;
 }
+
+/// Lets several clients dial through one shared connector (e.g. the
+/// connections of a [`crate::http1::MyHttpClientPool`]).
+#[async_trait::async_trait]
+impl<TStream, TConnector> MyHttpClientConnector<TStream> for Arc<TConnector>
+where
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+{
+    async fn connect(&self) -> Result<TStream, MyHttpClientError> {
+        self.as_ref().connect().await
+    }
+
+    fn get_remote_endpoint(&self) -> RemoteEndpoint<'_> {
+        self.as_ref().get_remote_endpoint()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.as_ref().is_debug()
+    }
+
+    fn reunite(read: ReadHalf<TStream>, write: WriteHalf<TStream>) -> TStream {
+        TConnector::reunite(read, write)
+    }
+}
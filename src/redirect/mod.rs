@@ -0,0 +1,12 @@
+mod redirect_policy;
+pub use redirect_policy::*;
+mod redirect_origin;
+pub use redirect_origin::*;
+mod redirect_connector_factory;
+pub use redirect_connector_factory::*;
+mod redirect_step;
+pub use redirect_step::*;
+mod redirect_chain;
+pub(crate) use redirect_chain::*;
+mod redirect_hyper_request;
+pub(crate) use redirect_hyper_request::*;
@@ -0,0 +1,122 @@
+use std::sync::Arc;
+
+use http::{header, header::HeaderName, Method};
+
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+use super::{RedirectConnectorFactory, RedirectOrigin, RedirectPolicy, RedirectStep};
+
+/// Credentials of the current origin, dropped on a hop to another origin.
+/// `Host` is rewritten instead.
+pub(crate) const CROSS_ORIGIN_HEADERS: [HeaderName; 3] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::PROXY_AUTHORIZATION,
+];
+
+/// Headers describing the body, dropped together with it.
+pub(crate) const BODY_HEADERS: [HeaderName; 6] = [
+    header::CONTENT_LENGTH,
+    header::CONTENT_TYPE,
+    header::CONTENT_ENCODING,
+    header::TRANSFER_ENCODING,
+    header::EXPECT,
+    header::TRAILER,
+];
+
+/// Redirect hop to follow. `target_origin` and `connector` are set when it
+/// leaves the origin the chain is on.
+pub(crate) struct RedirectHop<TConnector> {
+    pub step: RedirectStep,
+    pub target_origin: Option<RedirectOrigin>,
+    pub connector: Option<TConnector>,
+}
+
+/// State of the redirects followed for one request, shared by the clients:
+/// which responses are followed, the hop limit and the origin the chain is on.
+pub(crate) struct RedirectChain {
+    policy: RedirectPolicy,
+    current_origin: Option<RedirectOrigin>,
+    redirects: usize,
+}
+
+impl RedirectChain {
+    pub fn new(policy: RedirectPolicy, current_origin: Option<RedirectOrigin>) -> Self {
+        Self {
+            policy,
+            current_origin,
+            redirects: 0,
+        }
+    }
+
+    /// Hop for `response` to the request sent last, or `None` if the response
+    /// goes back to the caller as is: it is not a followable redirect, the body
+    /// can not be sent again, or it points to another origin the policy or a
+    /// missing connector factory does not allow.
+    pub fn get_next_hop<TStream, TConnector>(
+        &self,
+        response: &crate::HyperResponse,
+        method: &Method,
+        path_and_query: &str,
+        can_send_body_again: bool,
+        connector_factory: Option<
+            &Arc<dyn RedirectConnectorFactory<TStream, TConnector> + Send + Sync + 'static>,
+        >,
+    ) -> Result<Option<RedirectHop<TConnector>>, MyHttpClientError>
+    where
+        TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+        TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+    {
+        let step = RedirectStep::from_response(
+            response.status(),
+            response.headers(),
+            method,
+            path_and_query,
+        );
+
+        let step = match step {
+            Some(step) => step,
+            None => return Ok(None),
+        };
+
+        if self.redirects >= self.policy.max_redirects {
+            return Err(MyHttpClientError::TooManyRedirects(self.redirects));
+        }
+
+        if step.keep_body && !can_send_body_again {
+            return Ok(None);
+        }
+
+        let target_origin = match (&step.origin, &self.current_origin) {
+            (Some(target), Some(current)) if current.is_same_origin(target) => None,
+            (Some(target), _) => Some(target.clone()),
+            (None, _) => None,
+        };
+
+        let mut connector = None;
+
+        if let Some(target_origin) = target_origin.as_ref() {
+            let factory = match connector_factory {
+                Some(factory) if self.policy.allow_cross_origin => factory,
+                _ => return Ok(None),
+            };
+
+            connector = Some(factory.create_connector(target_origin)?);
+        }
+
+        Ok(Some(RedirectHop {
+            step,
+            target_origin,
+            connector,
+        }))
+    }
+
+    /// Counts a followed hop; one to `target_origin` moves the chain there.
+    pub fn follow(&mut self, target_origin: Option<RedirectOrigin>) {
+        if target_origin.is_some() {
+            self.current_origin = target_origin;
+        }
+
+        self.redirects += 1;
+    }
+}
@@ -0,0 +1,12 @@
+use crate::{MyHttpClientConnector, MyHttpClientError};
+
+use super::RedirectOrigin;
+
+/// Creates connectors for origins a cross-origin redirect points to.
+pub trait RedirectConnectorFactory<
+    TStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Sync + 'static,
+    TConnector: MyHttpClientConnector<TStream> + Send + Sync + 'static,
+>
+{
+    fn create_connector(&self, origin: &RedirectOrigin) -> Result<TConnector, MyHttpClientError>;
+}
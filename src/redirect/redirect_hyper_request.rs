@@ -0,0 +1,79 @@
+use bytes::Bytes;
+use http::{header, uri::Scheme, Uri};
+use http_body_util::Full;
+
+use super::{RedirectOrigin, RedirectStep, BODY_HEADERS, CROSS_ORIGIN_HEADERS};
+
+/// Origin of a hyper request: the authority of an absolute URI, otherwise
+/// `host_port` of the connector.
+pub(crate) fn get_hyper_request_origin(
+    req: &hyper::Request<Full<Bytes>>,
+    host_port: &str,
+) -> Option<RedirectOrigin> {
+    if let Some(authority) = req.uri().authority() {
+        return Some(RedirectOrigin::new(
+            req.uri().scheme().cloned(),
+            authority.clone(),
+        ));
+    }
+
+    RedirectOrigin::from_host_port(host_port)
+}
+
+/// Next request of a redirect chain. A hop to `target_origin` gets its
+/// authority and loses the [`CROSS_ORIGIN_HEADERS`]; a request with an absolute
+/// URI keeps an absolute URI.
+pub(crate) fn create_redirect_hyper_request(
+    req: &hyper::Request<Full<Bytes>>,
+    step: &RedirectStep,
+    target_origin: Option<&RedirectOrigin>,
+) -> hyper::Request<Full<Bytes>> {
+    let mut uri = Uri::builder().path_and_query(step.path_and_query.clone());
+
+    if let Some(authority) = req.uri().authority() {
+        let scheme = target_origin
+            .and_then(|itm| itm.scheme.clone())
+            .or_else(|| req.uri().scheme().cloned())
+            .unwrap_or(Scheme::HTTP);
+
+        let authority = match target_origin {
+            Some(target_origin) => target_origin.authority.clone(),
+            None => authority.clone(),
+        };
+
+        uri = uri.scheme(scheme).authority(authority);
+    }
+
+    let mut headers = req.headers().clone();
+
+    if let Some(target_origin) = target_origin {
+        for cross_origin_header in CROSS_ORIGIN_HEADERS.iter() {
+            headers.remove(cross_origin_header);
+        }
+
+        if headers.contains_key(header::HOST) {
+            headers.insert(
+                header::HOST,
+                http::HeaderValue::from_str(target_origin.authority.as_str()).unwrap(),
+            );
+        }
+    }
+
+    let body = if step.keep_body {
+        req.body().clone()
+    } else {
+        for body_header in BODY_HEADERS.iter() {
+            headers.remove(body_header);
+        }
+
+        Full::new(Bytes::new())
+    };
+
+    let mut result = hyper::Request::new(body);
+    *result.method_mut() = step.method.clone();
+    *result.uri_mut() = uri.build().unwrap();
+    *result.version_mut() = req.version();
+    *result.headers_mut() = headers;
+
+    result
+}
@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+use http::uri::{Authority, Scheme};
+
+/// Scheme, host and port a request is sent to. The scheme is unknown for
+/// clients that only know the `host:port` of their connector.
+#[derive(Debug, Clone)]
+pub struct RedirectOrigin {
+    pub scheme: Option<Scheme>,
+    pub authority: Authority,
+}
+
+impl RedirectOrigin {
+    pub fn new(scheme: Option<Scheme>, authority: Authority) -> Self {
+        Self { scheme, authority }
+    }
+
+    pub fn from_host_port(host_port: &str) -> Option<Self> {
+        let authority = Authority::from_str(host_port).ok()?;
+        Some(Self::new(None, authority))
+    }
+
+    pub fn get_host(&self) -> &str {
+        self.authority.host()
+    }
+
+    pub fn get_port(&self) -> Option<u16> {
+        self.authority
+            .port_u16()
+            .or_else(|| self.scheme.as_ref().and_then(get_default_port))
+    }
+
+    /// Origins are the same when hosts and ports match and the schemes do not
+    /// contradict each other. A missing port is taken from the other side's
+    /// scheme when this side's scheme is unknown.
+    pub fn is_same_origin(&self, other: &RedirectOrigin) -> bool {
+        if let (Some(scheme), Some(other_scheme)) = (&self.scheme, &other.scheme) {
+            if scheme != other_scheme {
+                return false;
+            }
+        }
+
+        if !self.get_host().eq_ignore_ascii_case(other.get_host()) {
+            return false;
+        }
+
+        let scheme = self.scheme.as_ref().or(other.scheme.as_ref());
+        let default_port = scheme.and_then(get_default_port);
+
+        self.authority.port_u16().or(default_port) == other.authority.port_u16().or(default_port)
+    }
+}
+
+fn get_default_port(scheme: &Scheme) -> Option<u16> {
+    if *scheme == Scheme::HTTP {
+        return Some(80);
+    }
+
+    if *scheme == Scheme::HTTPS {
+        return Some(443);
+    }
+
+    None
+}
@@ -0,0 +1,26 @@
+/// Opt-in redirect following for `do_request`.
+///
+/// 303 turns any method but HEAD into GET without a body; 301 and 302 do the
+/// same for POST only; 307 and 308 repeat the method and body. A hop to another
+/// origin is followed only with `allow_cross_origin` and a connector factory set
+/// on the client, and drops the `Authorization`, `Cookie` and
+/// `Proxy-Authorization` headers. A hop without the body also drops the headers
+/// describing it, `Expect` and `Trailer` included. A hop that can not be
+/// followed (cross-origin not allowed, a streamed body that can not be sent
+/// again) returns the 3xx response to the caller as is.
+#[derive(Debug, Clone, Copy)]
+pub struct RedirectPolicy {
+    /// Redirects followed for one request before failing with
+    /// [`crate::MyHttpClientError::TooManyRedirects`].
+    pub max_redirects: usize,
+    pub allow_cross_origin: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: 10,
+            allow_cross_origin: false,
+        }
+    }
+}
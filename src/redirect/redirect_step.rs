@@ -0,0 +1,277 @@
+use std::str::FromStr;
+
+use http::{uri::PathAndQuery, HeaderMap, Method, StatusCode, Uri};
+
+use super::RedirectOrigin;
+
+/// Next request of a redirect chain, derived from a 3xx response.
+#[derive(Debug, Clone)]
+pub struct RedirectStep {
+    pub method: Method,
+    /// `false` when the next request is sent without the body and the headers
+    /// describing it.
+    pub keep_body: bool,
+    /// Set when `Location` names an authority. The scheme stays unknown for a
+    /// scheme-relative (`//host/path`) location.
+    pub origin: Option<RedirectOrigin>,
+    pub path_and_query: PathAndQuery,
+}
+
+impl RedirectStep {
+    /// `None` if the response is not a followable redirect: a status other
+    /// than 301, 302, 303, 307, 308 or a missing or malformed `Location`.
+    pub fn from_response(
+        status: StatusCode,
+        headers: &HeaderMap,
+        method: &Method,
+        current_path_and_query: &str,
+    ) -> Option<Self> {
+        let (method, keep_body) = match status.as_u16() {
+            303 => {
+                if *method == Method::HEAD {
+                    (Method::HEAD, false)
+                } else {
+                    (Method::GET, false)
+                }
+            }
+            301 | 302 => {
+                if *method == Method::POST {
+                    (Method::GET, false)
+                } else {
+                    (method.clone(), true)
+                }
+            }
+            307 | 308 => (method.clone(), true),
+            _ => return None,
+        };
+
+        let location = headers.get(http::header::LOCATION)?.to_str().ok()?.trim();
+
+        // A fragment is never sent to the server
+        let location = match location.find('#') {
+            Some(pos) => &location[..pos],
+            None => location,
+        };
+
+        let (origin, path_and_query) = resolve_location(location, current_path_and_query)?;
+        let path_and_query = remove_dot_segments(&path_and_query)?;
+
+        Some(Self {
+            method,
+            keep_body,
+            origin,
+            path_and_query,
+        })
+    }
+}
+
+fn resolve_location(
+    location: &str,
+    current_path_and_query: &str,
+) -> Option<(Option<RedirectOrigin>, PathAndQuery)> {
+    if location.starts_with("//") {
+        let uri = Uri::from_str(format!("http:{}", location).as_str()).ok()?;
+        let origin = RedirectOrigin::new(None, uri.authority()?.clone());
+        return Some((Some(origin), get_path_and_query(&uri)));
+    }
+
+    if location.starts_with('/') {
+        return Some((None, PathAndQuery::from_str(location).ok()?));
+    }
+
+    if let Ok(uri) = Uri::from_str(location) {
+        if let (Some(scheme), Some(authority)) = (uri.scheme(), uri.authority()) {
+            let origin = RedirectOrigin::new(Some(scheme.clone()), authority.clone());
+            return Some((Some(origin), get_path_and_query(&uri)));
+        }
+    }
+
+    // Relative reference: resolved against the directory of the current path
+    let current_path = match current_path_and_query.find('?') {
+        Some(pos) => &current_path_and_query[..pos],
+        None => current_path_and_query,
+    };
+
+    // Only the query changes
+    if location.starts_with('?') {
+        let path_and_query =
+            PathAndQuery::from_str(format!("{}{}", current_path, location).as_str());
+        return Some((None, path_and_query.ok()?));
+    }
+
+    let directory = match current_path.rfind('/') {
+        Some(pos) => &current_path[..pos + 1],
+        None => "/",
+    };
+
+    let path_and_query = PathAndQuery::from_str(format!("{}{}", directory, location).as_str());
+    Some((None, path_and_query.ok()?))
+}
+
+/// Resolves `.` and `..` segments of the path as RFC 3986 section 5.2.4 does.
+/// A `..` never climbs above the root.
+fn remove_dot_segments(path_and_query: &PathAndQuery) -> Option<PathAndQuery> {
+    let path = path_and_query.path();
+
+    if !path
+        .split('/')
+        .any(|segment| segment == "." || segment == "..")
+    {
+        return Some(path_and_query.clone());
+    }
+
+    let mut output: Vec<&str> = Vec::new();
+    let mut ends_with_slash = false;
+
+    // The path always starts with '/', so the first segment is empty
+    for segment in path.split('/').skip(1) {
+        ends_with_slash = false;
+
+        match segment {
+            "." => ends_with_slash = true,
+            ".." => {
+                output.pop();
+                ends_with_slash = true;
+            }
+            _ => output.push(segment),
+        }
+    }
+
+    let mut result = String::with_capacity(path_and_query.as_str().len());
+
+    for segment in output {
+        result.push('/');
+        result.push_str(segment);
+    }
+
+    if ends_with_slash || result.is_empty() {
+        result.push('/');
+    }
+
+    if let Some(query) = path_and_query.query() {
+        result.push('?');
+        result.push_str(query);
+    }
+
+    PathAndQuery::from_str(result.as_str()).ok()
+}
+
+fn get_path_and_query(uri: &Uri) -> PathAndQuery {
+    match uri.path_and_query() {
+        Some(path_and_query) if !path_and_query.as_str().is_empty() => path_and_query.clone(),
+        _ => PathAndQuery::from_static("/"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{HeaderMap, Method, StatusCode};
+
+    use super::RedirectStep;
+
+    fn step(status: u16, method: Method, location: &str, current: &str) -> Option<RedirectStep> {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::LOCATION, location.parse().unwrap());
+        RedirectStep::from_response(
+            StatusCode::from_u16(status).unwrap(),
+            &headers,
+            &method,
+            current,
+        )
+    }
+
+    #[test]
+    fn method_and_body_follow_status() {
+        let result = step(303, Method::POST, "/next", "/").unwrap();
+        assert_eq!(result.method, Method::GET);
+        assert!(!result.keep_body);
+
+        let result = step(302, Method::POST, "/next", "/").unwrap();
+        assert_eq!(result.method, Method::GET);
+        assert!(!result.keep_body);
+
+        let result = step(302, Method::PUT, "/next", "/").unwrap();
+        assert_eq!(result.method, Method::PUT);
+        assert!(result.keep_body);
+
+        let result = step(307, Method::POST, "/next", "/").unwrap();
+        assert_eq!(result.method, Method::POST);
+        assert!(result.keep_body);
+
+        assert!(step(304, Method::GET, "/next", "/").is_none());
+    }
+
+    #[test]
+    fn location_is_resolved() {
+        let result = step(301, Method::GET, "/a/b?x=1#frag", "/old").unwrap();
+        assert!(result.origin.is_none());
+        assert_eq!(result.path_and_query.as_str(), "/a/b?x=1");
+
+        let result = step(301, Method::GET, "c?y=2", "/a/b?x=1").unwrap();
+        assert_eq!(result.path_and_query.as_str(), "/a/c?y=2");
+
+        let result = step(301, Method::GET, "?y=2", "/a/b?x=1").unwrap();
+        assert_eq!(result.path_and_query.as_str(), "/a/b?y=2");
+
+        let result = step(301, Method::GET, "https://other.com:8443", "/").unwrap();
+        let origin = result.origin.unwrap();
+        assert_eq!(origin.scheme.unwrap().as_str(), "https");
+        assert_eq!(origin.authority.as_str(), "other.com:8443");
+        assert_eq!(result.path_and_query.as_str(), "/");
+
+        let result = step(301, Method::GET, "//cdn.com/file", "/").unwrap();
+        let origin = result.origin.unwrap();
+        assert!(origin.scheme.is_none());
+        assert_eq!(origin.authority.as_str(), "cdn.com");
+        assert_eq!(result.path_and_query.as_str(), "/file");
+    }
+
+    #[test]
+    fn dot_segments_are_removed() {
+        // RFC 3986 section 5.4 examples, base path /b/c/d;p
+        for (location, expected) in [
+            ("./g", "/b/c/g"),
+            ("g/", "/b/c/g/"),
+            (".", "/b/c/"),
+            ("./", "/b/c/"),
+            ("..", "/b/"),
+            ("../g", "/b/g"),
+            ("../..", "/"),
+            ("../../../g", "/g"),
+            ("/./g", "/g"),
+            ("/../g", "/g"),
+            ("g.", "/b/c/g."),
+            ("..g", "/b/c/..g"),
+            ("./../g", "/b/g"),
+            ("g/./h", "/b/c/g/h"),
+            ("g/../h", "/b/c/h"),
+            ("g;x=1/../y", "/b/c/y"),
+            ("../g?q=./..", "/b/g?q=./.."),
+        ] {
+            let result = step(301, Method::GET, location, "/b/c/d;p?q").unwrap();
+            assert_eq!(result.path_and_query.as_str(), expected, "{}", location);
+        }
+
+        let result = step(301, Method::GET, "https://other.com/a/./b/../c", "/").unwrap();
+        assert_eq!(result.path_and_query.as_str(), "/a/c");
+    }
+
+    #[test]
+    fn same_origin_uses_default_ports() {
+        use super::RedirectOrigin;
+
+        let current = RedirectOrigin::from_host_port("example.com:443").unwrap();
+
+        let target = step(301, Method::GET, "https://EXAMPLE.com/x", "/")
+            .unwrap()
+            .origin
+            .unwrap();
+        assert!(current.is_same_origin(&target));
+
+        let target = step(301, Method::GET, "http://example.com/x", "/")
+            .unwrap()
+            .origin
+            .unwrap();
+        assert!(!current.is_same_origin(&target));
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use http::{Method, StatusCode};
+
+use super::{RetryPolicy, RetryReason};
+
+/// Retries with a delay doubling after every attempt.
+///
+/// Errors the request did not reach the upstream with are retried for any
+/// method. Timeouts, failures after the request was sent and `retry_statuses`
+/// are retried for idempotent methods only, unless `retry_non_idempotent` is
+/// set. A `Retry-After` of the response replaces the computed delay.
+#[derive(Debug, Clone)]
+pub struct ExponentialBackoffRetryPolicy {
+    /// Attempts including the first one
+    pub max_attempts: usize,
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Picks each delay at random between half of the backoff and the full backoff,
+    /// so clients failing together do not retry together
+    pub jitter: bool,
+    pub retry_statuses: Vec<StatusCode>,
+    /// A response asking to wait longer than this is returned to the caller
+    pub max_retry_after: Duration,
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for ExponentialBackoffRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            retry_statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            max_retry_after: Duration::from_secs(30),
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl ExponentialBackoffRetryPolicy {
+    pub fn get_backoff(&self, attempt: usize) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31) as u32;
+        let backoff = self
+            .initial_delay
+            .saturating_mul(2u32.saturating_pow(exp))
+            .min(self.max_delay);
+
+        if !self.jitter {
+            return backoff;
+        }
+
+        let half = backoff / 2;
+        let random = RandomState::new().build_hasher().finish();
+        half + Duration::from_nanos(random % (half.as_nanos() as u64).max(1))
+    }
+}
+
+impl RetryPolicy for ExponentialBackoffRetryPolicy {
+    fn get_retry_delay(
+        &self,
+        method: &Method,
+        attempt: usize,
+        reason: &RetryReason<'_>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        if !reason.request_is_not_sent() {
+            if !reason.request_may_be_executed() {
+                return None;
+            }
+
+            if !method.is_idempotent() && !self.retry_non_idempotent {
+                return None;
+            }
+        }
+
+        match reason {
+            RetryReason::Error(_) => Some(self.get_backoff(attempt)),
+            RetryReason::Status {
+                status,
+                retry_after,
+            } => {
+                if !self.retry_statuses.contains(status) {
+                    return None;
+                }
+
+                match retry_after {
+                    Some(retry_after) if *retry_after > self.max_retry_after => None,
+                    Some(retry_after) => Some(*retry_after),
+                    None => Some(self.get_backoff(attempt)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::{Method, StatusCode};
+
+    use super::ExponentialBackoffRetryPolicy;
+    use crate::{
+        retry::{RetryPolicy, RetryReason},
+        MyHttpClientError,
+    };
+
+    fn create_policy() -> ExponentialBackoffRetryPolicy {
+        ExponentialBackoffRetryPolicy {
+            max_attempts: 4,
+            jitter: false,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_delay() {
+        let policy = ExponentialBackoffRetryPolicy {
+            max_delay: Duration::from_millis(300),
+            ..create_policy()
+        };
+
+        assert_eq!(policy.get_backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.get_backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.get_backoff(3), Duration::from_millis(300));
+        assert_eq!(policy.get_backoff(100), Duration::from_millis(300));
+
+        let policy = ExponentialBackoffRetryPolicy {
+            jitter: true,
+            ..policy
+        };
+
+        for _ in 0..100 {
+            let backoff = policy.get_backoff(2);
+            assert!(backoff >= Duration::from_millis(100));
+            assert!(backoff <= Duration::from_millis(200));
+        }
+    }
+
+    #[test]
+    fn non_idempotent_request_is_retried_only_if_not_sent() {
+        let policy = create_policy();
+
+        let not_sent = MyHttpClientError::CanNotConnectToRemoteHost("refused".to_string());
+        let timeout = MyHttpClientError::RequestTimeout(Duration::from_secs(1));
+
+        let delay = policy.get_retry_delay(&Method::POST, 1, &RetryReason::Error(&not_sent));
+        assert_eq!(delay, Some(Duration::from_millis(100)));
+
+        let delay = policy.get_retry_delay(&Method::POST, 1, &RetryReason::Error(&timeout));
+        assert_eq!(delay, None);
+
+        let delay = policy.get_retry_delay(&Method::GET, 1, &RetryReason::Error(&timeout));
+        assert_eq!(delay, Some(Duration::from_millis(100)));
+
+        let delay = policy.get_retry_delay(&Method::GET, 4, &RetryReason::Error(&timeout));
+        assert_eq!(delay, None);
+
+        let delay = policy.get_retry_delay(
+            &Method::POST,
+            1,
+            &RetryReason::Error(&MyHttpClientError::RequestNotSent),
+        );
+        assert_eq!(delay, Some(Duration::from_millis(100)));
+
+        // Written before the connection was lost
+        let delay = policy.get_retry_delay(
+            &Method::POST,
+            1,
+            &RetryReason::Error(&MyHttpClientError::Disconnected),
+        );
+        assert_eq!(delay, None);
+
+        let disposed = MyHttpClientError::Disposed;
+        let delay = policy.get_retry_delay(&Method::GET, 1, &RetryReason::Error(&disposed));
+        assert_eq!(delay, None);
+    }
+
+    #[test]
+    fn status_is_retried_with_retry_after() {
+        let policy = create_policy();
+
+        let reason = RetryReason::Status {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            retry_after: Some(Duration::from_secs(2)),
+        };
+        let delay = policy.get_retry_delay(&Method::GET, 1, &reason);
+        assert_eq!(delay, Some(Duration::from_secs(2)));
+
+        let reason = RetryReason::Status {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            retry_after: Some(Duration::from_secs(60)),
+        };
+        assert_eq!(policy.get_retry_delay(&Method::GET, 1, &reason), None);
+
+        let reason = RetryReason::Status {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            retry_after: None,
+        };
+        assert_eq!(policy.get_retry_delay(&Method::GET, 1, &reason), None);
+    }
+}
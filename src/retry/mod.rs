@@ -0,0 +1,6 @@
+mod retry_policy;
+pub use retry_policy::*;
+mod exponential_backoff_retry_policy;
+pub use exponential_backoff_retry_policy::*;
+mod retry_after;
+pub use retry_after::*;
@@ -0,0 +1,74 @@
+use std::time::{Duration, SystemTime};
+
+use http::{header, HeaderMap, Method, StatusCode};
+
+use super::{RetryPolicy, RetryReason};
+
+/// `Retry-After` of a response, given either in seconds or as an HTTP date.
+/// A date in the past gives a zero delay.
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(header::RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    )
+}
+
+/// Delay before repeating a request that got a response with `status` and
+/// `headers`, or `None` if the response goes to the caller.
+pub(crate) fn get_response_retry_delay(
+    retry_policy: &(dyn RetryPolicy + Send + Sync + 'static),
+    method: &Method,
+    attempt: usize,
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> Option<Duration> {
+    if status.is_success() || status.is_redirection() || status.is_informational() {
+        return None;
+    }
+
+    let reason = RetryReason::Status {
+        status,
+        retry_after: parse_retry_after(headers),
+    };
+
+    retry_policy.get_retry_delay(method, attempt, &reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use http::{HeaderMap, HeaderValue};
+
+    use super::parse_retry_after;
+
+    #[test]
+    fn retry_after_in_seconds_and_as_date() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(parse_retry_after(&headers), None);
+
+        headers.insert("retry-after", HeaderValue::from_static("120"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(120)));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        headers.insert("retry-after", HeaderValue::from_str(date.as_str()).unwrap());
+        let delay = parse_retry_after(&headers).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+
+        headers.insert(
+            "retry-after",
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+
+        headers.insert("retry-after", HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+}
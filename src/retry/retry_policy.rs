@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use http::{Method, StatusCode};
+
+use crate::MyHttpClientError;
+
+/// Why an attempt of a request failed.
+#[derive(Debug)]
+pub enum RetryReason<'s> {
+    Error(&'s MyHttpClientError),
+    Status {
+        status: StatusCode,
+        /// Parsed `Retry-After` header of the response
+        retry_after: Option<Duration>,
+    },
+}
+
+impl RetryReason<'_> {
+    /// The request did not reach the upstream, so repeating it is safe for any method.
+    pub fn request_is_not_sent(&self) -> bool {
+        matches!(
+            self,
+            RetryReason::Error(MyHttpClientError::CanNotConnectToRemoteHost(_))
+                | RetryReason::Error(MyHttpClientError::RequestNotSent)
+        )
+    }
+
+    /// The upstream may have started executing the request, so repeating it is
+    /// safe only for idempotent methods.
+    pub fn request_may_be_executed(&self) -> bool {
+        match self {
+            RetryReason::Error(err) => matches!(
+                err,
+                MyHttpClientError::RequestTimeout(_)
+                    | MyHttpClientError::CanNotExecuteRequest(_)
+                    | MyHttpClientError::Disconnected
+            ),
+            RetryReason::Status { .. } => true,
+        }
+    }
+}
+
+/// Decides whether and when a failed attempt of `do_request` is repeated.
+///
+/// Consulted after every attempt that failed with an error or returned a
+/// response with a status the caller would rather not see. Retries happen on top
+/// of the reconnects each client does on its own for requests that never reached
+/// the wire, and that [`crate::http1::MyHttpClient`] also does for idempotent
+/// requests that lost their connection. Requests with a streamed body are never
+/// retried.
+pub trait RetryPolicy {
+    /// Delay before the next attempt after attempt number `attempt` (the first
+    /// one is `1`) of a `method` request failed for `reason`, or `None` to give
+    /// the failure to the caller.
+    fn get_retry_delay(
+        &self,
+        method: &Method,
+        attempt: usize,
+        reason: &RetryReason<'_>,
+    ) -> Option<Duration>;
+}
@@ -37,3 +37,39 @@ pub fn into_body(
         .body(full_body.map_err(|itm| itm.to_string()).boxed())
         .unwrap()
 }
+
+/// Reads and discards the body of a response that is not handed to the caller,
+/// so the connection can take the next request. Gives up silently after `timeout`.
+pub async fn drain_body(response: crate::HyperResponse, timeout: std::time::Duration) {
+    let mut body = response.into_body();
+
+    let drain = async { while let Some(Ok(_)) = body.frame().await {} };
+
+    let _ = tokio::time::timeout(timeout, drain).await;
+}
+
+/// Standard base64 with padding, e.g. for `Basic` credentials.
+pub(crate) fn encode_base64(src: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::with_capacity(src.len().div_ceil(3) * 4);
+
+    for chunk in src.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}